@@ -0,0 +1,81 @@
+use crate::venvs::VirtualEnv;
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::fs::{create_dir_all, rename, File};
+use std::path::{Component, Path, PathBuf};
+
+/// zstd level; favours speed since environments can be gigabytes
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Archive file name unique to the environment's location and the current time,
+/// e.g. `home_me_code_app_.venv-20240102T030405.tar.zst`
+pub fn get_archive_name(venv_path: &Path) -> String {
+    let flattened = venv_path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("_");
+    format!(
+        "{}-{}.tar.zst",
+        flattened,
+        Local::now().format("%Y%m%dT%H%M%S")
+    )
+}
+
+/// Writes `venv` to a `.tar.zst` in `dir`, storing its absolute path (minus the
+/// leading `/`) so `tar --zstd -xf ARCHIVE -C /` puts it back where it was
+pub fn archive_venv(venv: &VirtualEnv, dir: &Path) -> Result<PathBuf> {
+    create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let archive_path = dir.join(get_archive_name(&venv.path));
+    // Only move the archive into place once it is complete
+    let partial_path = archive_path.with_extension("zst.partial");
+
+    let file = File::create(&partial_path)
+        .with_context(|| format!("Failed to create {}", partial_path.display()))?;
+    let encoder = zstd::Encoder::new(file, COMPRESSION_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+    builder.follow_symlinks(false);
+    let name_in_archive = venv.path.strip_prefix("/").unwrap_or(&venv.path);
+    builder
+        .append_dir_all(name_in_archive, &venv.path)
+        .with_context(|| format!("Failed to archive {}", venv.path.display()))?;
+    builder.into_inner()?.finish()?.sync_all()?;
+
+    rename(&partial_path, &archive_path)
+        .with_context(|| format!("Failed to write {}", archive_path.display()))?;
+    Ok(archive_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::write;
+
+    #[test]
+    pub fn test_archive_venv() {
+        let source = tempfile::tempdir().expect("Failed to create tempdir");
+        let archives = tempfile::tempdir().expect("Failed to create tempdir");
+        let venv_path = source.path().join(".venv");
+        create_dir_all(venv_path.join("bin")).unwrap();
+        write(venv_path.join("pyvenv.cfg"), "version = 3.12.1\n").unwrap();
+
+        let venv = VirtualEnv {
+            path: venv_path.clone(),
+            ..Default::default()
+        };
+        let archive_path = archive_venv(&venv, archives.path()).expect("Failed to archive");
+        assert!(archive_path.to_string_lossy().ends_with(".tar.zst"));
+
+        let decoder = zstd::Decoder::new(File::open(&archive_path).unwrap()).unwrap();
+        let names: Vec<PathBuf> = tar::Archive::new(decoder)
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().into_owned())
+            .collect();
+        let expected = venv_path.strip_prefix("/").unwrap().join("pyvenv.cfg");
+        assert!(names.contains(&expected));
+    }
+}
@@ -0,0 +1,44 @@
+use crate::history::get_data_dir;
+use crate::venvs::VirtualEnv;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// One line of the audit log: an environment venvpruner deleted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Local>,
+    #[serde(with = "crate::paths")]
+    pub path: PathBuf,
+    pub size: u64,
+    pub python_version: String,
+    /// Where the environment was archived before deletion, if it was
+    #[serde(with = "crate::paths::option", default)]
+    pub archive: Option<PathBuf>,
+}
+
+/// `~/.local/share/venvpruner/audit.jsonl`
+pub fn get_audit_path() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("audit.jsonl"))
+}
+
+pub fn record_deletion(venv: &VirtualEnv, archive: Option<PathBuf>) -> Result<()> {
+    let entry = AuditEntry {
+        timestamp: Local::now(),
+        path: venv.path.clone(),
+        size: venv.venv_size,
+        python_version: venv.python_version.clone(),
+        archive,
+    };
+    let path = get_audit_path()?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
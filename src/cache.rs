@@ -0,0 +1,48 @@
+use crate::venvs::VirtualEnv;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local};
+use dirs::cache_dir;
+use serde::{Deserialize, Serialize};
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::PathBuf;
+
+/// The result of the most recent local scan
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScanCache {
+    pub timestamp: DateTime<Local>,
+    pub venvs: Vec<VirtualEnv>,
+}
+
+/// Directory for venvpruner's regenerable state (`~/.cache/venvpruner`)
+pub fn get_cache_dir() -> Result<PathBuf> {
+    let dir = cache_dir()
+        .ok_or_else(|| anyhow!("Could not find cache directory"))?
+        .join("venvpruner");
+    create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+fn get_scan_cache_path() -> Result<PathBuf> {
+    Ok(get_cache_dir()?.join("last_scan.json"))
+}
+
+pub fn load_last_scan() -> Result<Option<ScanCache>> {
+    let path = get_scan_cache_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents =
+        read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    // An unreadable cache (e.g. from an incompatible version) is as good as none
+    Ok(serde_json::from_str(&contents).ok())
+}
+
+pub fn save_scan(venvs: &[VirtualEnv]) -> Result<()> {
+    let path = get_scan_cache_path()?;
+    let cache = ScanCache {
+        timestamp: Local::now(),
+        venvs: venvs.to_vec(),
+    };
+    write(&path, serde_json::to_string(&cache)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,150 @@
+use crate::venvs::get_dir_size;
+use anyhow::{anyhow, Context, Result};
+use dirs::{cache_dir, home_dir};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A package/download cache kept by a Python tool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheDir {
+    pub tool: &'static str,
+    pub path: PathBuf,
+}
+
+/// Returns the well-known Python tool caches that exist on this machine
+pub fn get_cache_dirs() -> Vec<CacheDir> {
+    let mut candidates: Vec<(&'static str, PathBuf)> = Vec::new();
+
+    // ~/.cache on Linux, ~/Library/Caches on macOS, %LOCALAPPDATA% on Windows
+    if let Some(cache_dir) = cache_dir() {
+        candidates.extend([
+            ("pip", cache_dir.join("pip")),
+            ("uv", cache_dir.join("uv")),
+            ("poetry", cache_dir.join("pypoetry/cache")),
+            ("poetry", cache_dir.join("pypoetry/artifacts")),
+            ("pipenv", cache_dir.join("pipenv")),
+            ("pdm", cache_dir.join("pdm")),
+        ]);
+    }
+
+    if let Some(home_dir) = home_dir() {
+        for conda_root in [
+            ".conda",
+            "anaconda3",
+            "miniconda3",
+            "miniforge3",
+            "mambaforge",
+        ] {
+            candidates.push(("conda", home_dir.join(conda_root).join("pkgs")));
+        }
+    }
+
+    candidates
+        .into_iter()
+        .filter(|(_, path)| path.is_dir())
+        .map(|(tool, path)| CacheDir { tool, path })
+        .collect()
+}
+
+/// A tool's own command for emptying its cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cleaner {
+    pub tool: &'static str,
+    pub program: PathBuf,
+    pub args: Vec<String>,
+}
+
+impl Cleaner {
+    /// The command line, for showing before running it
+    pub fn command_line(&self) -> String {
+        let mut words = vec![self.program.display().to_string()];
+        words.extend(self.args.iter().cloned());
+        words.join(" ")
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let status = Command::new(&self.program)
+            .args(&self.args)
+            .status()
+            .with_context(|| format!("Failed to run {}", self.command_line()))?;
+        match status.success() {
+            true => Ok(()),
+            false => Err(anyhow!("{} failed with {}", self.command_line(), status)),
+        }
+    }
+}
+
+/// Finds `name` on `PATH`
+fn find_program(name: &str) -> Option<PathBuf> {
+    let path_var = env::var_os("PATH")?;
+    env::split_paths(&path_var)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Poetry clears one named cache at a time
+fn get_poetry_caches(program: &Path) -> Vec<String> {
+    let output = match Command::new(program).args(["cache", "list"]).output() {
+        Ok(output) if output.status.success() => output,
+        _ => return vec![],
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.contains(' '))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The cache-clean commands of the Python tools installed on this machine
+pub fn get_cleaners() -> Vec<Cleaner> {
+    let tools: [(&'static str, &[&str], &[&str]); 6] = [
+        ("pip", &["pip", "pip3"], &["cache", "purge"]),
+        ("uv", &["uv"], &["cache", "clean"]),
+        (
+            "poetry",
+            &["poetry"],
+            &["cache", "clear", "--all", "--no-interaction"],
+        ),
+        ("conda", &["conda", "mamba"], &["clean", "--all", "--yes"]),
+        ("pipenv", &["pipenv"], &["--clear"]),
+        ("pdm", &["pdm"], &["cache", "clear"]),
+    ];
+    let mut cleaners = Vec::new();
+    for (tool, programs, args) in tools {
+        let program = match programs.iter().find_map(|program| find_program(program)) {
+            Some(program) => program,
+            None => continue,
+        };
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        match tool {
+            "poetry" => {
+                cleaners.extend(
+                    get_poetry_caches(&program)
+                        .into_iter()
+                        .map(|cache| Cleaner {
+                            tool,
+                            program: program.clone(),
+                            args: [args.clone(), vec![cache]].concat(),
+                        }),
+                )
+            }
+            _ => cleaners.push(Cleaner {
+                tool,
+                program,
+                args,
+            }),
+        }
+    }
+    cleaners
+}
+
+/// Total size of the cache directories of `tool`
+pub fn get_tool_cache_size(tool: &str) -> u64 {
+    get_cache_dirs()
+        .iter()
+        .filter(|cache| cache.tool == tool)
+        .map(|cache| get_dir_size(&cache.path))
+        .sum()
+}
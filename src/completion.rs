@@ -0,0 +1,85 @@
+use crate::cache::load_last_scan;
+use crate::size::format_size;
+use crate::venvs::VirtualEnv;
+use clap_complete::CompletionCandidate;
+use std::ffi::OsStr;
+use std::path::{is_separator, Path, PathBuf};
+
+/// Environments from `venvs` matching the word being completed: by path
+/// prefix, or by name when the word has no `/` in it
+pub fn matching_venvs<'a>(venvs: &'a [VirtualEnv], current: &OsStr) -> Vec<&'a VirtualEnv> {
+    let current = current.as_encoded_bytes();
+    let by_name = !current.iter().any(|&byte| is_separator(byte as char));
+    venvs
+        .iter()
+        .filter(|venv| {
+            venv.path
+                .as_os_str()
+                .as_encoded_bytes()
+                .starts_with(current)
+                || (by_name && venv.name.as_bytes().starts_with(current))
+        })
+        .collect()
+}
+
+/// Completes environment paths from the cached scan, so completion never
+/// waits for a filesystem walk. Nothing is offered before the first scan.
+pub fn complete_venv_paths(current: &OsStr) -> Vec<CompletionCandidate> {
+    // `~/` is left unexpanded in the word being completed
+    let rest = match current.as_encoded_bytes().starts_with(b"~/") {
+        true => Path::new(current).strip_prefix("~").ok(),
+        false => None,
+    };
+    let (current, home) = match (rest, dirs::home_dir()) {
+        (Some(rest), Some(home)) => (home.join(rest), Some(home)),
+        _ => (PathBuf::from(current), None),
+    };
+    let venvs = match load_last_scan() {
+        Ok(Some(cache)) => cache.venvs,
+        _ => return vec![],
+    };
+    matching_venvs(&venvs, current.as_os_str())
+        .into_iter()
+        .map(|venv| {
+            let value = match home.as_deref().map(|home| venv.path.strip_prefix(home)) {
+                Some(Ok(relative)) => PathBuf::from("~").join(relative),
+                _ => venv.path.clone(),
+            };
+            CompletionCandidate::new(value).help(Some(
+                format!("{} ({})", venv.manager, format_size(venv.venv_size)).into(),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_matching_venvs() {
+        let venvs = vec![
+            VirtualEnv {
+                name: "torch-env".to_string(),
+                path: PathBuf::from("/home/me/.virtualenvs/torch-env"),
+                ..Default::default()
+            },
+            VirtualEnv {
+                name: ".venv".to_string(),
+                path: PathBuf::from("/home/me/code/api/.venv"),
+                ..Default::default()
+            },
+        ];
+        let paths = |current: &str| -> Vec<&str> {
+            matching_venvs(&venvs, OsStr::new(current))
+                .iter()
+                .map(|venv| venv.path.to_str().unwrap())
+                .collect()
+        };
+        assert_eq!(paths("tor"), vec!["/home/me/.virtualenvs/torch-env"]);
+        assert_eq!(paths("/home/me/code"), vec!["/home/me/code/api/.venv"]);
+        assert_eq!(paths(""), paths("/"));
+        // Names only match words without a slash
+        assert!(paths("code/tor").is_empty());
+    }
+}
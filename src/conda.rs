@@ -0,0 +1,283 @@
+use crate::manager::Manager;
+use crate::venvs::{is_venv_root, VirtualEnv};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::fs::{canonicalize, read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The environments listed in `~/.conda/environments.txt`, where conda
+/// records every environment it creates, including `--prefix` ones outside
+/// the usual `envs` directories.
+///
+/// The base installation is listed too but skipped: deleting it would
+/// remove conda itself.
+pub fn get_registered_envs(home_dir: &Path) -> Vec<PathBuf> {
+    let contents = match read_to_string(home_dir.join(".conda/environments.txt")) {
+        Ok(contents) => contents,
+        Err(_) => return vec![],
+    };
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| canonicalize(line).ok())
+        .filter(|path| is_prunable_env(path))
+        .collect()
+}
+
+/// An environment other than a base installation, which holds conda itself
+fn is_prunable_env(path: &Path) -> bool {
+    is_venv_root(path) && !path.join("condabin").is_dir()
+}
+
+/// The output of `conda env list --json`
+#[derive(Debug, Deserialize)]
+struct EnvList {
+    envs: Vec<PathBuf>,
+}
+
+/// The environments `conda env list --json` reports, for `--query-conda`.
+/// conda is usually a shell function, so the `$CONDA_EXE` it sets is tried
+/// before `conda` on the PATH; nothing is found if neither runs.
+pub fn get_listed_envs() -> Vec<PathBuf> {
+    let conda = env::var_os("CONDA_EXE")
+        .filter(|exe| !exe.is_empty())
+        .unwrap_or_else(|| "conda".into());
+    let output = match Command::new(&conda)
+        .args(["env", "list", "--json"])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return vec![],
+    };
+    parse_env_list(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_env_list(json: &str) -> Vec<PathBuf> {
+    match serde_json::from_str::<EnvList>(json) {
+        Ok(list) => list
+            .envs
+            .iter()
+            .filter_map(|path| canonicalize(path).ok())
+            .filter(|path| is_prunable_env(path))
+            .collect(),
+        Err(err) => {
+            eprintln!("Failed to parse conda env list: {}", err);
+            vec![]
+        }
+    }
+}
+
+/// Where conda is configured to create named environments besides the
+/// default `envs` directories: `$CONDA_ENVS_PATH` / `$CONDA_ENVS_DIRS` and
+/// the `envs_dirs` of the user's and system `.condarc` files
+pub fn get_conda_envs_dirs(home_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = ["CONDA_ENVS_PATH", "CONDA_ENVS_DIRS"]
+        .into_iter()
+        .filter_map(env::var_os)
+        .flat_map(|value| env::split_paths(&value).collect::<Vec<_>>())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .collect();
+
+    let mut condarcs = vec![
+        home_dir.join(".condarc"),
+        home_dir.join(".conda/.condarc"),
+        home_dir.join(".conda/condarc"),
+        home_dir.join(".config/conda/.condarc"),
+        home_dir.join(".config/conda/condarc"),
+        PathBuf::from("/etc/conda/.condarc"),
+        PathBuf::from("/etc/conda/condarc"),
+    ];
+    condarcs.extend(env::var_os("CONDARC").map(PathBuf::from));
+    for condarc in condarcs {
+        if let Ok(contents) = read_to_string(&condarc) {
+            dirs.extend(parse_envs_dirs(&contents));
+        }
+    }
+    // `~` is the home directory of the user whose .condarc this is
+    dirs.into_iter()
+        .map(|dir| match dir.strip_prefix("~") {
+            Ok(rest) => home_dir.join(rest),
+            Err(_) => dir,
+        })
+        .collect()
+}
+
+/// Reads the `envs_dirs` list of a `.condarc`, in block (`- dir` lines) or
+/// flow (`[dir, dir]`) style; the rest of the YAML is ignored
+fn parse_envs_dirs(condarc: &str) -> Vec<PathBuf> {
+    let unquote = |value: &str| {
+        value
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'')
+            .to_string()
+    };
+    let mut dirs = Vec::new();
+    let mut lines = condarc.lines().peekable();
+    while let Some(line) = lines.next() {
+        let value = match line.strip_prefix("envs_dirs:") {
+            Some(value) => value.split(" #").next().unwrap_or_default().trim(),
+            None => continue,
+        };
+        if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            dirs.extend(list.split(',').map(unquote).filter(|dir| !dir.is_empty()));
+            continue;
+        }
+        while let Some(item) = lines.peek().map(|line| line.trim()) {
+            match item.strip_prefix("- ") {
+                Some(dir) => dirs.push(unquote(dir)),
+                None if item.is_empty() || item.starts_with('#') => {}
+                None => break,
+            }
+            lines.next();
+        }
+    }
+    dirs.into_iter().map(PathBuf::from).collect()
+}
+
+/// The explicit package set of a conda environment: one
+/// `name-version-build` entry per record in `conda-meta`
+pub fn get_conda_spec(env_root: &Path) -> Option<BTreeSet<String>> {
+    let spec: BTreeSet<String> = read_dir(env_root.join("conda-meta"))
+        .ok()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.strip_suffix(".json").map(String::from)
+        })
+        .collect();
+    (!spec.is_empty()).then_some(spec)
+}
+
+/// Marks conda environments whose package set is identical to a more
+/// recently modified one (typically left over from `conda create --clone`)
+/// as clones of it
+pub fn mark_clones(venvs: &mut [VirtualEnv]) {
+    let mut groups: HashMap<BTreeSet<String>, Vec<usize>> = HashMap::new();
+    for (i, venv) in venvs.iter().enumerate() {
+        if !matches!(venv.manager, Manager::Conda | Manager::Mamba) {
+            continue;
+        }
+        if let Some(spec) = get_conda_spec(&venv.path) {
+            groups.entry(spec).or_default().push(i);
+        }
+    }
+
+    for mut members in groups.into_values().filter(|members| members.len() > 1) {
+        members.sort_by_key(|&i| std::cmp::Reverse(venvs[i].modified));
+        let newest: PathBuf = venvs[members[0]].path.clone();
+        for &i in &members[1..] {
+            venvs[i].clone_of = Some(newest.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Local};
+    use std::fs::{create_dir_all, write};
+
+    #[test]
+    pub fn test_parse_envs_dirs() {
+        let condarc = "\
+channels:
+  - conda-forge
+envs_dirs:
+  - ~/conda-envs
+  # scratch space
+  - \"/data/conda envs\"
+pkgs_dirs:
+  - /data/pkgs
+";
+        assert_eq!(
+            parse_envs_dirs(condarc),
+            vec![
+                PathBuf::from("~/conda-envs"),
+                PathBuf::from("/data/conda envs")
+            ]
+        );
+        assert_eq!(
+            parse_envs_dirs("envs_dirs: [/a, '/b']  # two\n"),
+            vec![PathBuf::from("/a"), PathBuf::from("/b")]
+        );
+        assert!(parse_envs_dirs("channels: [defaults]\n").is_empty());
+    }
+
+    #[test]
+    pub fn test_get_registered_envs() {
+        let home = tempfile::tempdir().expect("Failed to create tempdir");
+        let home = canonicalize(home.path()).unwrap();
+        for env in ["miniconda3", "miniconda3/envs/ml", "scratch/analysis"] {
+            create_dir_all(home.join(env).join("conda-meta")).unwrap();
+            write(home.join(env).join("pyvenv.cfg"), "").unwrap();
+        }
+        create_dir_all(home.join("miniconda3/condabin")).unwrap();
+        create_dir_all(home.join(".conda")).unwrap();
+        write(
+            home.join(".conda/environments.txt"),
+            format!(
+                "{0}/miniconda3\n{0}/miniconda3/envs/ml\n\n{0}/scratch/analysis\n{0}/deleted\n",
+                home.display()
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(
+            get_registered_envs(&home),
+            vec![
+                home.join("miniconda3/envs/ml"),
+                home.join("scratch/analysis")
+            ]
+        );
+        assert!(get_registered_envs(&home.join("nowhere")).is_empty());
+
+        let json = serde_json::json!({
+            "envs": [
+                home.join("miniconda3"),
+                home.join("miniconda3/envs/ml"),
+                home.join("deleted"),
+            ]
+        });
+        assert_eq!(
+            parse_env_list(&json.to_string()),
+            vec![home.join("miniconda3/envs/ml")]
+        );
+    }
+
+    #[test]
+    pub fn test_mark_clones() {
+        let root = tempfile::tempdir().expect("Failed to create tempdir");
+        let now = Local::now();
+        let mut venvs = Vec::new();
+        for (name, packages, days_old) in [
+            ("base", vec!["python-3.11.5-h1", "numpy-1.26.0-py311"], 30),
+            (
+                "base-copy",
+                vec!["python-3.11.5-h1", "numpy-1.26.0-py311"],
+                1,
+            ),
+            ("other", vec!["python-3.12.0-h2"], 1),
+        ] {
+            let path = root.path().join(name);
+            create_dir_all(path.join("conda-meta")).unwrap();
+            write(path.join("conda-meta/history"), "").unwrap();
+            for package in packages {
+                write(path.join(format!("conda-meta/{}.json", package)), "{}").unwrap();
+            }
+            venvs.push(VirtualEnv {
+                path,
+                manager: Manager::Conda,
+                modified: Some(now - Duration::days(days_old)),
+                ..Default::default()
+            });
+        }
+
+        mark_clones(&mut venvs);
+        assert_eq!(venvs[0].clone_of, Some(root.path().join("base-copy")));
+        assert_eq!(venvs[1].clone_of, None);
+        assert_eq!(venvs[2].clone_of, None);
+    }
+}
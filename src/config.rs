@@ -0,0 +1,306 @@
+use crate::hooks::HooksConfig;
+use crate::notify::NotifyConfig;
+use crate::rules::Age;
+use crate::size::{ByteSize, Units};
+use crate::slim::SlimConfig;
+use crate::telemetry::TelemetryConfig;
+use crate::venvs::VirtualEnv;
+use crate::SortKey;
+use anyhow::{anyhow, Context, Result};
+use chrono::{Duration, Local};
+use dirs::home_dir;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+/// Settings read from `~/.config/venvpruner/config.toml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Warn when all environments together use more than this
+    pub budget: Option<ByteSize>,
+    /// Limits on the environments inside individual project directories
+    pub budgets: Vec<ProjectBudget>,
+    /// Where `watch` sends alerts and prune reports
+    pub notify: NotifyConfig,
+    /// Settings for `slim --aggressive`
+    pub slim: SlimConfig,
+    /// Guards against deleting too much at once
+    pub safety: SafetyConfig,
+    /// When listings color environments as aging or stale
+    pub staleness: StalenessConfig,
+    /// Commands run before and after deleting each environment
+    pub hooks: HooksConfig,
+    /// Default for `--units`
+    pub units: Option<Units>,
+    /// Project directories searched on every run, as with `--project-root`
+    pub project_roots: Vec<PathBuf>,
+    /// Extra search roots, as with `--path`
+    pub paths: Vec<PathBuf>,
+    /// Patterns skipped while scanning project roots, added to any `--exclude`
+    pub excludes: Vec<String>,
+    /// How deep to walk below individual search or project roots
+    pub depths: HashMap<PathBuf, usize>,
+    /// Default for `--sort`
+    pub sort: Option<SortKey>,
+    /// Always ask conda for its environments, as with `--query-conda`
+    pub query_conda: bool,
+    /// Always find project environments through the filesystem index, as
+    /// with `--use-index`
+    pub use_index: bool,
+    /// Defaults for how environments are deleted
+    pub delete: DeleteConfig,
+    /// Where opted-in usage reports are sent
+    pub telemetry: TelemetryConfig,
+}
+
+impl Config {
+    /// The configured project roots with `~` expanded
+    pub fn get_project_roots(&self) -> Vec<PathBuf> {
+        self.project_roots
+            .iter()
+            .map(|root| expand_home(root))
+            .collect()
+    }
+
+    /// The configured search roots with `~` expanded
+    pub fn get_search_paths(&self) -> Vec<PathBuf> {
+        self.paths.iter().map(|path| expand_home(path)).collect()
+    }
+
+    /// The configured walk depths, by root with `~` expanded
+    pub fn get_depths(&self) -> HashMap<PathBuf, usize> {
+        self.depths
+            .iter()
+            .map(|(root, depth)| (expand_home(root), *depth))
+            .collect()
+    }
+}
+
+/// The `[delete]` section of the config file; the command-line flags win
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DeleteConfig {
+    /// Default for `--archive`
+    pub archive: Option<PathBuf>,
+    /// Always delete as with `--nice-io`
+    pub nice_io: bool,
+}
+
+impl DeleteConfig {
+    /// The archive directory with `~` expanded
+    pub fn get_archive(&self) -> Option<PathBuf> {
+        self.archive.as_deref().map(expand_home)
+    }
+}
+
+/// The `[safety]` section of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SafetyConfig {
+    /// Deleting more than this requires typing a confirmation
+    pub typed_confirm_size: ByteSize,
+    /// Deleting more than this many environments requires typing a confirmation
+    pub typed_confirm_count: usize,
+    /// Environments modified more recently than this are never selected by a
+    /// policy or deleted with `--yes`; picking them interactively still works
+    pub min_age: Age,
+}
+
+impl Default for SafetyConfig {
+    fn default() -> SafetyConfig {
+        SafetyConfig {
+            typed_confirm_size: ByteSize(10_000_000_000),
+            typed_confirm_count: 20,
+            min_age: Age(Duration::days(7)),
+        }
+    }
+}
+
+impl SafetyConfig {
+    /// Whether deleting `venvs` is big enough to need a typed confirmation
+    pub fn needs_typed_confirm(&self, venvs: &[VirtualEnv]) -> bool {
+        let total_size: u64 = venvs.iter().map(|venv| venv.reclaimable_size()).sum();
+        total_size > self.typed_confirm_size.0 || venvs.len() > self.typed_confirm_count
+    }
+
+    /// Whether `venv` must not be deleted without someone picking it: it is
+    /// too new, or a scheduled job uses it
+    pub fn is_protected(&self, venv: &VirtualEnv) -> bool {
+        venv.scheduled_by.is_some()
+            || venv
+                .modified
+                .is_some_and(|modified| Local::now() - modified < self.min_age.0)
+    }
+}
+
+/// How long ago an environment was last used, for coloring
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Staleness {
+    Fresh,
+    Aging,
+    Stale,
+}
+
+/// The `[staleness]` section of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StalenessConfig {
+    /// Environments unused for longer than this are aging
+    pub aging: Age,
+    /// Environments unused for longer than this are stale
+    pub stale: Age,
+}
+
+impl Default for StalenessConfig {
+    fn default() -> StalenessConfig {
+        StalenessConfig {
+            aging: Age(Duration::days(30)),
+            stale: Age(Duration::days(180)),
+        }
+    }
+}
+
+impl StalenessConfig {
+    /// None when the environment's age is unknown
+    pub fn classify(&self, venv: &VirtualEnv) -> Option<Staleness> {
+        let age = Local::now() - venv.modified?;
+        match (age > self.stale.0, age > self.aging.0) {
+            (true, _) => Some(Staleness::Stale),
+            (false, true) => Some(Staleness::Aging),
+            (false, false) => Some(Staleness::Fresh),
+        }
+    }
+}
+
+/// A `[[budgets]]` entry: environments under `path` may use at most `max`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ProjectBudget {
+    pub path: PathBuf,
+    pub max: ByteSize,
+}
+
+/// Expands a leading `~` to the home directory
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), home_dir()) {
+        (Ok(rest), Some(home_dir)) => home_dir.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// `$XDG_CONFIG_HOME/venvpruner/config.toml`, defaulting to `~/.config`
+pub fn get_config_path() -> Result<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home_dir()
+            .ok_or_else(|| anyhow!("Could not find home directory"))?
+            .join(".config"),
+    };
+    Ok(config_home.join("venvpruner/config.toml"))
+}
+
+impl Config {
+    /// Loads the config file, or the defaults if there is none
+    pub fn load() -> Result<Config> {
+        let path = get_config_path()?;
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let contents =
+            read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut config: Config = toml::from_str(&contents)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        for budget in &mut config.budgets {
+            budget.path = expand_home(&budget.path);
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_config() {
+        let config: Config = toml::from_str(r#"budget = "40GB""#).expect("Failed to parse");
+        assert_eq!(config.budget, Some(ByteSize(40_000_000_000)));
+
+        let config: Config = toml::from_str("").expect("Failed to parse");
+        assert_eq!(config.budget, None);
+        assert!(config.budgets.is_empty());
+        assert_eq!(config.safety.typed_confirm_count, 20);
+
+        let config: Config = toml::from_str("[safety]\nmin_age = \"2w\"").expect("Failed to parse");
+        assert_eq!(config.safety.min_age, Age(Duration::days(14)));
+
+        let config: Config =
+            toml::from_str("[staleness]\nstale = \"1y\"").expect("Failed to parse");
+        assert_eq!(config.staleness.aging, Age(Duration::days(30)));
+        let venv = VirtualEnv {
+            modified: Some(Local::now() - Duration::days(200)),
+            ..Default::default()
+        };
+        assert_eq!(config.staleness.classify(&venv), Some(Staleness::Aging));
+        assert_eq!(config.staleness.classify(&VirtualEnv::default()), None);
+
+        let config: Config = toml::from_str(
+            r#"
+            [[budgets]]
+            path = "~/code/ml-experiments"
+            max = "10GB"
+            "#,
+        )
+        .expect("Failed to parse");
+        assert_eq!(config.budgets[0].max, ByteSize(10_000_000_000));
+
+        let config: Config = toml::from_str("[hooks]\npre_delete = \"backup-env.sh {path}\"")
+            .expect("Failed to parse");
+        assert_eq!(
+            config.hooks.pre_delete.as_deref(),
+            Some("backup-env.sh {path}")
+        );
+        assert_eq!(config.hooks.post_delete, None);
+
+        let config: Config = toml::from_str(r#"project_roots = ["~/code", "/srv/projects"]"#)
+            .expect("Failed to parse");
+        let roots = config.get_project_roots();
+        assert_eq!(roots[0], expand_home(Path::new("~/code")));
+        assert_eq!(roots[1], PathBuf::from("/srv/projects"));
+
+        let config: Config = toml::from_str(
+            r#"
+            paths = ["/mnt/data"]
+            excludes = ["legacy/"]
+            sort = "score"
+            query_conda = true
+
+            [delete]
+            archive = "~/venv-archive"
+            nice_io = true
+            "#,
+        )
+        .expect("Failed to parse");
+        assert_eq!(config.get_search_paths(), vec![PathBuf::from("/mnt/data")]);
+        assert_eq!(config.excludes, vec!["legacy/"]);
+        assert_eq!(config.sort, Some(SortKey::Score));
+        assert!(config.query_conda);
+        assert_eq!(
+            config.delete.get_archive(),
+            Some(expand_home(Path::new("~/venv-archive")))
+        );
+        assert!(config.delete.nice_io);
+        assert!(!Config::default().delete.nice_io);
+        assert_eq!(Config::default().telemetry.url, None);
+
+        let config: Config =
+            toml::from_str("[telemetry]\nurl = \"https://stats.example.org/venvpruner\"")
+                .expect("Failed to parse");
+        assert_eq!(
+            config.telemetry.url.as_deref(),
+            Some("https://stats.example.org/venvpruner")
+        );
+    }
+}
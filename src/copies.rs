@@ -0,0 +1,200 @@
+use crate::venvs::{is_venv_root, VirtualEnv};
+use std::collections::HashMap;
+use std::fs::{canonicalize, read_link, read_to_string, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Where the environment at `venv_root` was created: the target of the
+/// `command` line in `pyvenv.cfg` (Python 3.11+), or the path baked into
+/// `bin/activate`. Copying or moving the directory leaves both unchanged.
+pub fn get_origin(venv_root: &Path) -> Option<PathBuf> {
+    let from_cfg = read_to_string(venv_root.join("pyvenv.cfg"))
+        .ok()
+        .and_then(|contents| parse_pyvenv_command(&contents));
+    from_cfg.or_else(|| {
+        read_to_string(venv_root.join("bin/activate"))
+            .ok()
+            .and_then(|contents| parse_activate(&contents))
+    })
+}
+
+/// `command = /usr/bin/python3 -m venv /home/me/app/.venv` names the
+/// environment last
+fn parse_pyvenv_command(contents: &str) -> Option<PathBuf> {
+    contents
+        .lines()
+        .find_map(|line| line.strip_prefix("command = "))
+        .and_then(|command| command.split_whitespace().last())
+        .filter(|path| path.starts_with('/'))
+        .map(PathBuf::from)
+}
+
+/// `VIRTUAL_ENV="/home/me/app/.venv"`, quoted either way or not at all
+fn parse_activate(contents: &str) -> Option<PathBuf> {
+    contents
+        .lines()
+        .filter_map(|line| line.strip_prefix("VIRTUAL_ENV="))
+        .map(|value| value.trim().trim_matches(|c| c == '"' || c == '\''))
+        .find(|value| value.starts_with('/'))
+        .map(PathBuf::from)
+}
+
+/// Whether two directory trees hold the same entries with the same contents
+pub fn is_identical(a: &Path, b: &Path) -> bool {
+    let walk = |root: &Path| {
+        WalkDir::new(root)
+            .follow_links(false)
+            .sort_by_file_name()
+            .into_iter()
+    };
+    let mut left = walk(a);
+    let mut right = walk(b);
+    loop {
+        let (left, right) = match (left.next(), right.next()) {
+            (None, None) => return true,
+            (Some(Ok(left)), Some(Ok(right))) => (left, right),
+            _ => return false,
+        };
+        let same_entry = left.path().strip_prefix(a).ok() == right.path().strip_prefix(b).ok()
+            && left.file_type() == right.file_type();
+        if !same_entry {
+            return false;
+        }
+        let same_contents = match left.file_type() {
+            file_type if file_type.is_file() => is_same_file_contents(left.path(), right.path()),
+            file_type if file_type.is_symlink() => {
+                read_link(left.path()).ok() == read_link(right.path()).ok()
+            }
+            _ => true,
+        };
+        if !same_contents {
+            return false;
+        }
+    }
+}
+
+fn is_same_file_contents(a: &Path, b: &Path) -> bool {
+    let open = |path: &Path| File::open(path).map(BufReader::new);
+    let (mut a, mut b) = match (open(a), open(b)) {
+        (Ok(a), Ok(b)) => (a, b),
+        _ => return false,
+    };
+    let mut left = [0u8; 64 * 1024];
+    let mut right = [0u8; 64 * 1024];
+    loop {
+        let read = match a.read(&mut left) {
+            Ok(read) => read,
+            Err(_) => return false,
+        };
+        if read == 0 {
+            // Both must end together
+            return b.read(&mut right[..1]).is_ok_and(|read| read == 0);
+        }
+        if b.read_exact(&mut right[..read]).is_err() || left[..read] != right[..read] {
+            return false;
+        }
+    }
+}
+
+/// Marks environments that are wholesale copies of another one, which are
+/// safe to remove: those created at a different location that still holds an
+/// environment, and those byte-identical to a more recently modified one
+pub fn mark_copies(venvs: &mut [VirtualEnv]) {
+    for venv in venvs.iter_mut().filter(|venv| venv.clone_of.is_none()) {
+        let origin = match get_origin(&venv.path) {
+            Some(origin) => canonicalize(&origin).unwrap_or(origin),
+            None => continue,
+        };
+        // A moved environment is the only one left, so it is not a copy
+        if origin != venv.path && is_venv_root(&origin) {
+            venv.clone_of = Some(origin);
+        }
+    }
+
+    // Only environments of exactly the same size can be identical
+    let mut groups: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, venv) in venvs.iter().enumerate() {
+        if venv.clone_of.is_none() && venv.venv_size > 0 {
+            groups.entry(venv.venv_size).or_default().push(i);
+        }
+    }
+    for mut members in groups.into_values().filter(|members| members.len() > 1) {
+        members.sort_by_key(|&i| std::cmp::Reverse(venvs[i].modified));
+        for (n, &i) in members.iter().enumerate() {
+            let original = members[..n]
+                .iter()
+                .find(|&&newer| {
+                    venvs[newer].clone_of.is_none()
+                        && is_identical(&venvs[newer].path, &venvs[i].path)
+                })
+                .map(|&newer| venvs[newer].path.clone());
+            venvs[i].clone_of = original;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+
+    #[test]
+    pub fn test_mark_copies() {
+        assert_eq!(
+            parse_pyvenv_command(
+                "home = /usr/bin\ncommand = /usr/bin/python3 -m venv --prompt app /srv/app/.venv\n"
+            ),
+            Some(PathBuf::from("/srv/app/.venv"))
+        );
+        assert_eq!(
+            parse_activate(
+                "deactivate nondestructive\nVIRTUAL_ENV='/srv/app/venv'\nexport VIRTUAL_ENV\n"
+            ),
+            Some(PathBuf::from("/srv/app/venv"))
+        );
+
+        let root = tempfile::tempdir().expect("Failed to create tempdir");
+        let root = canonicalize(root.path()).unwrap();
+        let make = |name: &str, origin: &str, extra: &str| {
+            let path = root.join(name);
+            create_dir_all(path.join("bin")).unwrap();
+            write(path.join("bin/python"), "").unwrap();
+            write(
+                path.join("pyvenv.cfg"),
+                format!(
+                    "command = /usr/bin/python3 -m venv {}\n",
+                    root.join(origin).display()
+                ),
+            )
+            .unwrap();
+            write(path.join("extra"), extra).unwrap();
+            VirtualEnv {
+                venv_size: 100,
+                path,
+                ..Default::default()
+            }
+        };
+        let mut venvs = vec![
+            make("app", "app", "a"),
+            // `cp -r app app-backup`
+            make("app-backup", "app", "a"),
+            // Created in its own right, and the same size as the others
+            make("other", "other", "b"),
+            // `mv gone moved`: the original no longer exists
+            make("moved", "gone", "c"),
+        ];
+        mark_copies(&mut venvs);
+        assert_eq!(venvs[0].clone_of, None);
+        assert_eq!(venvs[1].clone_of, Some(root.join("app")));
+        assert_eq!(venvs[2].clone_of, None);
+        assert_eq!(venvs[3].clone_of, None);
+
+        // Byte-identical trees
+        let copy = make("copy", "app", "a");
+        write(root.join("app/pyvenv.cfg"), "").unwrap();
+        write(copy.path.join("pyvenv.cfg"), "").unwrap();
+        assert!(is_identical(&root.join("app"), &copy.path));
+        assert!(!is_identical(&root.join("app"), &root.join("other")));
+    }
+}
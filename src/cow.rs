@@ -0,0 +1,164 @@
+use crate::venvs::VirtualEnv;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A copy-on-write filesystem, where deleting a file only frees the blocks
+/// no snapshot or reflinked copy still refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub enum CowFilesystem {
+    Btrfs,
+    Xfs,
+    Zfs,
+    Bcachefs,
+}
+
+impl fmt::Display for CowFilesystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CowFilesystem::Btrfs => write!(f, "btrfs"),
+            CowFilesystem::Xfs => write!(f, "XFS"),
+            CowFilesystem::Zfs => write!(f, "ZFS"),
+            CowFilesystem::Bcachefs => write!(f, "bcachefs"),
+        }
+    }
+}
+
+/// The copy-on-write filesystem holding `path`, if it is on one.
+///
+/// Always None on platforms other than Linux; APFS is handled by `apfs`.
+#[cfg(target_os = "linux")]
+pub fn get_cow_filesystem(path: &Path) -> Option<CowFilesystem> {
+    use std::os::unix::ffi::OsStrExt;
+
+    const BTRFS_SUPER_MAGIC: u32 = 0x9123_683e;
+    const XFS_SUPER_MAGIC: u32 = 0x5846_5342;
+    const ZFS_SUPER_MAGIC: u32 = 0x2fc1_2fc1;
+    const BCACHEFS_SUPER_MAGIC: u32 = 0xca45_1a4e;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `statfs` is plain old data
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid out pointer
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // The width and signedness of f_type vary between architectures
+    match stat.f_type as u32 {
+        BTRFS_SUPER_MAGIC => Some(CowFilesystem::Btrfs),
+        XFS_SUPER_MAGIC => Some(CowFilesystem::Xfs),
+        ZFS_SUPER_MAGIC => Some(CowFilesystem::Zfs),
+        BCACHEFS_SUPER_MAGIC => Some(CowFilesystem::Bcachefs),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_cow_filesystem(_path: &Path) -> Option<CowFilesystem> {
+    None
+}
+
+/// The root of the btrfs subvolume containing `path`: the closest ancestor
+/// with inode 256, which every subvolume root has
+#[cfg(unix)]
+pub fn get_btrfs_subvolume(path: &Path) -> Option<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+    const BTRFS_FIRST_FREE_OBJECTID: u64 = 256;
+
+    path.ancestors()
+        .find(|ancestor| {
+            ancestor
+                .metadata()
+                .is_ok_and(|metadata| metadata.ino() == BTRFS_FIRST_FREE_OBJECTID)
+        })
+        .map(Path::to_path_buf)
+}
+
+#[cfg(not(unix))]
+pub fn get_btrfs_subvolume(_path: &Path) -> Option<PathBuf> {
+    None
+}
+
+/// Bytes referenced only by the subvolume at `path`, from its quota group.
+/// Needs quotas enabled and usually root, so mostly None.
+pub fn get_btrfs_exclusive(subvolume: &Path) -> Option<u64> {
+    let output = Command::new("btrfs")
+        .args(["qgroup", "show", "-f", "--raw"])
+        .arg(subvolume)
+        .output()
+        .ok()?;
+    match output.status.success() {
+        true => parse_qgroup_exclusive(&String::from_utf8_lossy(&output.stdout)),
+        false => None,
+    }
+}
+
+/// Reads the exclusive column of the first row of `btrfs qgroup show --raw`
+fn parse_qgroup_exclusive(output: &str) -> Option<u64> {
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with("--"))
+        .nth(1)?
+        .split_whitespace()
+        .nth(2)?
+        .parse()
+        .ok()
+}
+
+/// The environments on one copy-on-write filesystem or btrfs subvolume
+#[derive(Debug)]
+pub struct CowUsage {
+    pub filesystem: CowFilesystem,
+    pub subvolume: Option<PathBuf>,
+    pub venvs: usize,
+    pub size: u64,
+    /// Bytes exclusive to the subvolume, when btrfs quotas report it
+    pub exclusive: Option<u64>,
+}
+
+/// Groups the environments living on copy-on-write filesystems by filesystem
+/// and btrfs subvolume
+pub fn get_cow_usage(venvs: &[VirtualEnv]) -> Vec<CowUsage> {
+    let mut groups: BTreeMap<(CowFilesystem, Option<PathBuf>), (usize, u64)> = BTreeMap::new();
+    for venv in venvs {
+        let filesystem = match get_cow_filesystem(&venv.path) {
+            Some(filesystem) => filesystem,
+            None => continue,
+        };
+        let subvolume = match filesystem {
+            CowFilesystem::Btrfs => get_btrfs_subvolume(&venv.path),
+            _ => None,
+        };
+        let entry = groups.entry((filesystem, subvolume)).or_default();
+        entry.0 += 1;
+        entry.1 += venv.venv_size;
+    }
+    groups
+        .into_iter()
+        .map(|((filesystem, subvolume), (venvs, size))| CowUsage {
+            exclusive: subvolume.as_deref().and_then(get_btrfs_exclusive),
+            filesystem,
+            subvolume,
+            venvs,
+            size,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_qgroup_exclusive() {
+        let output = "\
+Qgroupid    Referenced    Exclusive   Path
+--------    ----------    ---------   ----
+0/257       5368709120    1073741824  home
+";
+        assert_eq!(parse_qgroup_exclusive(output), Some(1_073_741_824));
+        assert_eq!(parse_qgroup_exclusive("ERROR: quotas not enabled"), None);
+    }
+}
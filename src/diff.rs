@@ -0,0 +1,153 @@
+use crate::size::format_size;
+use crate::venvs::VirtualEnv;
+use console::style;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Growth below this many bytes is never reported, whatever the percentage
+const MIN_GROWTH_BYTES: u64 = 1024 * 1024;
+
+/// Differences between two scans
+#[derive(Debug, Default)]
+pub struct ScanDiff {
+    pub added: Vec<VirtualEnv>,
+    pub removed: Vec<VirtualEnv>,
+    /// (previous, current) pairs of environments that grew past the threshold
+    pub grown: Vec<(VirtualEnv, VirtualEnv)>,
+}
+
+impl ScanDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.grown.is_empty()
+    }
+}
+
+/// Compares two scans, reporting environments that grew by at least
+/// `growth_percent` percent.
+pub fn diff_scans(
+    previous: &[VirtualEnv],
+    current: &[VirtualEnv],
+    growth_percent: f64,
+) -> ScanDiff {
+    let previous_by_path: HashMap<&PathBuf, &VirtualEnv> =
+        previous.iter().map(|venv| (&venv.path, venv)).collect();
+    let current_by_path: HashMap<&PathBuf, &VirtualEnv> =
+        current.iter().map(|venv| (&venv.path, venv)).collect();
+
+    let mut diff = ScanDiff::default();
+    for venv in current {
+        match previous_by_path.get(&venv.path) {
+            None => diff.added.push(venv.clone()),
+            Some(old) => {
+                let growth = venv.venv_size.saturating_sub(old.venv_size);
+                let threshold = old.venv_size as f64 * growth_percent / 100.0;
+                if growth >= MIN_GROWTH_BYTES && growth as f64 >= threshold {
+                    diff.grown.push(((*old).clone(), venv.clone()));
+                }
+            }
+        }
+    }
+    diff.removed = previous
+        .iter()
+        .filter(|venv| !current_by_path.contains_key(&venv.path))
+        .cloned()
+        .collect();
+
+    diff.added
+        .sort_by_key(|venv| std::cmp::Reverse(venv.venv_size));
+    diff.removed
+        .sort_by_key(|venv| std::cmp::Reverse(venv.venv_size));
+    diff.grown
+        .sort_by_key(|(old, new)| std::cmp::Reverse(new.venv_size - old.venv_size));
+    diff
+}
+
+pub fn print_diff(diff: &ScanDiff) {
+    if diff.is_empty() {
+        println!("{}", style("No changes since the previous scan.").cyan());
+        return;
+    }
+
+    if !diff.added.is_empty() {
+        println!("{}", style("New environments").bold());
+        for venv in &diff.added {
+            println!("  {} {}", style("+").green(), venv);
+        }
+    }
+    if !diff.removed.is_empty() {
+        println!("{}", style("Removed environments").bold());
+        for venv in &diff.removed {
+            println!("  {} {}", style("-").red(), venv);
+        }
+    }
+    if !diff.grown.is_empty() {
+        println!("{}", style("Grown environments").bold());
+        for (old, new) in &diff.grown {
+            println!(
+                "  {} {} {} -> {} (+{})",
+                style("^").yellow(),
+                new.path.display(),
+                format_size(old.venv_size),
+                format_size(new.venv_size),
+                format_size(new.venv_size - old.venv_size)
+            );
+        }
+    }
+
+    let removed_total: u64 = diff.removed.iter().map(|venv| venv.venv_size).sum();
+    let added_total: u64 = diff.added.iter().map(|venv| venv.venv_size).sum();
+    let grown_total: u64 = diff
+        .grown
+        .iter()
+        .map(|(old, new)| new.venv_size - old.venv_size)
+        .sum();
+    println!();
+    println!(
+        "{}",
+        style(format!(
+            "New: +{}, grown: +{}, removed: -{}",
+            format_size(added_total),
+            format_size(grown_total),
+            format_size(removed_total)
+        ))
+        .cyan()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn venv(path: &str, venv_size: u64) -> VirtualEnv {
+        VirtualEnv {
+            path: PathBuf::from(path),
+            name: path.rsplit('/').next().unwrap().to_string(),
+            python_path: PathBuf::from(path).join("bin/python"),
+            python_version: "3.11.7".to_string(),
+            venv_size,
+            venv_size_str: crate::size::format_human(venv_size),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    pub fn test_diff_scans() {
+        const MB: u64 = 1024 * 1024;
+        let previous = vec![
+            venv("/envs/kept", 100 * MB),
+            venv("/envs/grown", 100 * MB),
+            venv("/envs/gone", 50 * MB),
+        ];
+        let current = vec![
+            venv("/envs/kept", 105 * MB),
+            venv("/envs/grown", 8000 * MB),
+            venv("/envs/new", 10 * MB),
+        ];
+
+        let diff = diff_scans(&previous, &current, 10.0);
+        assert_eq!(diff.added, vec![venv("/envs/new", 10 * MB)]);
+        assert_eq!(diff.removed, vec![venv("/envs/gone", 50 * MB)]);
+        assert_eq!(diff.grown.len(), 1);
+        assert_eq!(diff.grown[0].1.path, PathBuf::from("/envs/grown"));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::packages::{normalize_name, Package};
+use console::style;
+use std::collections::BTreeMap;
+
+/// Differences between the packages of two environments
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PackageDiff {
+    /// Only in the second environment
+    pub added: Vec<Package>,
+    /// Only in the first environment
+    pub removed: Vec<Package>,
+    /// (first, second) pairs of the same package at different versions
+    pub changed: Vec<(Package, Package)>,
+}
+
+impl PackageDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compares two package sets by normalized name
+pub fn diff_packages(a: &[Package], b: &[Package]) -> PackageDiff {
+    let a_by_name: BTreeMap<String, &Package> = a
+        .iter()
+        .map(|package| (normalize_name(&package.name), package))
+        .collect();
+    let b_by_name: BTreeMap<String, &Package> = b
+        .iter()
+        .map(|package| (normalize_name(&package.name), package))
+        .collect();
+
+    let mut diff = PackageDiff::default();
+    for (name, package) in &a_by_name {
+        match b_by_name.get(name) {
+            None => diff.removed.push((*package).clone()),
+            Some(other) if other.version != package.version => {
+                diff.changed.push(((*package).clone(), (*other).clone()))
+            }
+            Some(_) => {}
+        }
+    }
+    diff.added = b_by_name
+        .iter()
+        .filter(|(name, _)| !a_by_name.contains_key(*name))
+        .map(|(_, package)| (*package).clone())
+        .collect();
+    diff
+}
+
+pub fn print_package_diff(diff: &PackageDiff, a_label: &str, b_label: &str) {
+    if diff.is_empty() {
+        println!(
+            "{}",
+            style("Both environments have identical packages; either one can go.").cyan()
+        );
+        return;
+    }
+
+    if !diff.removed.is_empty() {
+        println!("{}", style(format!("Only in {}", a_label)).bold());
+        for package in &diff.removed {
+            println!(
+                "  {} {} {}",
+                style("-").red(),
+                package.name,
+                package.version
+            );
+        }
+    }
+    if !diff.added.is_empty() {
+        println!("{}", style(format!("Only in {}", b_label)).bold());
+        for package in &diff.added {
+            println!(
+                "  {} {} {}",
+                style("+").green(),
+                package.name,
+                package.version
+            );
+        }
+    }
+    if !diff.changed.is_empty() {
+        println!("{}", style("Different versions").bold());
+        for (a, b) in &diff.changed {
+            println!(
+                "  {} {} {} -> {}",
+                style("~").yellow(),
+                a.name,
+                a.version,
+                b.version
+            );
+        }
+    }
+
+    println!();
+    println!(
+        "{}",
+        style(format!(
+            "{} only in the first, {} only in the second, {} at different versions",
+            diff.removed.len(),
+            diff.added.len(),
+            diff.changed.len()
+        ))
+        .cyan()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    pub fn test_diff_packages() {
+        let a = vec![
+            package("numpy", "1.26.0"),
+            package("Typing_Extensions", "4.8.0"),
+            package("six", "1.16.0"),
+        ];
+        let b = vec![
+            package("numpy", "2.0.0"),
+            package("typing-extensions", "4.8.0"),
+            package("torch", "2.3.0"),
+        ];
+
+        let diff = diff_packages(&a, &b);
+        assert_eq!(diff.removed, vec![package("six", "1.16.0")]);
+        assert_eq!(diff.added, vec![package("torch", "2.3.0")]);
+        assert_eq!(
+            diff.changed,
+            vec![(package("numpy", "1.26.0"), package("numpy", "2.0.0"))]
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use crate::excludes::{ExcludeMatcher, KNOWN_IRRELEVANT_DIRS};
+use crate::manager::Manager;
+use crate::privacy::BlockedDirs;
+use crate::timings::{self, Phase};
+use crate::venvs::{
+    get_created, get_dir_usage, get_modified, is_venv_root, ScanOptions, VirtualEnv, KEEP_MARKER,
+};
+use anyhow::Result;
+use rayon::prelude::*;
+use std::fs::canonicalize;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// How deep to walk below a root looking for build and dependency directories
+const ECOSYSTEM_SCAN_DEPTH: usize = 6;
+
+/// Development caches outside Python that `--ecosystem` adds to scans
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Ecosystem {
+    /// `node_modules` next to a `package.json`
+    Node,
+    /// Rust `target/` directories next to a `Cargo.toml`
+    Cargo,
+    /// The Gradle cache in `~/.gradle/caches`
+    Gradle,
+}
+
+impl Ecosystem {
+    pub fn manager(self) -> Manager {
+        match self {
+            Ecosystem::Node => Manager::Node,
+            Ecosystem::Cargo => Manager::Cargo,
+            Ecosystem::Gradle => Manager::Gradle,
+        }
+    }
+
+    /// Whether `dir` is this ecosystem's dependency or build directory
+    fn matches(self, dir: &Path) -> bool {
+        let (name, parent) = match (dir.file_name(), dir.parent()) {
+            (Some(name), Some(parent)) => (name, parent),
+            _ => return false,
+        };
+        match self {
+            Ecosystem::Node => name == "node_modules" && parent.join("package.json").is_file(),
+            Ecosystem::Cargo => {
+                name == "target"
+                    && parent.join("Cargo.toml").is_file()
+                    && ["CACHEDIR.TAG", "debug", "release"]
+                        .iter()
+                        .any(|entry| dir.join(entry).exists())
+            }
+            // Lives at a fixed location instead
+            Ecosystem::Gradle => false,
+        }
+    }
+}
+
+/// Finds the directories of the ecosystems enabled in `options`: the Gradle
+/// cache of each home, and project directories below the project roots, or
+/// below the homes when there are none
+pub fn find_ecosystem_dirs(
+    options: &ScanOptions,
+    homes: &[PathBuf],
+) -> Result<Vec<(PathBuf, Manager)>> {
+    let mut found = Vec::new();
+    if options.ecosystems.contains(&Ecosystem::Gradle) {
+        for home in homes {
+            let caches = home.join(".gradle/caches");
+            if let Ok(caches) = canonicalize(caches) {
+                found.push((caches, Manager::Gradle));
+            }
+        }
+    }
+
+    let walked: Vec<Ecosystem> = options
+        .ecosystems
+        .iter()
+        .copied()
+        .filter(|ecosystem| *ecosystem != Ecosystem::Gradle)
+        .collect();
+    if walked.is_empty() {
+        return Ok(found);
+    }
+    let roots = match options.project_roots.is_empty() {
+        true => homes.to_vec(),
+        false => options.project_roots.clone(),
+    };
+    let blocked = BlockedDirs::default();
+    for root in roots {
+        let root = match canonicalize(&root) {
+            Ok(root) => root,
+            Err(_) => continue,
+        };
+        // Known irrelevant trees are what we are looking for here
+        let matcher = ExcludeMatcher::new(&root, &options.excludes, false)?;
+        let start = std::time::Instant::now();
+        let mut walker = WalkDir::new(&root)
+            .follow_links(false)
+            .max_depth(ECOSYSTEM_SCAN_DEPTH)
+            .into_iter()
+            .filter_entry(|entry| {
+                let hidden = entry.file_name().as_encoded_bytes().starts_with(b".");
+                entry.depth() == 0
+                    || (entry.file_type().is_dir()
+                        && !hidden
+                        && !matcher.is_excluded(entry.path(), true))
+            });
+        while let Some(entry) = walker.next() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    blocked.note(&err);
+                    continue;
+                }
+            };
+            let path = entry.path();
+            if let Some(ecosystem) = walked.iter().find(|ecosystem| ecosystem.matches(path)) {
+                found.push((path.to_path_buf(), ecosystem.manager()));
+                walker.skip_current_dir();
+            } else if is_venv_root(path)
+                || entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|name| KNOWN_IRRELEVANT_DIRS.contains(&name))
+            {
+                walker.skip_current_dir();
+            }
+        }
+        timings::record(Phase::Walk, Some(&root), start.elapsed());
+    }
+    blocked.report();
+    Ok(found)
+}
+
+/// Describes a dependency or build directory like an environment, so it goes
+/// through the same listing, selection and deletion
+pub fn build_ecosystem_dir(path: PathBuf, manager: Manager) -> VirtualEnv {
+    // Named after the project it belongs to
+    let name = match manager {
+        Manager::Gradle => "gradle caches".to_string(),
+        _ => path
+            .parent()
+            .and_then(Path::file_name)
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy()
+            .into_owned(),
+    };
+    let usage = timings::time(Phase::Sizing, Some(&path), || get_dir_usage(&path));
+    VirtualEnv {
+        name,
+        venv_size: usage.size,
+        venv_size_str: crate::size::format_human(usage.size),
+        sparse_size: usage.sparse,
+        manager,
+        modified: get_modified(&path),
+        created: get_created(&path),
+        pinned: path.join(KEEP_MARKER).exists(),
+        path,
+        ..Default::default()
+    }
+}
+
+pub fn get_ecosystem_dirs(options: &ScanOptions, homes: &[PathBuf]) -> Result<Vec<VirtualEnv>> {
+    Ok(find_ecosystem_dirs(options, homes)?
+        .into_par_iter()
+        .map(|(path, manager)| build_ecosystem_dir(path, manager))
+        .inspect(crate::progress::env_found)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+
+    #[test]
+    pub fn test_find_ecosystem_dirs() {
+        let dir = tempfile::tempdir().expect("Failed to create tempdir");
+        let root = canonicalize(dir.path()).unwrap();
+        for project in [
+            "web",
+            "web/node_modules/left-pad",
+            "cli/target/debug",
+            "scratch/target",
+        ] {
+            create_dir_all(root.join(project)).unwrap();
+        }
+        write(root.join("web/package.json"), "{}").unwrap();
+        write(root.join("web/node_modules/left-pad/package.json"), "{}").unwrap();
+        write(root.join("cli/Cargo.toml"), "").unwrap();
+        create_dir_all(root.join(".gradle/caches")).unwrap();
+
+        let options = ScanOptions {
+            ecosystems: vec![Ecosystem::Node, Ecosystem::Cargo, Ecosystem::Gradle],
+            ..Default::default()
+        };
+        let mut found = find_ecosystem_dirs(&options, std::slice::from_ref(&root)).unwrap();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                (root.join(".gradle/caches"), Manager::Gradle),
+                (root.join("cli/target"), Manager::Cargo),
+                (root.join("web/node_modules"), Manager::Node),
+            ]
+        );
+
+        let venv = build_ecosystem_dir(root.join("web/node_modules"), Manager::Node);
+        assert_eq!(venv.name, "web");
+        assert!(!venv.is_broken());
+    }
+}
@@ -0,0 +1,173 @@
+use crate::config::expand_home;
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Directories that are expensive to walk and never hold Python environments
+pub const KNOWN_IRRELEVANT_DIRS: &[&str] = &[
+    "node_modules",
+    "bower_components",
+    ".git",
+    ".hg",
+    ".svn",
+    "target",
+    ".gradle",
+    ".next",
+    ".terraform",
+];
+
+/// Gitignore-style file of paths to skip, read from the home directory and
+/// from the top of each search path and project root
+pub const IGNORE_FILE_NAME: &str = ".venvprunerignore";
+
+/// Decides which paths a directory walk should skip, based on user supplied
+/// gitignore-style patterns and (optionally) the list of known irrelevant trees.
+pub struct ExcludeMatcher {
+    gitignore: Gitignore,
+    /// Patterns starting with `~/`, which match full paths wherever the walk starts
+    absolute: Gitignore,
+    skip_known_dirs: bool,
+}
+
+/// Anchors the `~/` patterns at `/`, so they match the full paths below
+/// the home directory
+fn build_absolute(patterns: &[String]) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new("/");
+    for pattern in patterns.iter().filter(|pattern| pattern.starts_with("~/")) {
+        let expanded = expand_home(Path::new(pattern));
+        builder
+            .add_line(None, &expanded.to_string_lossy())
+            .with_context(|| format!("Invalid exclude pattern: {}", pattern))?;
+    }
+    builder.build().context("Failed to build exclude patterns")
+}
+
+impl ExcludeMatcher {
+    /// Builds a matcher whose relative patterns are anchored at `root`,
+    /// including those of a `.venvprunerignore` file there
+    pub fn new(root: &Path, patterns: &[String], skip_known_dirs: bool) -> Result<Self> {
+        let mut builder = GitignoreBuilder::new(root);
+        let ignore_file = root.join(IGNORE_FILE_NAME);
+        if ignore_file.is_file() {
+            if let Some(err) = builder.add(&ignore_file) {
+                return Err(err).with_context(|| format!("Invalid {}", ignore_file.display()));
+            }
+        }
+        for pattern in patterns.iter().filter(|pattern| !pattern.starts_with("~/")) {
+            builder
+                .add_line(None, pattern)
+                .with_context(|| format!("Invalid exclude pattern: {}", pattern))?;
+        }
+        let gitignore = builder
+            .build()
+            .context("Failed to build exclude patterns")?;
+
+        Ok(ExcludeMatcher {
+            gitignore,
+            absolute: build_absolute(patterns)?,
+            skip_known_dirs,
+        })
+    }
+
+    /// A matcher for environments found without walking, e.g. registered
+    /// by conda or an IDE, where only the `~/` patterns apply
+    pub fn absolute_only(patterns: &[String]) -> Result<Self> {
+        Ok(ExcludeMatcher {
+            gitignore: Gitignore::empty(),
+            absolute: build_absolute(patterns)?,
+            skip_known_dirs: false,
+        })
+    }
+
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        if is_dir && self.skip_known_dirs {
+            let known = path
+                .file_name()
+                .and_then(OsStr::to_str)
+                .is_some_and(|name| KNOWN_IRRELEVANT_DIRS.contains(&name));
+            if known {
+                return true;
+            }
+        }
+
+        if path.has_root()
+            && self
+                .absolute
+                .matched_path_or_any_parents(path, is_dir)
+                .is_ignore()
+        {
+            return true;
+        }
+
+        // Paths outside the matcher's root can't match its patterns
+        if !path.starts_with(self.gitignore.path()) {
+            return false;
+        }
+
+        self.gitignore
+            .matched_path_or_any_parents(path, is_dir)
+            .is_ignore()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_exclude_patterns() {
+        let root = Path::new("/home/user/code");
+        let matcher = ExcludeMatcher::new(
+            root,
+            &["work-critical/".to_string(), "*.bak".to_string()],
+            false,
+        )
+        .expect("Failed to build matcher");
+
+        assert!(matcher.is_excluded(&root.join("work-critical"), true));
+        assert!(matcher.is_excluded(&root.join("work-critical/app/.venv"), true));
+        assert!(matcher.is_excluded(&root.join("old.bak"), true));
+        assert!(!matcher.is_excluded(&root.join("project/.venv"), true));
+        assert!(!matcher.is_excluded(Path::new("/elsewhere/work-critical"), true));
+    }
+
+    #[test]
+    pub fn test_home_patterns() {
+        let home = expand_home(Path::new("~"));
+        let patterns = ["~/Projects/work-critical/**".to_string()];
+        let matcher = ExcludeMatcher::new(&home.join(".virtualenvs"), &patterns, false)
+            .expect("Failed to build matcher");
+        assert!(matcher.is_excluded(&home.join("Projects/work-critical/app/.venv"), true));
+        assert!(!matcher.is_excluded(&home.join("Projects/app/.venv"), true));
+
+        let matcher = ExcludeMatcher::absolute_only(&patterns).expect("Failed to build matcher");
+        assert!(matcher.is_excluded(&home.join("Projects/work-critical/app"), true));
+        assert!(!matcher.is_excluded(&home.join(".virtualenvs/work-critical"), true));
+    }
+
+    #[test]
+    pub fn test_ignore_file() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        std::fs::write(
+            dir.path().join(IGNORE_FILE_NAME),
+            "# shared images\nkeep-*/\n",
+        )
+        .expect("Failed to write ignore file");
+        let matcher = ExcludeMatcher::new(dir.path(), &[], false).expect("Failed to build matcher");
+
+        assert!(matcher.is_excluded(&dir.path().join("keep-me/.venv"), true));
+        assert!(!matcher.is_excluded(&dir.path().join("scratch/.venv"), true));
+    }
+
+    #[test]
+    pub fn test_skip_known_dirs() {
+        let root = Path::new("/home/user/code");
+        let matcher = ExcludeMatcher::new(root, &[], true).expect("Failed to build matcher");
+        assert!(matcher.is_excluded(&root.join("web/node_modules"), true));
+        assert!(!matcher.is_excluded(&root.join("web/node_modules"), false));
+
+        let matcher = ExcludeMatcher::new(root, &[], false).expect("Failed to build matcher");
+        assert!(!matcher.is_excluded(&root.join("web/node_modules"), true));
+    }
+}
@@ -0,0 +1,69 @@
+use std::env;
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+
+/// Hatch's data directories: the default one, and `$HATCH_DATA_DIR`
+fn get_hatch_data_dirs(home_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![
+        home_dir.join(".local/share/hatch"),
+        home_dir.join("Library/Application Support/hatch"),
+    ];
+    if let Some(data_dir) = env::var_os("HATCH_DATA_DIR").filter(|dir| !dir.is_empty()) {
+        dirs.push(PathBuf::from(data_dir));
+    }
+    dirs
+}
+
+/// The per-project directories of Hatch's virtual environments.
+///
+/// Hatch nests environments as `env/virtual/<project>/<hash>/<env>`, one
+/// level deeper than the search paths are walked, so each project directory
+/// is searched on its own.
+pub fn get_hatch_project_dirs(home_dir: &Path) -> Vec<PathBuf> {
+    get_hatch_data_dirs(home_dir)
+        .into_iter()
+        .filter_map(|data_dir| read_dir(data_dir.join("env/virtual")).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .map(|entry| entry.path())
+        // `.pythons` holds the interpreters Hatch installed, not environments
+        .filter(|path| path.is_dir() && !is_hidden(path))
+        .collect()
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+/// The project of the Hatch environment at `virtual/<project>/<hash>/<env>`
+pub fn get_project_name(venv_root: &Path) -> Option<String> {
+    let project_dir = venv_root.parent()?.parent()?;
+    match project_dir.parent()?.file_name()?.to_str()? {
+        "virtual" => Some(project_dir.file_name()?.to_string_lossy().into_owned()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_hatch_layout() {
+        let home = tempfile::tempdir().expect("Failed to create temporary directory");
+        let virtual_dir = home.path().join(".local/share/hatch/env/virtual");
+        std::fs::create_dir_all(virtual_dir.join("webapp/Fk3xQ9aB/default")).unwrap();
+        std::fs::create_dir_all(virtual_dir.join(".pythons/3.12")).unwrap();
+
+        assert_eq!(
+            get_hatch_project_dirs(home.path()),
+            vec![virtual_dir.join("webapp")]
+        );
+        assert_eq!(
+            get_project_name(&virtual_dir.join("webapp/Fk3xQ9aB/default")).as_deref(),
+            Some("webapp")
+        );
+        assert_eq!(get_project_name(Path::new("/home/me/code/app/.venv")), None);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::manager::Manager;
+use crate::size::format_size;
+use crate::venvs::VirtualEnv;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use console::style;
+use dirs::data_local_dir;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+/// One line of the history file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum HistoryEntry {
+    /// Summary of a completed scan
+    Scan {
+        timestamp: DateTime<Local>,
+        count: usize,
+        total_size: u64,
+        by_manager: BTreeMap<Manager, u64>,
+    },
+    /// Environments deleted in one go
+    Prune {
+        timestamp: DateTime<Local>,
+        count: usize,
+        reclaimed: u64,
+    },
+}
+
+/// Directory for venvpruner's persistent state (`~/.local/share/venvpruner`)
+pub fn get_data_dir() -> Result<PathBuf> {
+    let dir = data_local_dir()
+        .ok_or_else(|| anyhow!("Could not find data directory"))?
+        .join("venvpruner");
+    create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+fn get_history_path() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("history.jsonl"))
+}
+
+fn append_entry(entry: &HistoryEntry) -> Result<()> {
+    let path = get_history_path()?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+pub fn record_scan(venvs: &[VirtualEnv]) -> Result<()> {
+    let mut by_manager = BTreeMap::new();
+    for venv in venvs {
+        *by_manager.entry(venv.manager).or_default() += venv.venv_size;
+    }
+    append_entry(&HistoryEntry::Scan {
+        timestamp: Local::now(),
+        count: venvs.len(),
+        total_size: venvs.iter().map(|venv| venv.venv_size).sum(),
+        by_manager,
+    })
+}
+
+pub fn record_prune(deleted: &[VirtualEnv]) -> Result<()> {
+    if deleted.is_empty() {
+        return Ok(());
+    }
+    append_entry(&HistoryEntry::Prune {
+        timestamp: Local::now(),
+        count: deleted.len(),
+        reclaimed: deleted.iter().map(|venv| venv.reclaimable_size()).sum(),
+    })
+}
+
+pub fn load_history() -> Result<Vec<HistoryEntry>> {
+    let path = get_history_path()?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let file = File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect())
+}
+
+/// Usage on one day: the last scan of the day plus everything pruned that day
+#[derive(Debug, Default, PartialEq)]
+pub struct DailySummary {
+    pub count: usize,
+    pub total_size: u64,
+    pub by_manager: BTreeMap<Manager, u64>,
+    pub reclaimed: u64,
+}
+
+/// Groups history entries newer than `days` days by calendar day
+pub fn summarize_by_day(entries: &[HistoryEntry], days: i64) -> BTreeMap<NaiveDate, DailySummary> {
+    let since = Local::now() - Duration::days(days);
+    let mut summaries: BTreeMap<NaiveDate, DailySummary> = BTreeMap::new();
+
+    for entry in entries {
+        match entry {
+            HistoryEntry::Scan {
+                timestamp,
+                count,
+                total_size,
+                by_manager,
+            } if *timestamp >= since => {
+                let summary = summaries.entry(timestamp.date_naive()).or_default();
+                summary.count = *count;
+                summary.total_size = *total_size;
+                summary.by_manager = by_manager.clone();
+            }
+            HistoryEntry::Prune {
+                timestamp,
+                reclaimed,
+                ..
+            } if *timestamp >= since => {
+                summaries
+                    .entry(timestamp.date_naive())
+                    .or_default()
+                    .reclaimed += reclaimed;
+            }
+            _ => {}
+        }
+    }
+    summaries
+}
+
+const SPARK_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Width of the longest bar in the `--chart` view
+const CHART_WIDTH: usize = 40;
+
+/// Renders values as a one-line sparkline scaled between their min and max
+pub fn render_sparkline(values: &[u64]) -> String {
+    let (min, max) = match (values.iter().min(), values.iter().max()) {
+        (Some(min), Some(max)) => (*min, *max),
+        _ => return String::new(),
+    };
+    let range = (max - min).max(1) as f64;
+    values
+        .iter()
+        .map(|value| {
+            let level = ((value - min) as f64 / range * (SPARK_CHARS.len() - 1) as f64).round();
+            SPARK_CHARS[level as usize]
+        })
+        .collect()
+}
+
+/// Draws a horizontal bar per scanned day, scaled to the largest total
+pub fn print_chart(entries: &[HistoryEntry], days: i64) {
+    let summaries = summarize_by_day(entries, days);
+    let max = summaries
+        .values()
+        .map(|summary| summary.total_size)
+        .max()
+        .unwrap_or(0);
+    if max == 0 {
+        println!(
+            "{}",
+            style("No history recorded yet; it is collected on every scan.").cyan()
+        );
+        return;
+    }
+
+    for (date, summary) in &summaries {
+        let width = (summary.total_size as f64 / max as f64 * CHART_WIDTH as f64).round() as usize;
+        let mut line = format!(
+            "{} {}{} {:>10}",
+            date.format("%Y-%m-%d"),
+            style("█".repeat(width)).cyan(),
+            " ".repeat(CHART_WIDTH - width),
+            format_size(summary.total_size)
+        );
+        if summary.reclaimed > 0 {
+            line.push_str(&format!(
+                "  {}",
+                style(format!("-{} pruned", format_size(summary.reclaimed))).green()
+            ));
+        }
+        println!("{}", line);
+    }
+}
+
+pub fn print_history(entries: &[HistoryEntry], days: i64) {
+    let summaries = summarize_by_day(entries, days);
+    if summaries.is_empty() {
+        println!(
+            "{}",
+            style("No history recorded yet; it is collected on every scan.").cyan()
+        );
+        return;
+    }
+
+    println!(
+        "{}",
+        style(format!(
+            "{:<12} {:>6} {:>12} {:>12}  {}",
+            "Date", "Envs", "Total", "Reclaimed", "By manager"
+        ))
+        .bold()
+    );
+    for (date, summary) in &summaries {
+        let reclaimed = match summary.reclaimed {
+            0 => "-".to_string(),
+            reclaimed => format_size(reclaimed),
+        };
+        let mut by_manager: Vec<(&Manager, &u64)> = summary.by_manager.iter().collect();
+        by_manager.sort_by_key(|(_, size)| std::cmp::Reverse(**size));
+        let by_manager = by_manager
+            .iter()
+            .map(|(manager, size)| format!("{} {}", manager, format_size(**size)))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        // Days with only prunes have no scan totals
+        let (count, total) = match summary.count {
+            0 if summary.total_size == 0 => ("-".to_string(), "-".to_string()),
+            count => (count.to_string(), format_size(summary.total_size)),
+        };
+        println!(
+            "{:<12} {:>6} {:>12} {:>12}  {}",
+            date.format("%Y-%m-%d"),
+            count,
+            total,
+            reclaimed,
+            by_manager
+        );
+    }
+
+    let totals: Vec<u64> = summaries
+        .values()
+        .filter(|summary| summary.count > 0 || summary.total_size > 0)
+        .map(|summary| summary.total_size)
+        .collect();
+    let total_reclaimed: u64 = summaries.values().map(|summary| summary.reclaimed).sum();
+    println!();
+    // The sparkline's block characters mean nothing to a screen reader
+    if totals.len() > 1 && !crate::plain::is_plain() {
+        println!("Trend: {}", render_sparkline(&totals));
+    }
+    println!(
+        "{}",
+        style(format!(
+            "Space reclaimed by pruning in the last {} days: {}",
+            days,
+            format_size(total_reclaimed)
+        ))
+        .green()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_render_sparkline() {
+        assert_eq!(render_sparkline(&[]), "");
+        assert_eq!(render_sparkline(&[5, 5]), "▁▁");
+        assert_eq!(render_sparkline(&[0, 70, 35, 10]), "▁█▅▂");
+    }
+
+    #[test]
+    pub fn test_summarize_by_day() {
+        let now = Local::now();
+        let scan = |timestamp: DateTime<Local>, total_size: u64| HistoryEntry::Scan {
+            timestamp,
+            count: 2,
+            total_size,
+            by_manager: BTreeMap::from([(Manager::Poetry, total_size)]),
+        };
+        let entries = vec![
+            scan(now - Duration::days(400), 1),
+            scan(now, 100),
+            HistoryEntry::Prune {
+                timestamp: now,
+                count: 1,
+                reclaimed: 40,
+            },
+            scan(now, 60),
+        ];
+
+        let summaries = summarize_by_day(&entries, 30);
+        assert_eq!(summaries.len(), 1);
+        let today = &summaries[&now.date_naive()];
+        assert_eq!(today.total_size, 60);
+        assert_eq!(today.reclaimed, 40);
+    }
+}
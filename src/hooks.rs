@@ -0,0 +1,108 @@
+use crate::remote::{shell_quote, shell_quote_os};
+use crate::size::format_size;
+use crate::venvs::VirtualEnv;
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::process::Command;
+
+/// The `[hooks]` section of the config file: shell commands run for every
+/// deleted environment
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Runs before deleting; if it fails, the environment is kept
+    pub pre_delete: Option<String>,
+    /// Runs after the environment is deleted
+    pub post_delete: Option<String>,
+}
+
+/// Replaces `{path}`, `{name}`, `{size}`, `{bytes}` and `{manager}` in
+/// `template` with the shell-quoted values for `venv`
+pub fn expand(template: &str, venv: &VirtualEnv) -> String {
+    let variables = [
+        ("{path}", shell_quote_os(venv.path.as_os_str())),
+        ("{name}", shell_quote(&venv.name)),
+        ("{size}", shell_quote(&format_size(venv.venv_size))),
+        ("{bytes}", venv.venv_size.to_string()),
+        ("{manager}", shell_quote(&venv.manager.to_string())),
+    ];
+    // One pass over the template, so values are never expanded again
+    let mut command = String::new();
+    let mut rest = template;
+    while !rest.is_empty() {
+        match variables
+            .iter()
+            .find(|(variable, _)| rest.starts_with(variable))
+        {
+            Some((variable, value)) => {
+                command.push_str(value);
+                rest = &rest[variable.len()..];
+            }
+            None => {
+                let next = rest.chars().next().map_or(1, char::len_utf8);
+                command.push_str(&rest[..next]);
+                rest = &rest[next..];
+            }
+        }
+    }
+    command
+}
+
+/// Runs `template` for `venv` with `sh -c`, failing if it exits unsuccessfully
+pub fn run_hook(template: &str, venv: &VirtualEnv) -> Result<()> {
+    let command = expand(template, venv);
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .status()
+        .with_context(|| format!("Failed to run {:?}", command))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(anyhow!("{:?} failed with {}", command, status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    pub fn test_hooks() {
+        let venv = VirtualEnv {
+            path: PathBuf::from("/home/me/my env"),
+            name: "my env".to_string(),
+            venv_size: 1234,
+            ..Default::default()
+        };
+        assert_eq!(
+            expand("backup-env.sh {path} && notify.sh {name} {bytes}", &venv),
+            "backup-env.sh '/home/me/my env' && notify.sh 'my env' 1234"
+        );
+
+        // Variables inside substituted values stay as they are
+        let venv = VirtualEnv {
+            path: PathBuf::from("/envs/;touch PWNED;{name}"),
+            name: "it's {path}".to_string(),
+            ..venv
+        };
+        assert_eq!(
+            expand("backup.sh {path} {name}", &venv),
+            r"backup.sh '/envs/;touch PWNED;{name}' 'it'\''s {path}'"
+        );
+        let dir = tempfile::tempdir().expect("Failed to create tempdir");
+        let marker = dir.path().join("PWNED");
+        let venv = VirtualEnv {
+            path: dir
+                .path()
+                .join(format!(";touch {};{{name}}", marker.display())),
+            name: "{path}'".to_string(),
+            ..venv
+        };
+        assert!(run_hook("true {path} {name}", &venv).is_ok());
+        assert!(!marker.exists());
+
+        assert!(run_hook("test {bytes} -eq 1234", &venv).is_ok());
+        assert!(run_hook("exit 1", &venv).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::packages::{normalize_name, Package};
+use crate::venvs::VirtualEnv;
+use chrono::Local;
+use serde_json::{json, Value};
+
+/// Package URL of a PyPI distribution
+pub fn get_purl(package: &Package) -> String {
+    format!(
+        "pkg:pypi/{}@{}",
+        normalize_name(&package.name),
+        package.version
+    )
+}
+
+/// Builds a CycloneDX 1.5 SBOM with one application component per
+/// environment, each containing its installed packages as libraries
+pub fn build_cyclonedx(inventory: &[(VirtualEnv, Vec<Package>)], host: &str) -> Value {
+    let components: Vec<Value> = inventory
+        .iter()
+        .map(|(venv, packages)| {
+            let env_ref = venv.path.display().to_string();
+            let libraries: Vec<Value> = packages
+                .iter()
+                .map(|package| {
+                    let mut library = json!({
+                        "type": "library",
+                        "bom-ref": format!("{}#{}", env_ref, get_purl(package)),
+                        "name": package.name,
+                        "version": package.version,
+                        "purl": get_purl(package),
+                    });
+                    if let Some(license) = &package.license {
+                        library["licenses"] = json!([{"license": {"name": license}}]);
+                    }
+                    library
+                })
+                .collect();
+            json!({
+                "type": "application",
+                "bom-ref": env_ref,
+                "name": venv.name,
+                "version": venv.python_version,
+                "description": format!("Python {} environment ({})", venv.python_version, venv.manager),
+                "properties": [
+                    {"name": "venvpruner:path", "value": env_ref},
+                    {"name": "venvpruner:manager", "value": venv.manager.to_string()},
+                    {"name": "venvpruner:size", "value": venv.venv_size.to_string()},
+                ],
+                "components": libraries,
+            })
+        })
+        .collect();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "timestamp": Local::now().to_rfc3339(),
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                }]
+            },
+            "component": {
+                "type": "device",
+                "bom-ref": host,
+                "name": host,
+            },
+        },
+        "components": components,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    pub fn test_build_cyclonedx() {
+        let venv = VirtualEnv {
+            name: ".venv".to_string(),
+            path: PathBuf::from("/code/app/.venv"),
+            python_version: "3.12.1".to_string(),
+            ..Default::default()
+        };
+        let packages = vec![Package {
+            name: "Typing_Extensions".to_string(),
+            version: "4.8.0".to_string(),
+            ..Default::default()
+        }];
+
+        let bom = build_cyclonedx(&[(venv, packages)], "devbox");
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["metadata"]["component"]["name"], "devbox");
+        let library = &bom["components"][0]["components"][0];
+        assert_eq!(library["purl"], "pkg:pypi/typing-extensions@4.8.0");
+        assert_eq!(
+            library["bom-ref"],
+            "/code/app/.venv#pkg:pypi/typing-extensions@4.8.0"
+        );
+    }
+}
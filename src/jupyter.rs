@@ -0,0 +1,111 @@
+use crate::venvs::{is_venv_root, VirtualEnv};
+use serde::Deserialize;
+use std::env;
+use std::fs::{canonicalize, read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+/// A Jupyter kernel whose interpreter lives in a virtual environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JupyterKernel {
+    /// The kernel's directory name, which `jupyter kernelspec` knows it by
+    pub name: String,
+    /// Canonical root directory of the environment
+    pub venv_root: PathBuf,
+}
+
+/// The parts of a `kernel.json` that matter here
+#[derive(Debug, Deserialize)]
+struct KernelSpec {
+    argv: Vec<String>,
+}
+
+/// Where user-installed kernels live: `$JUPYTER_DATA_DIR/kernels`, or the
+/// Jupyter data directory of Linux or macOS
+pub fn get_kernel_dirs(home_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![
+        home_dir.join(".local/share/jupyter/kernels"),
+        home_dir.join("Library/Jupyter/kernels"),
+    ];
+    if let Some(data_dir) = env::var_os("JUPYTER_DATA_DIR").filter(|dir| !dir.is_empty()) {
+        dirs.push(PathBuf::from(data_dir).join("kernels"));
+    }
+    dirs
+}
+
+/// The kernels in `kernel_dirs` that run an environment's interpreter
+pub fn get_kernels(kernel_dirs: &[PathBuf]) -> Vec<JupyterKernel> {
+    kernel_dirs
+        .iter()
+        .filter_map(|dir| read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .filter_map(|entry| {
+            let text = read_to_string(entry.path().join("kernel.json")).ok()?;
+            let spec: KernelSpec = serde_json::from_str(&text).ok()?;
+            // `<venv>/bin/python -m ipykernel_launcher -f {connection_file}`
+            let interpreter = PathBuf::from(spec.argv.first()?);
+            let venv_root = interpreter.parent()?.parent()?;
+            if !is_venv_root(venv_root) {
+                return None;
+            }
+            Some(JupyterKernel {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                venv_root: canonicalize(venv_root).ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Records in each environment the kernels it backs
+pub fn mark_kernels(venvs: &mut [VirtualEnv], kernels: &[JupyterKernel]) {
+    for kernel in kernels {
+        for venv in venvs.iter_mut() {
+            if venv.path == kernel.venv_root && !venv.kernels.contains(&kernel.name) {
+                venv.kernels.push(kernel.name.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_get_kernels() {
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+        let home = canonicalize(dir.path()).unwrap();
+        let venv = home.join("research/envs/torch");
+        std::fs::create_dir_all(venv.join("bin")).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "version = 3.11.9\n").unwrap();
+
+        let kernels_dir = home.join(".local/share/jupyter/kernels");
+        for (name, interpreter) in [
+            ("torch", venv.join("bin/python")),
+            ("system", PathBuf::from("/usr/bin/python3")),
+        ] {
+            std::fs::create_dir_all(kernels_dir.join(name)).unwrap();
+            let spec = serde_json::json!({
+                "argv": [interpreter, "-m", "ipykernel_launcher", "-f", "{connection_file}"],
+                "display_name": name,
+                "language": "python",
+            });
+            std::fs::write(kernels_dir.join(name).join("kernel.json"), spec.to_string()).unwrap();
+        }
+
+        let kernels = get_kernels(&get_kernel_dirs(&home));
+        assert_eq!(
+            kernels,
+            vec![JupyterKernel {
+                name: "torch".to_string(),
+                venv_root: venv.clone(),
+            }]
+        );
+
+        let mut venvs = vec![VirtualEnv {
+            path: venv,
+            ..Default::default()
+        }];
+        mark_kernels(&mut venvs, &kernels);
+        assert_eq!(venvs[0].kernels, vec!["torch"]);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::packages::Package;
+use crate::venvs::VirtualEnv;
+use anyhow::{Context, Result};
+use console::style;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Label for packages whose metadata declares no license
+const UNKNOWN_LICENSE: &str = "Unknown";
+
+/// Where one license is used
+#[derive(Debug, Default)]
+pub struct LicenseUsage {
+    /// Distinct (name, version) pairs
+    pub packages: BTreeSet<(String, String)>,
+    pub envs: BTreeSet<PathBuf>,
+}
+
+/// Groups every package of every environment by declared license
+pub fn aggregate_licenses(
+    inventory: &[(VirtualEnv, Vec<Package>)],
+) -> BTreeMap<String, LicenseUsage> {
+    let mut licenses: BTreeMap<String, LicenseUsage> = BTreeMap::new();
+    for (venv, packages) in inventory {
+        for package in packages {
+            let license = package.license.as_deref().unwrap_or(UNKNOWN_LICENSE);
+            let usage = licenses.entry(license.to_string()).or_default();
+            usage
+                .packages
+                .insert((package.name.clone(), package.version.clone()));
+            usage.envs.insert(venv.path.clone());
+        }
+    }
+    licenses
+}
+
+pub fn print_license_report(licenses: &BTreeMap<String, LicenseUsage>) {
+    let mut sorted: Vec<(&String, &LicenseUsage)> = licenses.iter().collect();
+    sorted.sort_by_key(|(_, usage)| std::cmp::Reverse(usage.packages.len()));
+
+    println!(
+        "{}",
+        style(format!("{:>8}  {:>6}  {}", "PACKAGES", "ENVS", "LICENSE")).bold()
+    );
+    for (license, usage) in sorted {
+        let line = format!(
+            "{:>8}  {:>6}  {}",
+            usage.packages.len(),
+            usage.envs.len(),
+            license
+        );
+        match license == UNKNOWN_LICENSE {
+            true => println!("{}", style(line).yellow()),
+            false => println!("{}", line),
+        }
+    }
+}
+
+/// Quotes a CSV field if needed (RFC 4180)
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+/// Writes one `license,package,version,environment` row per installed package
+pub fn write_license_csv(path: &Path, inventory: &[(VirtualEnv, Vec<Package>)]) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    writeln!(writer, "license,package,version,environment")?;
+    for (venv, packages) in inventory {
+        for package in packages {
+            writeln!(
+                writer,
+                "{},{},{},{}",
+                csv_field(package.license.as_deref().unwrap_or(UNKNOWN_LICENSE)),
+                csv_field(&package.name),
+                csv_field(&package.version),
+                csv_field(&venv.path.to_string_lossy())
+            )?;
+        }
+    }
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_aggregate_licenses() {
+        let package = |name: &str, license: Option<&str>| Package {
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            license: license.map(String::from),
+            ..Default::default()
+        };
+        let env = |path: &str| VirtualEnv {
+            path: PathBuf::from(path),
+            ..Default::default()
+        };
+        let inventory = vec![
+            (
+                env("/a"),
+                vec![package("six", Some("MIT")), package("x", None)],
+            ),
+            (env("/b"), vec![package("six", Some("MIT"))]),
+        ];
+
+        let licenses = aggregate_licenses(&inventory);
+        assert_eq!(licenses["MIT"].packages.len(), 1);
+        assert_eq!(licenses["MIT"].envs.len(), 2);
+        assert_eq!(licenses[UNKNOWN_LICENSE].envs.len(), 1);
+        assert_eq!(csv_field("Apache, MIT"), "\"Apache, MIT\"");
+    }
+}
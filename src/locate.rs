@@ -0,0 +1,66 @@
+use crate::venvs::is_venv_root;
+use std::fs::canonicalize;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The queries for every `pyvenv.cfg` in the filesystem index: Spotlight on
+/// macOS, plocate or else mlocate/locate elsewhere
+#[cfg(target_os = "macos")]
+const INDEX_QUERIES: &[(&str, &[&str])] = &[("mdfind", &["kMDItemFSName == pyvenv.cfg"])];
+#[cfg(not(target_os = "macos"))]
+const INDEX_QUERIES: &[(&str, &[&str])] = &[
+    ("plocate", &["--basename", "\\pyvenv.cfg"]),
+    ("locate", &["--basename", "\\pyvenv.cfg"]),
+];
+
+/// The roots of the environments the filesystem index knows of, for
+/// `--use-index`. None when no index could be queried, so the caller walks
+/// instead. The index may be stale: environments deleted since are skipped,
+/// ones created since are missing.
+pub fn find_indexed_venv_roots() -> Option<Vec<PathBuf>> {
+    let stdout = INDEX_QUERIES.iter().find_map(|(program, args)| {
+        let output = Command::new(program).args(*args).output().ok()?;
+        // locate exits with 1 both when nothing matches and when it has no
+        // database, which it complains about
+        match (output.status.code(), output.stderr.is_empty()) {
+            (Some(0), _) | (Some(1), true) => {
+                Some(String::from_utf8_lossy(&output.stdout).into_owned())
+            }
+            _ => None,
+        }
+    })?;
+    Some(parse_index_output(&stdout))
+}
+
+/// Turns the `pyvenv.cfg` paths listed by the index into environment roots
+fn parse_index_output(stdout: &str) -> Vec<PathBuf> {
+    stdout
+        .lines()
+        .filter(|line| line.ends_with("/pyvenv.cfg"))
+        .filter_map(|line| PathBuf::from(line).parent().map(PathBuf::from))
+        .filter(|path| is_venv_root(path))
+        .filter_map(|path| canonicalize(path).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_index_output() {
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+        let root = canonicalize(dir.path()).unwrap();
+        std::fs::create_dir_all(root.join("code/app/.venv")).unwrap();
+        std::fs::write(root.join("code/app/.venv/pyvenv.cfg"), "").unwrap();
+
+        let stdout = format!(
+            "{0}/code/app/.venv/pyvenv.cfg\n{0}/code/gone/.venv/pyvenv.cfg\n{0}/notes/pyvenv.cfg.bak\n",
+            root.display()
+        );
+        assert_eq!(
+            parse_index_output(&stdout),
+            vec![root.join("code/app/.venv")]
+        );
+    }
+}
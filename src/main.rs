@@ -1,17 +1,86 @@
+mod apfs;
+mod archive;
+mod audit;
+mod cache;
+mod caches;
+mod completion;
+mod conda;
+mod config;
+mod copies;
+mod cow;
+mod diff;
+mod diff_env;
+mod ecosystems;
+mod excludes;
+mod hatch;
+mod history;
+mod hooks;
+mod inventory;
+mod jupyter;
+mod licenses;
+mod locate;
+mod manager;
+mod metadata;
+mod ml;
+mod notify;
+mod packages;
+mod paths;
+mod pdm;
+mod plain;
+mod plan;
+mod policy;
+mod privacy;
+mod progress;
+mod pycharm;
+mod pyenv;
+mod recommend;
+mod remote;
+mod remove;
+mod repair;
+mod residue;
+mod rules;
+mod rye;
+mod scheduled;
+mod score;
+mod script;
+mod selections;
+mod serve;
+mod size;
+mod slim;
+mod stats;
+mod superseded;
+mod telemetry;
+mod times;
+mod timings;
+mod top;
+mod update;
+mod upgrade;
+mod users;
 mod venvs;
+mod vscode;
+mod watch;
 
-use anyhow::{Context, Result};
-use clap::Parser;
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::{ArgValueCompleter, CompleteEnv};
 use console::style;
-use human_bytes::human_bytes;
-use indicatif::{ProgressBar, ProgressStyle};
-use inquire::list_option::ListOption;
-use inquire::{Confirm, MultiSelect};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use inquire::{Confirm, MultiSelect, Select, Text};
+use rayon::prelude::*;
 
+use config::{Config, Staleness};
+use manager::{Manager, ManagerFilter};
+use remote::Remote;
+use remove::DeleteOptions;
+use size::format_size;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Instant;
 use std::{fs, time::Duration};
-use venvs::{get_venvs, VirtualEnv};
+use venvs::{get_venvs, is_venv_root, load_venvs, ScanOptions, VirtualEnv};
 
 #[derive(Parser)]
 #[command(name = "venvpruner")]
@@ -21,114 +90,2387 @@ use venvs::{get_venvs, VirtualEnv};
     about = "Search and delete Python virtual environments at common search paths",
     long_about = "Search and delete Python virtual environments at common search paths."
 )]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
 
-struct Cli {}
+    #[command(flatten)]
+    scan: ScanArgs,
 
-fn select_venvs_to_delete(venvs: &Vec<VirtualEnv>) -> Result<Vec<VirtualEnv>> {
-    // Create a vector of tuples (original index, formatted string)
-    let options = venvs
-        .into_iter()
-        .enumerate()
-        .map(|(i, venv)| ListOption::new(i, venv))
-        .collect::<Vec<_>>();
+    /// Run discovery and deletion on a remote host over SSH
+    #[arg(long, global = true, value_name = "[USER@]HOST")]
+    ssh: Option<String>,
+
+    /// Copy this binary to the remote host instead of using its installed venvpruner
+    #[arg(long, global = true, requires = "ssh")]
+    ssh_upload: bool,
+
+    /// Archive each environment to DIR as .tar.zst before deleting it
+    #[arg(long, global = true, value_name = "DIR")]
+    archive: Option<PathBuf>,
+
+    /// Delete with idle I/O priority (throttled where unsupported) to keep the machine responsive
+    #[arg(long, global = true)]
+    nice_io: bool,
+
+    /// Allow deleting environments pinned with a .venvpruner-keep file
+    #[arg(long, global = true)]
+    force: bool,
+
+    /// Show dates (YYYY-MM-DD) instead of relative times like "3 months ago"
+    #[arg(long, global = true)]
+    absolute_times: bool,
+
+    /// Print sizes as exact byte counts, for scripting
+    #[arg(long, global = true)]
+    bytes: bool,
+
+    /// Show sizes in powers of 1000 (GB) or 1024 (GiB, the default unless set in the config)
+    #[arg(long, value_enum, global = true)]
+    units: Option<size::Units>,
+
+    /// Plain line-oriented output with labelled fields and no colors, progress
+    /// bars or symbols, for screen readers (implied by TERM=dumb)
+    #[arg(long, global = true)]
+    plain: bool,
+
+    /// Show progress as bars, or as JSON events on stderr for GUI frontends and scripts
+    #[arg(long, value_enum, default_value_t = progress::ProgressMode::Bars, global = true)]
+    progress: progress::ProgressMode,
+
+    /// Order of listed environments [default: size, unless set in the config]
+    #[arg(long, value_enum, global = true)]
+    sort: Option<SortKey>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// List the discovered virtual environments
+    List {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        output: OutputFormat,
+    },
+    /// Interactively select virtual environments to delete (the default)
+    Prune {
+        /// Preselect the oldest environments of projects over their configured budget
+        #[arg(long)]
+        policy: bool,
+
+        /// Preselect the environments of a selection saved with `selection save`
+        #[arg(long, value_name = "NAME")]
+        selection: Option<String>,
+
+        /// Write a reviewable shell script removing the selection instead of deleting it
+        #[arg(long, value_name = "FILE")]
+        emit_script: Option<PathBuf>,
+
+        /// Save the selection as a plan for `apply` instead of deleting it
+        #[arg(long, value_name = "FILE", conflicts_with = "emit_script")]
+        emit_plan: Option<PathBuf>,
+    },
+    /// Propose environments to delete and explain why
+    Recommend {
+        /// Recommend environments not used for longer than this (e.g. 90d, 6m)
+        #[arg(long, default_value = "90d", value_parser = rules::parse_age)]
+        unused_for: chrono::Duration,
+
+        /// Save the recommendations as a plan for `apply` instead of deleting them
+        #[arg(long, value_name = "FILE")]
+        emit_plan: Option<PathBuf>,
+    },
+    /// Delete the recommended environments without a selection UI
+    Clean {
+        /// Delete what `recommend` proposes, most delete-worthy first
+        #[arg(long, required = true)]
+        auto: bool,
+
+        /// Stop before reclaiming more than this (e.g. 20GB)
+        #[arg(long)]
+        max: Option<size::ByteSize>,
+
+        /// Recommend environments not used for longer than this (e.g. 90d, 6m)
+        #[arg(long, default_value = "90d", value_parser = rules::parse_age)]
+        unused_for: chrono::Duration,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Run the cache-clean commands of every installed Python tool
+    CleanCaches {
+        /// Only show the commands that would run
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Search any directory tree, e.g. a backup drive or NAS share, and select
+    /// environments below it to delete
+    Scan {
+        /// Directory to search
+        root: PathBuf,
+
+        /// How many levels below ROOT to search [default: no limit]
+        #[arg(long, value_name = "N")]
+        max_depth: Option<usize>,
+    },
+    /// Show disk usage statistics
+    Stats,
+    /// Exit with an error if environments use more than the configured budget (for CI)
+    Check,
+    /// Live view of the largest environments and caches, refreshed continuously
+    Top {
+        /// Seconds between full rescans
+        #[arg(long, value_name = "SECONDS", default_value_t = 30)]
+        interval: u64,
+    },
+    /// Serve scan/list/delete requests as newline-delimited JSON on a Unix socket
+    Serve {
+        /// Socket path [default: ~/.cache/venvpruner/venvpruner.sock]
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+    /// Remove regenerable content (bytecode caches, pip leftovers) from environments without deleting them
+    Slim {
+        /// Environments to slim; choose interactively if none are given
+        #[arg(value_name = "PATH")]
+        paths: Vec<PathBuf>,
+
+        /// Only report what would be removed
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Also strip tests, docs and examples bundled with installed packages
+        #[arg(long)]
+        aggressive: bool,
+    },
+    /// Relink an environment whose base interpreter moved or was upgraded away
+    Repair {
+        /// Root directory of the environment
+        path: PathBuf,
+
+        /// Interpreter to relink against [default: closest match on PATH]
+        #[arg(long, value_name = "INTERPRETER")]
+        python: Option<PathBuf>,
+
+        /// Also upgrade pip and setuptools in the environment
+        #[arg(long)]
+        upgrade_deps: bool,
+    },
+    /// Recreate environments with a newer interpreter, reinstalling their packages
+    Upgrade {
+        /// Environments to upgrade; choose interactively if none are given
+        #[arg(value_name = "PATH")]
+        paths: Vec<PathBuf>,
+
+        /// Interpreter to recreate the environments with
+        #[arg(long, value_name = "INTERPRETER")]
+        python: PathBuf,
+
+        /// Install the latest versions instead of the currently installed ones
+        #[arg(long)]
+        no_pin: bool,
+
+        /// Keep each old environment next to the new one as <PATH>.venvpruner-backup
+        #[arg(long)]
+        keep_backup: bool,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Rescan periodically, notifying when budgets are crossed
+    Watch {
+        /// Seconds between scans
+        #[arg(long, value_name = "SECONDS", default_value_t = 3600)]
+        interval: u64,
+
+        /// After each scan, delete the environments selected by the project budget policy
+        #[arg(long)]
+        prune: bool,
+    },
+    /// Show how disk usage has evolved over time, including past prunes
+    History {
+        /// How many days of history to show
+        #[arg(long, default_value_t = 90)]
+        days: i64,
+
+        /// Draw a bar chart of total usage per day
+        #[arg(long)]
+        chart: bool,
+    },
+    /// Compare with the previous scan: new, removed and grown environments
+    Diff {
+        /// Report environments that grew by at least this many percent
+        #[arg(long, value_name = "PERCENT", default_value_t = 10.0)]
+        growth_threshold: f64,
+    },
+    /// Export a CycloneDX SBOM of every package in every environment
+    Inventory {
+        /// Write the SBOM to FILE instead of stdout
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Summarize the licenses of the packages installed across all environments
+    Licenses {
+        /// Also write every package with its license and environment to FILE as CSV
+        #[arg(long, value_name = "FILE")]
+        export: Option<PathBuf>,
+    },
+    /// List the environments that have a package installed
+    WhichEnv {
+        /// Package name
+        package: String,
+        /// Only match this version or version prefix, e.g. "1.x" or "2.1"
+        version: Option<String>,
+    },
+    /// Compare the installed packages of two environments
+    DiffEnv {
+        /// Root directory of the first environment
+        a: PathBuf,
+        /// Root directory of the second environment
+        b: PathBuf,
+    },
+    /// Attach a note to an environment, shown in listings, or print its note
+    Note {
+        /// Root directory of the environment
+        path: PathBuf,
+
+        /// The note, e.g. "training env for paper X"
+        text: Option<String>,
+
+        /// Remove the environment's note
+        #[arg(long, conflicts_with = "text")]
+        clear: bool,
+    },
+    /// Build up a named selection of environments over several sessions
+    Selection {
+        #[command(subcommand)]
+        action: SelectionAction,
+    },
+    /// Add, remove or list tags on environments
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+    /// Delete the virtual environments at the given paths
+    Delete {
+        /// Root directories of the environments to delete
+        #[arg(
+            required_unless_present = "stdin",
+            value_name = "PATH",
+            add = ArgValueCompleter::new(completion::complete_venv_paths)
+        )]
+        paths: Vec<PathBuf>,
+
+        /// Read paths (one per line) or `list --output jsonl` records from stdin
+        #[arg(long, conflicts_with = "paths")]
+        stdin: bool,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+
+        /// With --yes, also delete recently modified or scheduled environments
+        #[arg(long, requires = "yes")]
+        override_protection: bool,
+
+        /// Write a reviewable shell script removing the environments instead of deleting them
+        #[arg(long, value_name = "FILE")]
+        emit_script: Option<PathBuf>,
+
+        /// Save the environments as a plan for `apply` instead of deleting them
+        #[arg(long, value_name = "FILE", conflicts_with = "emit_script")]
+        emit_plan: Option<PathBuf>,
+    },
+    /// Opt in to or out of anonymous usage statistics
+    Telemetry {
+        #[command(subcommand)]
+        action: TelemetryAction,
+    },
+    /// Replace this binary with the latest release from GitHub
+    SelfUpdate {
+        /// Only report whether a newer release exists
+        #[arg(long)]
+        check: bool,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Delete the environments in a plan saved with --emit-plan
+    Apply {
+        /// Plan file; progress is recorded in it as environments are deleted
+        plan: PathBuf,
+
+        /// Continue a partially applied plan, skipping environments already deleted
+        #[arg(long)]
+        resume: bool,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SelectionAction {
+    /// Pick environments and save them under NAME, starting from what it already holds
+    Save { name: String },
+    /// Show the saved selections
+    List,
+    /// Show the environments in a selection
+    Show { name: String },
+    /// Forget a selection
+    Remove { name: String },
+}
+
+#[derive(Subcommand)]
+enum TelemetryAction {
+    /// Show whether telemetry is enabled and exactly what a report would contain
+    Status,
+    /// Send a weekly report of environment counts and reclaimed space
+    Enable,
+    /// Stop sending reports
+    Disable,
+}
+
+#[derive(Subcommand)]
+enum TagAction {
+    /// Tag an environment
+    Add {
+        /// Root directory of the environment
+        path: PathBuf,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Remove tags from an environment
+    Remove {
+        /// Root directory of the environment
+        path: PathBuf,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Show the tags of one environment, or of all tagged environments
+    List {
+        /// Root directory of the environment
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SortKey {
+    #[default]
+    /// Largest first
+    Size,
+    /// Most worth deleting first: big, long unused, broken, orphaned or duplicated
+    Score,
+    /// Most space taken by ML frameworks (torch, tensorflow, jax, CUDA) first
+    Ml,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human readable listing
+    Table,
+    /// A single JSON array
+    Json,
+    /// One JSON object per line
+    Jsonl,
+}
+
+#[derive(Args)]
+struct ScanArgs {
+    /// Extra directory to search for environments, like the well-known locations (repeatable)
+    #[arg(long = "path", value_name = "DIR", global = true)]
+    search_paths: Vec<PathBuf>,
+
+    /// Project directory to search for local environments such as `.venv` (repeatable)
+    #[arg(long = "project-root", value_name = "DIR", global = true)]
+    project_roots: Vec<PathBuf>,
+
+    /// Gitignore-style pattern of paths to skip while scanning (repeatable)
+    #[arg(long = "exclude", value_name = "PATTERN", global = true)]
+    excludes: Vec<String>,
+
+    /// Also walk node_modules, .git and other trees that never contain environments
+    #[arg(long, global = true)]
+    no_skip_known_dirs: bool,
+
+    /// Search every user's home directory (/home/*, /Users/*); intended for root
+    #[arg(long, global = true)]
+    all_users: bool,
+
+    /// Stop scanning after this long (e.g. 30s, 2m) and show what was found so far
+    #[arg(long, value_name = "DURATION", value_parser = times::parse_duration, global = true)]
+    scan_timeout: Option<Duration>,
+
+    /// Only find and delete environments under this directory, ignoring the usual locations
+    #[arg(long, value_name = "DIR", global = true)]
+    only: Option<PathBuf>,
+
+    /// Report how long each search root and scan phase took at the end of the run
+    #[arg(long, global = true)]
+    timings: bool,
+
+    /// Also find other ecosystems' caches: node_modules, Rust target/ dirs, the Gradle cache
+    #[arg(
+        long = "ecosystem",
+        value_name = "ECOSYSTEM",
+        value_enum,
+        value_delimiter = ',',
+        global = true
+    )]
+    ecosystems: Vec<ecosystems::Ecosystem>,
+
+    /// Also list the Python installations in ~/.pyenv/versions, which the
+    /// environments created from them depend on
+    #[arg(long, global = true)]
+    include_pyenv_versions: bool,
+
+    /// Find the environments below project roots with Spotlight (macOS) or
+    /// plocate instead of walking, falling back to walking without an index
+    #[arg(long, global = true)]
+    use_index: bool,
+
+    /// Also find environments by running `conda env list`, which knows of
+    /// every one conda created, wherever it lives
+    #[arg(long, global = true)]
+    query_conda: bool,
+
+    /// Only show environments of these managers, e.g. conda,poetry
+    #[arg(
+        long = "manager",
+        value_name = "MANAGER",
+        value_delimiter = ',',
+        global = true
+    )]
+    managers: Vec<Manager>,
+
+    /// Only show environments with any of these tags (repeatable or comma separated)
+    #[arg(long = "tag", value_name = "TAG", value_delimiter = ',', global = true)]
+    tags: Vec<String>,
+
+    /// Only show broken environments, whose interpreter is gone
+    #[arg(long, global = true)]
+    broken: bool,
+
+    /// Only show orphaned environments, whose project directory is gone
+    #[arg(long, global = true)]
+    orphaned: bool,
+
+    /// Only show environments with ML frameworks like torch or tensorflow installed
+    #[arg(long, global = true)]
+    ml: bool,
+
+    /// Hide environments of these managers, e.g. pipx
+    #[arg(
+        long = "exclude-manager",
+        value_name = "MANAGER",
+        value_delimiter = ',',
+        global = true
+    )]
+    exclude_managers: Vec<Manager>,
+}
+
+impl ScanArgs {
+    fn scan_options(&self) -> ScanOptions {
+        ScanOptions {
+            search_paths: self.search_paths.clone(),
+            project_roots: self.project_roots.clone(),
+            excludes: self.excludes.clone(),
+            skip_known_dirs: !self.no_skip_known_dirs,
+            all_users: self.all_users,
+            timeout: self.scan_timeout,
+            walk_deadline: None,
+            ecosystems: self.ecosystems.clone(),
+            include_pyenv_versions: self.include_pyenv_versions,
+            query_conda: self.query_conda,
+            use_index: self.use_index,
+            only: self.only.clone(),
+            max_depth: None,
+            root_depths: HashMap::new(),
+        }
+    }
+
+    fn manager_filter(&self) -> ManagerFilter {
+        ManagerFilter {
+            include: self.managers.clone(),
+            exclude: self.exclude_managers.clone(),
+        }
+    }
+
+    /// The equivalent command line flags, for forwarding to a remote host.
+    /// Manager filters are applied locally instead.
+    fn to_args(&self) -> Vec<OsString> {
+        let mut args = Vec::new();
+        for path in &self.search_paths {
+            args.push("--path".into());
+            args.push(path.clone().into_os_string());
+        }
+        for root in &self.project_roots {
+            args.push("--project-root".into());
+            args.push(root.clone().into_os_string());
+        }
+        for pattern in &self.excludes {
+            args.push("--exclude".into());
+            args.push(pattern.into());
+        }
+        if self.no_skip_known_dirs {
+            args.push("--no-skip-known-dirs".into());
+        }
+        if self.all_users {
+            args.push("--all-users".into());
+        }
+        if let Some(timeout) = self.scan_timeout {
+            args.push("--scan-timeout".into());
+            args.push(format!("{}s", timeout.as_secs()).into());
+        }
+        if let Some(only) = &self.only {
+            args.push("--only".into());
+            args.push(only.clone().into_os_string());
+        }
+        if self.timings {
+            args.push("--timings".into());
+        }
+        for ecosystem in &self.ecosystems {
+            args.push("--ecosystem".into());
+            args.push(ecosystem.to_possible_value().unwrap().get_name().into());
+        }
+        if self.include_pyenv_versions {
+            args.push("--include-pyenv-versions".into());
+        }
+        if self.query_conda {
+            args.push("--query-conda".into());
+        }
+        if self.use_index {
+            args.push("--use-index".into());
+        }
+        args
+    }
+}
+
+/// Everything a command needs to run
+struct App {
+    target: Target,
+    config: Config,
+    /// Archiving and I/O priority for deletions
+    delete_options: DeleteOptions,
+    /// Managers whose environments are shown
+    managers: ManagerFilter,
+    /// If not empty, only environments with one of these tags are shown
+    tags: Vec<String>,
+    /// Only broken environments are shown
+    broken_only: bool,
+    /// Only orphaned environments are shown
+    orphaned_only: bool,
+    /// Only environments with ML frameworks are shown
+    ml_only: bool,
+    /// Show dates rather than relative times
+    absolute_times: bool,
+    sort: SortKey,
+}
+
+impl App {
+    /// Scans the target, keeping the environments of the selected managers
+    /// and tags, in the selected order
+    fn scan(&self, spinner: &ProgressBar) -> Result<Vec<VirtualEnv>> {
+        let mut venvs = self.target.scan(spinner, &self.config.telemetry)?;
+        venvs.retain(|venv| {
+            self.managers.matches(venv.manager)
+                && (self.tags.is_empty() || self.tags.iter().any(|tag| venv.tags.contains(tag)))
+                && (!self.broken_only || venv.is_broken())
+                && (!self.orphaned_only || venv.is_orphaned())
+                && (!self.ml_only || venv.ml_size > 0)
+        });
+        match self.sort {
+            SortKey::Size => venvs.sort_by_key(|venv| std::cmp::Reverse(venv.venv_size)),
+            SortKey::Ml => venvs.sort_by_key(|venv| std::cmp::Reverse(venv.ml_size)),
+            SortKey::Score => {
+                venvs.sort_by(|a, b| score::reclaim_score(b).total_cmp(&score::reclaim_score(a)))
+            }
+        }
+        Ok(venvs)
+    }
+
+    /// Fails if any of `venvs` lies outside the `--only` directory
+    fn check_within_only(&self, venvs: &[VirtualEnv]) -> Result<()> {
+        if let Target::Local(ScanOptions {
+            only: Some(only), ..
+        }) = &self.target
+        {
+            if let Some(outside) = venvs.iter().find(|venv| !venv.path.starts_with(only)) {
+                return Err(anyhow!(
+                    "{} is outside {}, the only directory this run may delete from",
+                    outside.path.display(),
+                    only.display()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn delete(&self, venvs: &[VirtualEnv]) -> Result<()> {
+        self.check_within_only(venvs)?;
+        self.target.delete(venvs, &self.delete_options)
+    }
+}
+
+/// Where environments are discovered and deleted
+enum Target {
+    Local(ScanOptions),
+    Remote(Remote, Vec<OsString>),
+}
+
+impl Target {
+    fn scan(
+        &self,
+        spinner: &ProgressBar,
+        telemetry_config: &telemetry::TelemetryConfig,
+    ) -> Result<Vec<VirtualEnv>> {
+        match self {
+            Target::Local(scan_options) => {
+                let scan = get_venvs(scan_options)?;
+                // A partial scan would look like deletions to `diff` and `history`
+                if scan.partial {
+                    spinner.suspend(|| {
+                        eprintln!(
+                            "{}",
+                            style(format!(
+                                "Partial results: the scan timed out; only the {} environments sized so far are shown.",
+                                scan.venvs.len()
+                            ))
+                            .yellow()
+                        )
+                    });
+                    return Ok(scan.venvs);
+                }
+                // So would a scan of a single tree
+                if scan_options.only.is_some() {
+                    return Ok(scan.venvs);
+                }
+                if let Err(err) = cache::save_scan(&scan.venvs) {
+                    eprintln!("Failed to save scan results: {}", err);
+                }
+                if let Err(err) = history::record_scan(&scan.venvs) {
+                    eprintln!("Failed to record scan history: {}", err);
+                }
+                if let Err(err) = telemetry::maybe_send(telemetry_config, &scan.venvs) {
+                    eprintln!("Failed to send usage statistics: {}", err);
+                }
+                Ok(scan.venvs)
+            }
+            Target::Remote(remote, scan_args) => {
+                let mut found = 0;
+                remote.list(scan_args, |venv| {
+                    progress::env_found(venv);
+                    found += 1;
+                    spinner.set_message(format!(
+                        "Searching for virtual environments on {}... ({} found)",
+                        remote.host(),
+                        found
+                    ));
+                })
+            }
+        }
+    }
+
+    fn delete(&self, venvs: &[VirtualEnv], options: &DeleteOptions) -> Result<()> {
+        match self {
+            Target::Local(_) => delete_venvs(venvs, options, |_| {}),
+            Target::Remote(remote, _) => remote.delete(venvs, options),
+        }
+    }
+}
+
+/// An entry of the interactive selector
+enum Choice {
+    /// Pseudo-entry that prompts for a rule and toggles the matching environments
+    SelectByRule,
+    /// A formatted environment
+    Venv(String),
+}
+
+impl std::fmt::Display for Choice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Choice::SelectByRule if plain::is_plain() => write!(f, "Select by rule..."),
+            Choice::SelectByRule => write!(f, "{}", style("» Select by rule...").cyan()),
+            Choice::Venv(venv) => write!(f, "{}", venv),
+        }
+    }
+}
+
+/// `venv` as labelled fields, with its staleness spelled out instead of colored
+fn format_venv_plain(app: &App, venv: &VirtualEnv) -> String {
+    let mut fields = vec![
+        format!("environment {}", venv.name),
+        format!("path {}", venv.path.display()),
+        format!("size {}", format_size(venv.venv_size)),
+    ];
+    if venv.manager.is_python() {
+        fields.push(format!("Python {}", venv.python_version));
+    }
+    fields.push(format!("manager {}", venv.manager));
+    if let Some(owner) = &venv.owner {
+        fields.push(format!("owner {}", owner));
+    }
+    if let Some(link) = &venv.link {
+        fields.push(format!("linked as {}", link.display()));
+    }
+    match (&venv.project, &venv.project_name) {
+        (Some(project), _) => fields.push(format!("project {}", project.display())),
+        (None, Some(name)) => fields.push(format!("project {}", name)),
+        (None, None) => {}
+    }
+    if let Some(clone_of) = &venv.clone_of {
+        fields.push(format!("clone of {}", clone_of.display()));
+    }
+    if let Some(newer) = &venv.superseded_by {
+        fields.push(format!("superseded by {}", newer.display()));
+    }
+    if venv.ml_size > 0 {
+        fields.push(format!("ML {}", format_size(venv.ml_size)));
+    }
+    if venv.is_broken() {
+        fields.push("broken".to_string());
+    }
+    if venv.is_orphaned() {
+        fields.push("orphaned".to_string());
+    }
+    if venv.pinned {
+        fields.push("pinned".to_string());
+    }
+    if let Some(source) = &venv.scheduled_by {
+        fields.push(format!("used by {}", source));
+    }
+    for settings in &venv.editor_configs {
+        fields.push(format!("selected in {}", settings.display()));
+    }
+    if !venv.kernels.is_empty() {
+        fields.push(format!("kernel-backed {}", venv.kernels.join(" ")));
+    }
+    if !venv.tags.is_empty() {
+        fields.push(format!("tags {}", venv.tags.join(" ")));
+    }
+    if let Some(note) = &venv.note {
+        fields.push(format!("note {}", note));
+    }
+    if let Some(modified) = venv.modified {
+        fields.push(format!(
+            "last used {}",
+            times::format_time(modified, app.absolute_times)
+        ));
+    }
+    if let Some(created) = venv.created {
+        fields.push(format!(
+            "created {}",
+            times::format_time(created, app.absolute_times)
+        ));
+    }
+    match app.config.staleness.classify(venv) {
+        Some(Staleness::Fresh) => fields.push("status fresh".to_string()),
+        Some(Staleness::Aging) => fields.push("status aging".to_string()),
+        Some(Staleness::Stale) => fields.push("status stale".to_string()),
+        None => {}
+    }
+    if app.sort == SortKey::Score {
+        fields.push(format!("score {:.0}", score::reclaim_score(venv)));
+    }
+    fields.join("; ")
+}
+
+/// `venv` with when it was last used and created, colored by how long ago
+/// it was last used
+fn format_venv(app: &App, venv: &VirtualEnv) -> String {
+    if plain::is_plain() {
+        return format_venv_plain(app, venv);
+    }
+    let mut times = Vec::new();
+    if let Some(modified) = venv.modified {
+        times.push(format!(
+            "used {}",
+            times::format_time(modified, app.absolute_times)
+        ));
+    }
+    if let Some(created) = venv.created {
+        times.push(format!(
+            "created {}",
+            times::format_time(created, app.absolute_times)
+        ));
+    }
+    if app.sort == SortKey::Score {
+        times.push(format!("score {:.0}", score::reclaim_score(venv)));
+    }
+    let text = match times.is_empty() {
+        true => venv.to_string(),
+        false => format!("{} · {}", venv, times.join(", ")),
+    };
+    match app.config.staleness.classify(venv) {
+        Some(Staleness::Fresh) => style(text).green().to_string(),
+        Some(Staleness::Aging) => style(text).yellow().to_string(),
+        Some(Staleness::Stale) => style(text).red().to_string(),
+        None => text,
+    }
+}
+
+fn select_venvs(
+    app: &App,
+    message: &str,
+    venvs: &[VirtualEnv],
+    preselected: &[PathBuf],
+) -> Result<Vec<VirtualEnv>> {
+    let mut selected: Vec<bool> = venvs
+        .iter()
+        .map(|venv| preselected.contains(&venv.path))
+        .collect();
+
+    loop {
+        let mut options = vec![Choice::SelectByRule];
+        options.extend(
+            venvs
+                .iter()
+                .map(|venv| Choice::Venv(format_venv(app, venv))),
+        );
+        // Offset by one for the rule entry
+        let defaults = (0..venvs.len())
+            .filter(|&i| selected[i])
+            .map(|i| i + 1)
+            .collect::<Vec<_>>();
+
+        let chosen = MultiSelect::new(message, options)
+            .with_default(&defaults)
+            .raw_prompt();
+        let chosen = match chosen {
+            Ok(chosen) => chosen,
+            Err(_) => {
+                return Ok(vec![]);
+            }
+        };
+
+        selected = vec![false; venvs.len()];
+        let mut select_by_rule = false;
+        for option in &chosen {
+            match option.index {
+                0 => select_by_rule = true,
+                i => selected[i - 1] = true,
+            }
+        }
+        if !select_by_rule {
+            break;
+        }
+
+        let rule = match Text::new("Select environments that are:")
+            .with_help_message(rules::RULE_HELP)
+            .prompt()
+        {
+            Ok(input) => input,
+            Err(_) => continue,
+        };
+        match rule.parse::<rules::Rule>() {
+            Ok(rule) => {
+                let matching: Vec<usize> = (0..venvs.len())
+                    // Pinned and scheduled environments can only be picked one by one
+                    .filter(|&i| {
+                        !venvs[i].pinned
+                            && venvs[i].scheduled_by.is_none()
+                            && rule.matches(&venvs[i])
+                    })
+                    .collect();
+                // Toggle: deselect the matches if they were all selected already
+                let select = !matching.iter().all(|&i| selected[i]);
+                for &i in &matching {
+                    selected[i] = select;
+                }
+                let verb = match select {
+                    true => "Selected",
+                    false => "Deselected",
+                };
+                print_info_message(&format!(
+                    "{} {} matching environments.",
+                    verb,
+                    matching.len()
+                ));
+            }
+            Err(err) => eprintln!("{}", style(err).red()),
+        }
+    }
+
+    let selected_venvs = venvs
+        .iter()
+        .zip(&selected)
+        .filter(|(_, &selected)| selected)
+        .map(|(venv, _)| venv.clone())
+        .collect::<Vec<VirtualEnv>>();
+
+    Ok(selected_venvs)
+}
+
+fn print_success_message(message: &str) {
+    println!("{}", style(message).green());
+}
+
+fn print_info_message(message: &str) {
+    println!("{}", style(message).cyan());
+}
+
+/// Warns about Python installations whose environments would stop working
+fn warn_dependents(venvs: &[VirtualEnv]) {
+    for venv in venvs.iter().filter(|venv| !venv.dependents.is_empty()) {
+        eprintln!(
+            "{}",
+            style(format!(
+                "Warning: {} environments use the Python at {} and will break:",
+                venv.dependents.len(),
+                venv.path.display()
+            ))
+            .yellow()
+        );
+        for dependent in &venv.dependents {
+            eprintln!("  {}", dependent.display());
+        }
+    }
+}
+
+/// Warns about environments an editor is set up to use
+fn warn_editor_configs(venvs: &[VirtualEnv]) {
+    for venv in venvs {
+        for settings in &venv.editor_configs {
+            eprintln!(
+                "{}",
+                style(format!(
+                    "Warning: {} is the interpreter selected in {}",
+                    venv.path.display(),
+                    settings.display()
+                ))
+                .yellow()
+            );
+        }
+    }
+}
+
+/// Refuses unattended deletion of environments modified too recently
+fn check_not_protected(app: &App, venvs: &[VirtualEnv]) -> Result<()> {
+    let protected: Vec<String> = venvs
+        .iter()
+        .filter(|venv| app.config.safety.is_protected(venv))
+        .map(|venv| venv.path.display().to_string())
+        .collect();
+    match protected.is_empty() {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "Refusing to delete recently modified or scheduled environments without confirmation: {}",
+            protected.join(", ")
+        )),
+    }
+}
+
+fn confirm_deletion(app: &App, venvs: &[VirtualEnv]) -> Result<bool> {
+    warn_dependents(venvs);
+    warn_editor_configs(venvs);
+    if app.config.safety.needs_typed_confirm(venvs) {
+        let total_size: u64 = venvs.iter().map(|venv| venv.reclaimable_size()).sum();
+        let total_size_str = format_size(total_size);
+        let answer = Text::new(&format!(
+            "This deletes {} environments ({}). Type \"delete\" or \"{}\" to continue:",
+            venvs.len(),
+            total_size_str,
+            total_size_str
+        ))
+        .prompt()?;
+        let answer = answer.trim();
+        return Ok(answer == "delete" || answer == total_size_str);
+    }
+    Confirm::new("Are you sure you want to delete the selected virtual environments?")
+        .with_default(false)
+        .prompt()
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Deletes `venvs` in order, calling `on_deleted` after each one
+fn delete_venvs(
+    venvs: &[VirtualEnv],
+    options: &DeleteOptions,
+    mut on_deleted: impl FnMut(&VirtualEnv),
+) -> Result<()> {
+    // Look at the disk rather than the scan, which may be out of date
+    let pinned: Vec<String> = venvs
+        .iter()
+        .filter(|venv| venv.path.join(venvs::KEEP_MARKER).exists())
+        .map(|venv| venv.path.display().to_string())
+        .collect();
+    if !pinned.is_empty() && !options.force {
+        return Err(anyhow!(
+            "Refusing to delete environments pinned with {} (pass --force to delete them anyway): {}",
+            venvs::KEEP_MARKER,
+            pinned.join(", ")
+        ));
+    }
+
+    // Without OS support, fall back to pausing between batches of files
+    let throttle = options.nice_io && !remove::lower_io_priority();
+
+    // Snapshots and reflinked copies can keep deleted data on disk
+    let on_cow = venvs
+        .iter()
+        .any(|venv| cow::get_cow_filesystem(&venv.path).is_some());
+
+    // Progress is in bytes so that huge environments don't look frozen
+    let total_bytes = venvs.iter().map(|venv| venv.venv_size).sum();
+    let pb = match plain::is_plain() || progress::is_json() {
+        true => ProgressBar::with_draw_target(Some(total_bytes), ProgressDrawTarget::hidden()),
+        false => ProgressBar::new(total_bytes),
+    };
+    let mut total_size: u64 = 0;
+    pb.set_style(
+        ProgressStyle::with_template(
+            "[{elapsed_precise}] [{bar:40.cyan/blue}] ({binary_bytes}/{binary_total_bytes}, ETA {eta}) {msg}",
+        )
+        .unwrap(),
+    );
+
+    let mut deleted = Vec::new();
+    let mut freed: u64 = 0;
+    let mut kept = 0;
+    for (i, venv) in venvs.iter().enumerate() {
+        let position = pb.position();
+        pb.set_prefix(format!("({}/{})", i + 1, venvs.len()));
+        if let Some(pre_delete) = &options.hooks.pre_delete {
+            if let Err(err) = hooks::run_hook(pre_delete, venv) {
+                pb.suspend(|| {
+                    eprintln!(
+                        "{}",
+                        style(format!(
+                            "Keeping {}: pre_delete hook failed: {:#}",
+                            venv.path.display(),
+                            err
+                        ))
+                        .yellow()
+                    )
+                });
+                pb.set_position(position + venv.venv_size);
+                kept += 1;
+                continue;
+            }
+        }
+        let parent = venv.path.parent().unwrap_or(&venv.path);
+        let available_before = residue::get_available_space(parent);
+        let result = archive_and_remove(venv, options.archive.as_deref(), throttle, &pb);
+        // Check the environment is really gone; removal can also fail halfway
+        let residue = residue::find_residue(&venv.path);
+        if !residue.is_empty() {
+            pb.suspend(|| print_residue(&venv.path, &residue));
+        }
+        let archive_path = match result {
+            Ok(archive_path) => archive_path,
+            Err(err) => {
+                record_prune(&deleted);
+                return Err(err);
+            }
+        };
+        if let (Some(before), Some(after)) =
+            (available_before, residue::get_available_space(parent))
+        {
+            freed += after.saturating_sub(before);
+        }
+        if let Err(err) = audit::record_deletion(venv, archive_path) {
+            eprintln!("Failed to write audit log: {}", err);
+        }
+        if let Some(post_delete) = &options.hooks.post_delete {
+            if let Err(err) = hooks::run_hook(post_delete, venv) {
+                pb.suspend(|| eprintln!("post_delete hook failed: {:#}", err));
+            }
+        }
+        if plain::is_plain() {
+            println!(
+                "Deleted {} of {}: {}",
+                i + 1,
+                venvs.len(),
+                venv.path.display()
+            );
+        }
+        on_deleted(venv);
+        deleted.push(venv.clone());
+        progress::emit(progress::Event::EnvDeleted {
+            path: venv.path.clone(),
+            deleted: deleted.len(),
+            count: venvs.len(),
+        });
+        // The file by file count may differ slightly from the scanned size
+        pb.set_position(position + venv.venv_size);
+        total_size += venv.reclaimable_size();
+    }
+    record_prune(&deleted);
+    let total_size_hr = format_size(total_size);
+    let estimate = match on_cow {
+        true => format!("up to {} (copy-on-write filesystem)", total_size_hr),
+        false => format!("{} (estimated)", total_size_hr),
+    };
+    let deleted_message = match kept {
+        0 => "All selected virtual environments have been deleted.".to_string(),
+        kept => format!(
+            "{} of the selected virtual environments have been deleted; {} were kept because their pre_delete hook failed.",
+            deleted.len(),
+            kept
+        ),
+    };
+    let message = format!(
+        "{} \n\n Total size reclaimed: {}, free space grew by {}",
+        deleted_message,
+        estimate,
+        format_size(freed)
+    );
+    // A hidden bar (plain output, or stderr not a terminal) shows no message
+    match pb.is_hidden() {
+        true => println!("{}", message.replace(" \n\n ", "\n")),
+        false => pb.finish_with_message(message),
+    }
+    // Other processes writing at the same time make this approximate, so only
+    // point out big shortfalls; on copy-on-write filesystems they are expected
+    if !on_cow && freed < total_size / 2 {
+        println!(
+            "{}",
+            style("Less space was freed than estimated. Files still open in running processes, hard links elsewhere or filesystem snapshots can keep the data on disk.").yellow()
+        );
+    }
+    Ok(())
+}
+
+fn print_residue(path: &Path, residue: &residue::Residue) {
+    eprintln!(
+        "{}",
+        style(format!(
+            "{} files ({}) are left at {}",
+            residue.files,
+            format_size(residue.bytes),
+            path.display()
+        ))
+        .yellow()
+    );
+    for (blocker, cause) in residue.blockers.iter().take(10) {
+        eprintln!("  {} ({})", blocker.display(), cause);
+    }
+    if residue.blockers.len() > 10 {
+        eprintln!("  ... and {} more", residue.blockers.len() - 10);
+    }
+}
+
+/// Deletes `venv`, archiving it into `archive` first if given; an
+/// environment that could not be archived is left in place
+fn archive_and_remove(
+    venv: &VirtualEnv,
+    archive: Option<&Path>,
+    throttle: bool,
+    pb: &ProgressBar,
+) -> Result<Option<PathBuf>> {
+    let archive_path = match archive {
+        Some(dir) => {
+            pb.set_message(format!(
+                "{} Archiving virtual environment at: {}",
+                pb.prefix(),
+                venv.path.display()
+            ));
+            Some(archive::archive_venv(venv, dir)?)
+        }
+        None => None,
+    };
+    pb.set_message(format!(
+        "{} Deleting virtual environment at: {}",
+        pb.prefix(),
+        &venv.path.display()
+    ));
+    let result = match throttle {
+        true => {
+            let mut throttle = remove::Throttle::default();
+            remove::remove_dir_with_progress(&venv.path, |bytes| {
+                pb.inc(bytes);
+                progress::delete_progress(&venv.path, pb);
+                throttle.tick();
+            })
+        }
+        false => remove::remove_dir_parallel(&venv.path, |bytes| {
+            pb.inc(bytes);
+            progress::delete_progress(&venv.path, pb);
+        }),
+    };
+    result.with_context(|| format!("Failed to delete {}", venv.path.display()))?;
+    if let Some(link) = &venv.link {
+        pyenv::remove_dangling_link(link);
+    }
+    Ok(archive_path)
+}
+
+fn record_prune(deleted: &[VirtualEnv]) {
+    if let Err(err) = history::record_prune(deleted) {
+        eprintln!("Failed to record prune history: {}", err);
+    }
+}
+
+fn main() -> Result<()> {
+    // Answers the shell's completion requests (`COMPLETE=bash venvpruner ...`) and exits
+    CompleteEnv::with_factory(Cli::command).complete();
+
+    let cli = Cli::parse();
+    size::set_exact_bytes(cli.bytes);
+    timings::set_enabled(cli.scan.timings);
+    progress::set_mode(cli.progress);
+    plain::set_plain(cli.plain || std::env::var_os("TERM").is_some_and(|term| term == "dumb"));
+
+    if cli.scan.all_users && cli.ssh.is_none() && !users::is_root() {
+        eprintln!(
+            "{}",
+            style("Warning: --all-users without root; other users' homes may be unreadable")
+                .yellow()
+        );
+    }
+
+    let config = Config::load()?;
+    size::set_units(cli.units.or(config.units).unwrap_or_default());
+    let command = cli.command.unwrap_or(Commands::Prune {
+        policy: false,
+        selection: None,
+        emit_script: None,
+        emit_plan: None,
+    });
+
+    let target = match &cli.ssh {
+        Some(host) => {
+            let remote = Remote::connect(host, cli.ssh_upload)?;
+            // Only listing and interactive pruning combine remote results with
+            // local rendering; everything else simply runs over there
+            if !matches!(command, Commands::List { .. } | Commands::Prune { .. }) {
+                return remote.run(&remote::get_forwarded_args());
+            }
+            Target::Remote(remote, cli.scan.to_args())
+        }
+        None => {
+            let mut scan_options = cli.scan.scan_options();
+            for root in config.get_project_roots() {
+                if !scan_options.project_roots.contains(&root) {
+                    scan_options.project_roots.push(root);
+                }
+            }
+            for path in config.get_search_paths() {
+                if !scan_options.search_paths.contains(&path) {
+                    scan_options.search_paths.push(path);
+                }
+            }
+            scan_options
+                .excludes
+                .extend(config.excludes.iter().cloned());
+            scan_options.root_depths = config.get_depths();
+            scan_options.query_conda |= config.query_conda;
+            scan_options.use_index |= config.use_index;
+            if let Commands::Scan { root, max_depth } = &command {
+                // Like `--only ROOT`, walked as deep as asked
+                scan_options.only = Some(root.clone());
+                scan_options.max_depth = Some(max_depth.unwrap_or(usize::MAX));
+            }
+            if let Some(only) = &scan_options.only {
+                scan_options.only =
+                    Some(fs::canonicalize(only).with_context(|| {
+                        format!("--only directory not found: {}", only.display())
+                    })?);
+            }
+            Target::Local(scan_options)
+        }
+    };
+    // A remote venvpruner falls back to the archive directory of its own config file
+    let archive = match &target {
+        Target::Local(_) => cli.archive.or_else(|| config.delete.get_archive()),
+        Target::Remote(..) => cli.archive,
+    };
+    let app = App {
+        target,
+        delete_options: DeleteOptions {
+            archive,
+            nice_io: cli.nice_io || config.delete.nice_io,
+            force: cli.force,
+            hooks: config.hooks.clone(),
+        },
+        sort: cli.sort.or(config.sort).unwrap_or_default(),
+        config,
+        managers: cli.scan.manager_filter(),
+        tags: cli
+            .scan
+            .tags
+            .iter()
+            .map(|tag| metadata::normalize_tag(tag))
+            .collect(),
+        broken_only: cli.scan.broken,
+        orphaned_only: cli.scan.orphaned,
+        ml_only: cli.scan.ml,
+        absolute_times: cli.absolute_times,
+    };
+
+    let result = match command {
+        Commands::List { output } => list(&app, output),
+        Commands::Prune {
+            policy,
+            selection,
+            emit_script,
+            emit_plan,
+        } => prune(
+            &app,
+            policy,
+            selection.as_deref(),
+            emit_script.as_deref(),
+            emit_plan.as_deref(),
+        ),
+        Commands::Recommend {
+            unused_for,
+            emit_plan,
+        } => recommend(&app, unused_for, emit_plan.as_deref()),
+        Commands::Clean {
+            auto: _,
+            max,
+            unused_for,
+            yes,
+        } => clean(&app, max, unused_for, yes),
+        Commands::CleanCaches { dry_run } => clean_caches(dry_run),
+        Commands::Scan { .. } => prune(&app, false, None, None, None),
+        Commands::Stats => show_stats(&app),
+        Commands::Check => check(&app),
+        Commands::Top { .. } if plain::is_plain() => Err(anyhow!(
+            "top is a full-screen view and has no plain mode; use `list --plain` instead"
+        )),
+        Commands::Top { interval } => match &app.target {
+            Target::Local(scan_options) => {
+                top::run_top(scan_options, Duration::from_secs(interval))
+            }
+            Target::Remote(..) => unreachable!("top is forwarded to the remote host"),
+        },
+        Commands::Serve { socket } => {
+            let socket = match socket {
+                Some(socket) => socket,
+                None => cache::get_cache_dir()?.join("venvpruner.sock"),
+            };
+            serve::serve(&app, &socket)
+        }
+        Commands::Slim {
+            paths,
+            dry_run,
+            aggressive,
+        } => slim(&app, &paths, dry_run, aggressive),
+        Commands::Repair {
+            path,
+            python,
+            upgrade_deps,
+        } => {
+            let interpreter = repair::repair_venv(&path, python.as_deref(), upgrade_deps)?;
+            print_success_message(&format!(
+                "Repaired {} using {}",
+                path.display(),
+                interpreter.display()
+            ));
+            Ok(())
+        }
+        Commands::Upgrade {
+            paths,
+            python,
+            no_pin,
+            keep_backup,
+            yes,
+        } => upgrade(&app, &paths, &python, !no_pin, keep_backup, yes),
+        Commands::Watch { interval, prune } => watch(&app, Duration::from_secs(interval), prune),
+        Commands::History { days, chart } => show_history(days, chart),
+        Commands::Diff { growth_threshold } => show_diff(&app, growth_threshold),
+        Commands::Inventory { output } => export_inventory(&app, output.as_deref()),
+        Commands::Licenses { export } => show_licenses(&app, export.as_deref()),
+        Commands::WhichEnv { package, version } => which_env(&app, &package, version.as_deref()),
+        Commands::DiffEnv { a, b } => diff_env(&a, &b),
+        Commands::Note { path, text, clear } => note(&path, text.as_deref(), clear),
+        Commands::Selection { action } => selection(&app, action),
+        Commands::Tag { action } => tag(action),
+        Commands::Delete {
+            paths,
+            stdin,
+            yes,
+            override_protection,
+            emit_script,
+            emit_plan,
+        } => {
+            let paths = match stdin {
+                true => {
+                    let mut input = Vec::new();
+                    std::io::stdin()
+                        .read_to_end(&mut input)
+                        .context("Failed to read stdin")?;
+                    parse_delete_targets(&input)?
+                }
+                false => paths,
+            };
+            delete(
+                &app,
+                &paths,
+                yes,
+                override_protection,
+                emit_script.as_deref(),
+                emit_plan.as_deref(),
+            )
+        }
+        Commands::Apply { plan, resume, yes } => apply(&app, &plan, resume, yes),
+        Commands::Telemetry { action } => telemetry(&app, action),
+        Commands::SelfUpdate { check, yes } => self_update(check, yes),
+    };
+    timings::report();
+    progress::done(&result);
+    result
+}
+
+fn scan(app: &App) -> Result<Vec<VirtualEnv>> {
+    let spinner = get_spinner();
+    let start = Instant::now();
+
+    let venvs = app
+        .scan(&spinner)
+        .context("Failed to search for virtual environments")?;
+
+    let message = format!(
+        "Found {} virtual environments in {:.4}s",
+        venvs.len(),
+        start.elapsed().as_secs_f32()
+    );
+    match plain::is_plain() {
+        true => println!("{}", message),
+        false => spinner.finish_with_message(style(message).green().to_string()),
+    }
+    warn_if_over_budget(app, &venvs);
+
+    Ok(venvs)
+}
+
+fn warn_if_over_budget(app: &App, venvs: &[VirtualEnv]) {
+    // The budget applies to this machine only
+    let budget = match (&app.target, app.config.budget) {
+        (Target::Local(_), Some(budget)) => budget,
+        _ => return,
+    };
+    let total_size: u64 = venvs.iter().map(|venv| venv.venv_size).sum();
+    if total_size > budget.0 {
+        eprintln!(
+            "{}",
+            style(format!(
+                "Warning: virtual environments use {}, over the budget of {}",
+                format_size(total_size),
+                budget
+            ))
+            .red()
+            .bold()
+        );
+    }
+}
+
+fn watch(app: &App, interval: Duration, prune: bool) -> Result<()> {
+    if !app.config.notify.is_configured() {
+        eprintln!(
+            "{}",
+            style("Warning: no notification channel is configured, alerts are only printed")
+                .yellow()
+        );
+    }
+
+    let mut budget_state = watch::BudgetState::default();
+    loop {
+        let mut venvs = app.scan(&ProgressBar::hidden())?;
+        for event in budget_state.update(&app.config, &venvs) {
+            eprintln!("{}", style(&event.text).red().bold());
+            notify::send(&app.config.notify, &event);
+        }
+
+        if prune {
+            let selected =
+                policy::select_over_budget(&app.config.budgets, &venvs, &app.config.safety);
+            venvs.retain(|venv| selected.contains(&venv.path));
+            if !venvs.is_empty() {
+                let result = app.delete(&venvs);
+                let reclaimed: u64 = venvs.iter().map(|venv| venv.reclaimable_size()).sum();
+                let (event, text) = match &result {
+                    Ok(()) => (
+                        "prune_completed",
+                        format!(
+                            "Scheduled prune deleted {} environments, reclaiming {}",
+                            venvs.len(),
+                            format_size(reclaimed)
+                        ),
+                    ),
+                    Err(err) => ("prune_failed", format!("Scheduled prune failed: {:#}", err)),
+                };
+                // A failed prune is reported and retried after the next scan
+                if result.is_err() {
+                    eprintln!("{}", style(&text).red().bold());
+                }
+                notify::send(&app.config.notify, &notify::Event::new(event, text));
+            }
+        }
+
+        std::thread::sleep(interval);
+    }
+}
 
-    let selected = MultiSelect::new("Select the virtualenvs to delete:", options).prompt();
-    let selected = match selected {
-        Ok(selected) => selected,
-        Err(_) => {
-            return Ok(vec![]);
+fn check(app: &App) -> Result<()> {
+    let budget = match app.config.budget {
+        Some(budget) => budget,
+        None => {
+            return Err(anyhow!(
+                "No budget is configured in {}",
+                config::get_config_path()?.display()
+            ))
         }
     };
+    // scan() has already printed the details of any overrun
+    let venvs = scan(app)?;
+    let total_size: u64 = venvs.iter().map(|venv| venv.venv_size).sum();
+    if total_size > budget.0 {
+        return Err(anyhow!("Budget exceeded"));
+    }
+    print_success_message(&format!(
+        "Virtual environments use {} of the {} budget",
+        format_size(total_size),
+        budget
+    ));
+    Ok(())
+}
 
-    let selected_venvs = selected
-        .iter()
-        .map(|option: &ListOption<&VirtualEnv>| (*option.value).clone())
-        .collect::<Vec<VirtualEnv>>();
+fn list(app: &App, output: OutputFormat) -> Result<()> {
+    let venvs = match output {
+        OutputFormat::Table => scan(app)?,
+        // Keep stdout clean for machine readable output
+        _ => {
+            let venvs = app.scan(&ProgressBar::hidden())?;
+            warn_if_over_budget(app, &venvs);
+            venvs
+        }
+    };
 
-    Ok(selected_venvs)
+    match output {
+        OutputFormat::Table => {
+            for venv in &venvs {
+                println!("{}", format_venv(app, venv));
+            }
+            let total_size: u64 = venvs.iter().map(|venv| venv.venv_size).sum();
+            print_info_message(&format!(
+                "Total size of all virtual environments: {}",
+                format_size(total_size)
+            ));
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&venvs)?);
+        }
+        OutputFormat::Jsonl => {
+            for venv in &venvs {
+                println!("{}", serde_json::to_string(venv)?);
+            }
+        }
+    }
+    Ok(())
 }
 
-fn print_success_message(message: &str) {
-    println!("{}", style(message).green());
+fn show_stats(app: &App) -> Result<()> {
+    let venvs = scan(app)?;
+    let site_packages = stats::get_system_site_packages();
+    println!();
+    let budgets = policy::get_budget_usage(&app.config.budgets, &venvs);
+    let cow_usage = cow::get_cow_usage(&venvs);
+    stats::print_stats(&venvs, &site_packages, &budgets, &cow_usage);
+    Ok(())
 }
 
-fn print_info_message(message: &str) {
-    println!("{}", style(message).cyan());
+/// A `list --output jsonl` record, of which only the path matters
+#[derive(serde::Deserialize)]
+struct DeleteTarget {
+    #[serde(with = "paths")]
+    path: PathBuf,
 }
 
-fn confirm_deletion() -> Result<bool> {
-    Confirm::new("Are you sure you want to delete the selected virtual environments?")
+/// Reads paths to delete, one per line, as raw bytes so that paths which
+/// aren't valid UTF-8 survive
+fn parse_delete_targets(input: &[u8]) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for line in input
+        .split(|&byte| byte == b'\n')
+        .map(<[u8]>::trim_ascii)
+        .filter(|line| !line.is_empty())
+    {
+        let path = match line.starts_with(b"{") {
+            true => {
+                let text = String::from_utf8_lossy(line);
+                serde_json::from_slice::<DeleteTarget>(line)
+                    .with_context(|| format!("Invalid JSON record with a \"path\": {}", text))?
+                    .path
+            }
+            false => paths::from_bytes(line),
+        };
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+
+    if paths.is_empty() {
+        return Err(anyhow!("No paths to delete were given on stdin"));
+    }
+    Ok(paths)
+}
+
+fn show_history(days: i64, chart: bool) -> Result<()> {
+    let entries = history::load_history()?;
+    match chart && !plain::is_plain() {
+        true => history::print_chart(&entries, days),
+        false => history::print_history(&entries, days),
+    }
+    Ok(())
+}
+
+fn show_diff(app: &App, growth_threshold: f64) -> Result<()> {
+    let previous = cache::load_last_scan()?;
+    let venvs = scan(app)?;
+    let previous = match previous {
+        Some(previous) => previous,
+        None => {
+            print_info_message("No previous scan to compare with; this scan has been saved.");
+            return Ok(());
+        }
+    };
+
+    print_info_message(&format!(
+        "Changes since the scan on {}:",
+        previous.timestamp.format("%Y-%m-%d %H:%M")
+    ));
+    println!();
+    diff::print_diff(&diff::diff_scans(&previous.venvs, &venvs, growth_threshold));
+    Ok(())
+}
+
+fn delete(
+    app: &App,
+    paths: &[PathBuf],
+    yes: bool,
+    override_protection: bool,
+    emit_script: Option<&Path>,
+    emit_plan: Option<&Path>,
+) -> Result<()> {
+    let venvs = load_venvs(paths)?;
+    app.check_within_only(&venvs)?;
+
+    for venv in &venvs {
+        println!("{}", format_venv(app, venv));
+    }
+    if let Some(script) = emit_script {
+        return emit_cleanup_script(script, &venvs);
+    }
+    if let Some(plan) = emit_plan {
+        return emit_deletion_plan(plan, &venvs);
+    }
+    if yes && !override_protection {
+        check_not_protected(app, &venvs)?;
+    }
+    if !yes && !confirm_deletion(app, &venvs)? {
+        print_info_message("Deletion cancelled.");
+        return Ok(());
+    }
+    app.delete(&venvs)
+}
+
+fn slim(app: &App, paths: &[PathBuf], dry_run: bool, aggressive: bool) -> Result<()> {
+    let options = slim::SlimOptions::new(aggressive, &app.config.slim);
+    let venvs = match paths.is_empty() {
+        true => {
+            let mut venvs = scan(app)?;
+            venvs.retain(|venv| venv.manager.is_environment());
+            select_venvs(app, "Select the virtualenvs to slim:", &venvs, &[])?
+        }
+        false => load_venvs(paths)?,
+    };
+
+    let mut total_freed = 0;
+    for venv in &venvs {
+        let plan = slim::plan_slim(&venv.path, &options);
+        let freed = match dry_run {
+            true => plan.size,
+            false => slim::apply_slim(&plan)?,
+        };
+        total_freed += freed;
+        println!(
+            "{} - {} ({} items)",
+            venv.name,
+            format_size(freed),
+            plan.paths.len()
+        );
+    }
+
+    let verb = match dry_run {
+        true => "Would free",
+        false => "Freed",
+    };
+    print_success_message(&format!(
+        "{} {} in {} virtual environments",
+        verb,
+        format_size(total_freed),
+        venvs.len()
+    ));
+    Ok(())
+}
+
+fn upgrade(
+    app: &App,
+    paths: &[PathBuf],
+    python: &Path,
+    pin: bool,
+    keep_backup: bool,
+    yes: bool,
+) -> Result<()> {
+    let version = upgrade::get_interpreter_version(python)?;
+    let venvs = match paths.is_empty() {
+        true => {
+            // Preselect everything on an older Python than the new interpreter
+            let mut venvs = scan(app)?;
+            venvs.retain(|venv| venv.manager.is_environment());
+            let new_minor = upgrade::parse_minor_version(&version);
+            let older: Vec<PathBuf> = venvs
+                .iter()
+                .filter(|venv| upgrade::parse_minor_version(&venv.python_version) < new_minor)
+                .map(|venv| venv.path.clone())
+                .collect();
+            let message = format!("Select the virtualenvs to upgrade to Python {}:", version);
+            select_venvs(app, &message, &venvs, &older)?
+        }
+        false => load_venvs(paths)?,
+    };
+    if venvs.is_empty() {
+        print_info_message("No virtual environments selected for upgrade.");
+        return Ok(());
+    }
+
+    for venv in &venvs {
+        println!("{}", format_venv(app, venv));
+    }
+    if !yes {
+        let confirmed = Confirm::new(&format!(
+            "Recreate these environments with Python {}?",
+            version
+        ))
         .with_default(false)
-        .prompt()
-        .map_err(|e| anyhow::anyhow!(e))
+        .prompt()?;
+        if !confirmed {
+            print_info_message("Upgrade cancelled.");
+            return Ok(());
+        }
+    }
+
+    let mut failed = 0;
+    for venv in &venvs {
+        print_info_message(&format!("Upgrading {}...", venv.path.display()));
+        match upgrade::upgrade_venv(&venv.path, python, pin, keep_backup) {
+            Ok(()) => print_success_message(&format!(
+                "Upgraded {} from Python {} to {}",
+                venv.path.display(),
+                venv.python_version,
+                version
+            )),
+            Err(err) => {
+                eprintln!("{} {:#}", style("Failed to upgrade:").red(), err);
+                failed += 1;
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(anyhow!(
+            "{} of {} environments could not be upgraded and were left as they were",
+            failed,
+            venvs.len()
+        )),
+    }
 }
 
-fn delete_venvs(venvs: &[VirtualEnv]) -> Result<()> {
-    // Provide a custom bar style
-    let pb = ProgressBar::new(venvs.len() as u64);
-    let mut total_size: u64 = 0;
-    pb.set_style(
-        ProgressStyle::with_template(
-            "[{elapsed_precise}] [{bar:40.cyan/blue}] ({pos}/{len}, ETA {eta}) {msg}",
-        )
-        .unwrap(),
+/// Every environment with its installed packages
+fn get_inventory(venvs: Vec<VirtualEnv>) -> Vec<(VirtualEnv, Vec<packages::Package>)> {
+    venvs
+        .into_par_iter()
+        .filter(|venv| venv.manager.is_environment())
+        .map(|venv| {
+            let packages = packages::get_installed_packages(&venv.path);
+            (venv, packages)
+        })
+        .collect()
+}
+
+fn which_env(app: &App, package: &str, version: Option<&str>) -> Result<()> {
+    let name = packages::normalize_name(package);
+    let mut matches: Vec<(VirtualEnv, String)> = get_inventory(scan(app)?)
+        .into_iter()
+        .filter_map(|(venv, packages)| {
+            let found = packages.into_iter().find(|installed| {
+                packages::normalize_name(&installed.name) == name
+                    && version.is_none_or(|pattern| {
+                        packages::version_matches(&installed.version, pattern)
+                    })
+            })?;
+            Some((venv, found.version))
+        })
+        .collect();
+    matches.sort_by_key(|(venv, _)| std::cmp::Reverse(venv.venv_size));
+
+    if matches.is_empty() {
+        print_info_message(&format!("No environment has {} installed.", package));
+        return Ok(());
+    }
+    for (venv, installed_version) in &matches {
+        println!(
+            "{:>12}  {}  {}",
+            installed_version,
+            format_size(venv.venv_size),
+            venv.path.display()
+        );
+    }
+    let total_size: u64 = matches.iter().map(|(venv, _)| venv.venv_size).sum();
+    print_info_message(&format!(
+        "{} environments with {}, using {} in total",
+        matches.len(),
+        package,
+        format_size(total_size)
+    ));
+    Ok(())
+}
+
+fn show_licenses(app: &App, export: Option<&Path>) -> Result<()> {
+    let inventory = get_inventory(scan(app)?);
+    licenses::print_license_report(&licenses::aggregate_licenses(&inventory));
+    if let Some(path) = export {
+        licenses::write_license_csv(path, &inventory)?;
+        print_success_message(&format!(
+            "Exported the license report to {}",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+fn export_inventory(app: &App, output: Option<&Path>) -> Result<()> {
+    // Keep stdout clean for the SBOM
+    let inventory = get_inventory(app.scan(&ProgressBar::hidden())?);
+    let bom = inventory::build_cyclonedx(&inventory, &notify::get_hostname());
+    let bom = serde_json::to_string_pretty(&bom)?;
+    match output {
+        Some(path) => {
+            fs::write(path, bom).with_context(|| format!("Failed to write {}", path.display()))?;
+            print_success_message(&format!(
+                "Wrote an inventory of {} environments to {}",
+                inventory.len(),
+                path.display()
+            ));
+        }
+        None => println!("{}", bom),
+    }
+    Ok(())
+}
+
+fn diff_env(a: &Path, b: &Path) -> Result<()> {
+    for path in [a, b] {
+        if !is_venv_root(path) {
+            return Err(anyhow!("Not a virtual environment: {}", path.display()));
+        }
+    }
+    let diff = diff_env::diff_packages(
+        &packages::get_installed_packages(a),
+        &packages::get_installed_packages(b),
     );
+    diff_env::print_package_diff(&diff, &a.display().to_string(), &b.display().to_string());
+    Ok(())
+}
 
-    for venv in venvs {
-        pb.set_message(format!(
-            "Deleting virtual environment at: {}",
-            &venv.path.display()
+fn note(path: &Path, text: Option<&str>, clear: bool) -> Result<()> {
+    let mut metadata = metadata::Metadata::load()?;
+    match (text, clear) {
+        (_, true) => {
+            match metadata.remove_note(path) {
+                Some(_) => {
+                    print_success_message(&format!("Removed the note on {}", path.display()))
+                }
+                None => print_info_message(&format!("{} has no note.", path.display())),
+            }
+            metadata.save()
+        }
+        (Some(text), false) => {
+            if !is_venv_root(path) {
+                return Err(anyhow!("Not a virtual environment: {}", path.display()));
+            }
+            metadata.set_note(path, text);
+            metadata.save()?;
+            print_success_message(&format!("Saved the note on {}", path.display()));
+            Ok(())
+        }
+        (None, false) => {
+            match metadata.get_note(path) {
+                Some(note) => println!(
+                    "{} {}",
+                    note.text,
+                    style(format!("({})", note.updated.format("%Y-%m-%d"))).dim()
+                ),
+                None => print_info_message(&format!("{} has no note.", path.display())),
+            }
+            Ok(())
+        }
+    }
+}
+
+fn selection(app: &App, action: SelectionAction) -> Result<()> {
+    let mut selections = selections::Selections::load()?;
+    match action {
+        SelectionAction::Save { name } => {
+            let venvs = scan(app)?;
+            let preselected = selections
+                .get(&name)
+                .map(|selection| selection.paths.clone())
+                .unwrap_or_default();
+            let selected = select_venvs(
+                app,
+                &format!("Select the virtualenvs for {}:", name),
+                &venvs,
+                &preselected,
+            )?;
+            selections.set(
+                &name,
+                selected.iter().map(|venv| venv.path.clone()).collect(),
+            );
+            selections.save()?;
+            print_success_message(&format!(
+                "Saved {} environments as {}; run `venvpruner prune --selection {}` to review and delete them",
+                selected.len(),
+                name,
+                name
+            ));
+        }
+        SelectionAction::List => {
+            for (name, selection) in selections.entries() {
+                println!(
+                    "{}  {} environments {}",
+                    name,
+                    selection.paths.len(),
+                    style(format!("({})", selection.updated.format("%Y-%m-%d"))).dim()
+                );
+            }
+        }
+        SelectionAction::Show { name } => {
+            let selection = selections
+                .get(&name)
+                .ok_or_else(|| anyhow!("No selection named {}", name))?;
+            for path in &selection.paths {
+                match path.exists() {
+                    true => println!("{}", path.display()),
+                    false => println!("{} {}", path.display(), style("(gone)").dim()),
+                }
+            }
+        }
+        SelectionAction::Remove { name } => match selections.remove(&name) {
+            Some(_) => {
+                selections.save()?;
+                print_success_message(&format!("Removed the selection {}", name));
+            }
+            None => print_info_message(&format!("No selection named {}.", name)),
+        },
+    }
+    Ok(())
+}
+
+fn tag(action: TagAction) -> Result<()> {
+    let mut metadata = metadata::Metadata::load()?;
+    match action {
+        TagAction::Add { path, tags } => {
+            if !is_venv_root(&path) {
+                return Err(anyhow!("Not a virtual environment: {}", path.display()));
+            }
+            metadata.add_tags(&path, &tags);
+            metadata.save()?;
+            print_success_message(&format!("Tagged {}", path.display()));
+        }
+        TagAction::Remove { path, tags } => {
+            let removed = metadata.remove_tags(&path, &tags);
+            metadata.save()?;
+            print_success_message(&format!("Removed {} tags from {}", removed, path.display()));
+        }
+        TagAction::List { path: Some(path) } => {
+            if let Some(entry) = metadata.get(&path) {
+                for tag in &entry.tags {
+                    println!("{}", tag);
+                }
+            }
+        }
+        TagAction::List { path: None } => {
+            for (path, entry) in metadata.entries() {
+                if !entry.tags.is_empty() {
+                    let tags: Vec<&str> = entry.tags.iter().map(String::as_str).collect();
+                    println!("{}  {}", path.display(), style(tags.join(", ")).cyan());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn emit_cleanup_script(path: &Path, venvs: &[VirtualEnv]) -> Result<()> {
+    script::write_cleanup_script(path, venvs)?;
+    print_success_message(&format!(
+        "Wrote a script removing {} virtual environments to {}",
+        venvs.len(),
+        path.display()
+    ));
+    Ok(())
+}
+
+fn emit_deletion_plan(path: &Path, venvs: &[VirtualEnv]) -> Result<()> {
+    plan::Plan::new(venvs).save(path)?;
+    print_success_message(&format!(
+        "Saved a plan deleting {} virtual environments to {}; run `venvpruner apply {}` to carry it out",
+        venvs.len(),
+        path.display(),
+        path.display()
+    ));
+    Ok(())
+}
+
+fn telemetry(app: &App, action: TelemetryAction) -> Result<()> {
+    let mut state = telemetry::TelemetryState::load()?;
+    let url = app.config.telemetry.url.as_deref();
+    match action {
+        TelemetryAction::Status => {
+            let status = match (state.enabled, telemetry::is_do_not_track()) {
+                (true, true) => "enabled, but suppressed by DO_NOT_TRACK",
+                (true, false) => "enabled",
+                (false, _) => "disabled",
+            };
+            println!("Telemetry is {}.", status);
+            match url {
+                Some(url) => println!("Reports are sent to {}.", url),
+                None => println!("No report URL is set (`url` in the [telemetry] config section)."),
+            }
+            if let Some(last_sent) = state.last_sent {
+                println!("Last report sent {}.", times::format_time(last_sent, false));
+            }
+            let venvs = cache::load_last_scan()?
+                .map(|cache| cache.venvs)
+                .unwrap_or_default();
+            let report = telemetry::build_report(&venvs, &history::load_history()?);
+            println!("A report built from the last scan would contain exactly:");
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        TelemetryAction::Enable => {
+            if url.is_none() {
+                return Err(anyhow!(
+                    "Set `url` in the [telemetry] section of {} to enable telemetry",
+                    config::get_config_path()?.display()
+                ));
+            }
+            state.enabled = true;
+            state.save()?;
+            print_success_message(
+                "Telemetry enabled. Run `venvpruner telemetry status` to see what is sent.",
+            );
+        }
+        TelemetryAction::Disable => {
+            state.enabled = false;
+            state.save()?;
+            print_success_message("Telemetry disabled. Nothing more will be sent.");
+        }
+    }
+    Ok(())
+}
+
+fn self_update(check: bool, yes: bool) -> Result<()> {
+    let release = update::get_latest_release()?;
+    if !update::is_newer(&release) {
+        print_success_message(&format!(
+            "venvpruner {} is up to date (latest release: {}).",
+            env!("CARGO_PKG_VERSION"),
+            release.tag_name
         ));
-        fs::remove_dir_all(&venv.path)
-            .with_context(|| format!("Failed to delete {}", venv.path.display()))?;
-        pb.inc(1);
-        total_size += venv.venv_size;
-    }
-    let total_size_hr = human_bytes(total_size as f32);
-    pb.finish_with_message(format!(
-        "All selected virtual environments have been deleted. \n\n Total size reclaimed: {}",
-        total_size_hr
+        return Ok(());
+    }
+    print_info_message(&format!(
+        "venvpruner {} is available (installed: {}).",
+        release.tag_name,
+        env!("CARGO_PKG_VERSION")
+    ));
+    if check {
+        return Ok(());
+    }
+    let proceed = yes
+        || Confirm::new(&format!("Update to {}?", release.tag_name))
+            .with_default(true)
+            .prompt()?;
+    if !proceed {
+        print_info_message("Update cancelled.");
+        return Ok(());
+    }
+    let exe = update::install(&release)?;
+    print_success_message(&format!(
+        "Updated {} to {} (checksum verified).",
+        exe.display(),
+        release.tag_name
     ));
     Ok(())
 }
 
-fn main() -> Result<()> {
-    let _cli = Cli::parse();
+fn apply(app: &App, path: &Path, resume: bool, yes: bool) -> Result<()> {
+    let mut plan = plan::Plan::load(path)?;
+    let done = plan.entries.iter().filter(|entry| entry.done).count();
+    if done > 0 && !resume {
+        return Err(anyhow!(
+            "{} of the {} environments in {} were already deleted; pass --resume to continue",
+            done,
+            plan.entries.len(),
+            path.display()
+        ));
+    }
 
-    let spinner = get_spinner();
+    // Environments removed by other means since the plan was made count as done
+    for venv in plan.pending() {
+        if !venv.path.exists() {
+            print_info_message(&format!("Already gone: {}", venv.path.display()));
+            plan.mark_done(&venv);
+        }
+    }
+    plan.save(path)?;
 
-    let start = Instant::now();
+    let pending = plan.pending();
+    if pending.is_empty() {
+        print_success_message("Nothing left to delete in this plan.");
+        return Ok(());
+    }
+    app.check_within_only(&pending)?;
+    for venv in &pending {
+        println!("{}", format_venv(app, venv));
+    }
+    if yes {
+        check_not_protected(app, &pending)?;
+    }
+    if !yes && !confirm_deletion(app, &pending)? {
+        print_info_message("Deletion cancelled.");
+        return Ok(());
+    }
 
-    loop {
-        let mut venvs = get_venvs().context("Failed to search for virtual environments")?;
+    delete_venvs(&pending, &app.delete_options, |venv| {
+        plan.mark_done(venv);
+        if let Err(err) = plan.save(path) {
+            eprintln!("Failed to record progress in the plan: {}", err);
+        }
+    })
+}
 
-        venvs.sort_by(|a, b| b.venv_size.cmp(&a.venv_size));
+fn print_recommendations(app: &App, recommendations: &[recommend::Recommendation]) {
+    for recommendation in recommendations {
+        println!("{}", format_venv(app, &recommendation.venv));
+        for reason in &recommendation.reasons {
+            println!("    - {}", reason);
+        }
+    }
+}
 
-        // total size
-        let total_size: u64 = venvs.iter().map(|venv| venv.venv_size).sum();
-        let total_size_str = human_bytes(total_size as f32);
+fn recommend(app: &App, unused_for: chrono::Duration, emit_plan: Option<&Path>) -> Result<()> {
+    let venvs = scan(app)?;
+    let recommendations = recommend::recommend(&venvs, unused_for, &app.config);
+    if recommendations.is_empty() {
+        print_success_message("Nothing to recommend deleting.");
+        return Ok(());
+    }
 
-        spinner.finish_with_message(
-            style(format!(
-                "Found {} virtual environments in {}",
-                venvs.len(),
-                format!("{:.4}s", start.elapsed().as_secs_f32())
-            ))
-            .green()
-            .to_string(),
+    print_recommendations(app, &recommendations);
+    let recommended: Vec<VirtualEnv> = recommendations
+        .into_iter()
+        .map(|recommendation| recommendation.venv)
+        .collect();
+    let total_size: u64 = recommended.iter().map(|venv| venv.reclaimable_size()).sum();
+    print_info_message(&format!(
+        "Recommended deleting {} environments, reclaiming {}.",
+        recommended.len(),
+        format_size(total_size)
+    ));
+
+    if let Some(plan) = emit_plan {
+        return emit_deletion_plan(plan, &recommended);
+    }
+
+    const ACCEPT: &str = "Delete all recommended environments";
+    const EDIT: &str = "Edit the selection";
+    const CANCEL: &str = "Cancel";
+    let selected = match Select::new("What now?", vec![ACCEPT, EDIT, CANCEL]).prompt() {
+        Ok(ACCEPT) => recommended,
+        Ok(EDIT) => {
+            let paths: Vec<PathBuf> = recommended.iter().map(|venv| venv.path.clone()).collect();
+            select_venvs(app, "Select the virtualenvs to delete:", &venvs, &paths)?
+        }
+        _ => vec![],
+    };
+    if selected.is_empty() {
+        print_info_message("No virtual environments selected for deletion.");
+        return Ok(());
+    }
+    if !confirm_deletion(app, &selected)? {
+        print_info_message("Deletion cancelled.");
+        return Ok(());
+    }
+    app.delete(&selected)
+}
+
+fn clean(
+    app: &App,
+    max: Option<size::ByteSize>,
+    unused_for: chrono::Duration,
+    yes: bool,
+) -> Result<()> {
+    let venvs = scan(app)?;
+    let mut recommendations = recommend::recommend(&venvs, unused_for, &app.config);
+    if let Some(max) = max {
+        recommendations = recommend::take_up_to(recommendations, max.0);
+    }
+    if recommendations.is_empty() {
+        print_success_message("Nothing to clean up.");
+        return Ok(());
+    }
+
+    print_recommendations(app, &recommendations);
+    let selected: Vec<VirtualEnv> = recommendations
+        .into_iter()
+        .map(|recommendation| recommendation.venv)
+        .collect();
+    if !yes && !confirm_deletion(app, &selected)? {
+        print_info_message("Deletion cancelled.");
+        return Ok(());
+    }
+    app.delete(&selected)
+}
+
+fn clean_caches(dry_run: bool) -> Result<()> {
+    let cleaners = caches::get_cleaners();
+    if cleaners.is_empty() {
+        print_info_message("No Python tools with a cache-clean command found.");
+        return Ok(());
+    }
+    if dry_run {
+        for cleaner in &cleaners {
+            println!("{}", cleaner.command_line());
+        }
+        return Ok(());
+    }
+
+    // (tool, size before, size after)
+    let mut report: Vec<(&str, u64, u64)> = Vec::new();
+    for cleaner in &cleaners {
+        if !report.iter().any(|(tool, ..)| *tool == cleaner.tool) {
+            report.push((cleaner.tool, caches::get_tool_cache_size(cleaner.tool), 0));
+        }
+        print_info_message(&format!("Running {}", cleaner.command_line()));
+        if let Err(err) = cleaner.run() {
+            eprintln!("{}", style(format!("{:#}", err)).yellow());
+        }
+    }
+
+    println!();
+    let mut total_freed = 0;
+    for (tool, before, after) in &mut report {
+        *after = caches::get_tool_cache_size(tool);
+        let freed = before.saturating_sub(*after);
+        total_freed += freed;
+        println!(
+            "{:<8} {:>12} -> {:>12} (freed {})",
+            tool,
+            format_size(*before),
+            format_size(*after),
+            format_size(freed)
         );
+    }
+    print_success_message(&format!(
+        "Total size reclaimed from caches: {}",
+        format_size(total_freed)
+    ));
+    Ok(())
+}
+
+fn prune(
+    app: &App,
+    policy: bool,
+    selection: Option<&str>,
+    emit_script: Option<&Path>,
+    emit_plan: Option<&Path>,
+) -> Result<()> {
+    let mut saved = match selection {
+        Some(name) => {
+            let selections = selections::Selections::load()?;
+            let selection = selections
+                .get(name)
+                .ok_or_else(|| anyhow!("No selection named {}", name))?;
+            selection.paths.clone()
+        }
+        None => vec![],
+    };
+    loop {
+        let venvs = scan(app)?;
 
+        // total size
+        let total_size: u64 = venvs.iter().map(|venv| venv.venv_size).sum();
+        let total_size_str = format_size(total_size);
         print_info_message(&format!(
             "Total size of all virtual environments: {}",
             total_size_str
         ));
 
+        let shared_size: u64 = venvs.iter().map(|venv| venv.shared_size).sum();
+        if shared_size > 0 {
+            print_info_message(&format!(
+                "Of which shared with other files (APFS clones): {}",
+                format_size(shared_size)
+            ));
+        }
+
         if venvs.is_empty() {
             print_info_message("No virtual environments found.");
             break;
         }
 
-        let selected_venvs = select_venvs_to_delete(&venvs)?;
+        let on_cow = venvs
+            .iter()
+            .filter(|venv| cow::get_cow_filesystem(&venv.path).is_some())
+            .count();
+        if on_cow > 0 {
+            print_info_message(&format!(
+                "{} environments are on copy-on-write filesystems; data shared with snapshots or reflinked copies is not freed by deleting them.",
+                on_cow
+            ));
+        }
+
+        let clones = venvs.iter().filter(|venv| venv.clone_of.is_some()).count();
+        if clones > 0 {
+            print_info_message(&format!(
+                "{} environments are redundant clones or copies of another one and can likely go.",
+                clones
+            ));
+        }
+
+        let mut preselected = match policy {
+            true => policy::select_over_budget(&app.config.budgets, &venvs, &app.config.safety),
+            false => vec![],
+        };
+        let missing = saved
+            .iter()
+            .filter(|path| !venvs.iter().any(|venv| &venv.path == *path))
+            .count();
+        if missing > 0 {
+            print_info_message(&format!(
+                "{} environments of the selection were not found and may be gone already.",
+                missing
+            ));
+            saved.retain(|path| venvs.iter().any(|venv| &venv.path == path));
+        }
+        preselected.extend(saved.iter().cloned());
+        if policy && preselected.is_empty() {
+            print_info_message("All projects are within their budgets.");
+        }
+        let selected_venvs = select_venvs(
+            app,
+            "Select the virtualenvs to delete:",
+            &venvs,
+            &preselected,
+        )?;
 
         match selected_venvs.is_empty() {
             true => {
@@ -136,14 +2478,21 @@ fn main() -> Result<()> {
                 break;
             }
             false => {
-                if !confirm_deletion()? {
+                if let Some(script) = emit_script {
+                    return emit_cleanup_script(script, &selected_venvs);
+                }
+                if let Some(plan) = emit_plan {
+                    return emit_deletion_plan(plan, &selected_venvs);
+                }
+                if !confirm_deletion(app, &selected_venvs)? {
                     print_info_message("Deletion cancelled.");
                     break;
                 }
             }
         }
 
-        delete_venvs(&selected_venvs)?;
+        app.delete(&selected_venvs)?;
+        saved.retain(|path| !selected_venvs.iter().any(|venv| &venv.path == path));
 
         // Update the cache
         let remaining_venvs: Vec<VirtualEnv> = venvs
@@ -170,9 +2519,59 @@ fn main() -> Result<()> {
 }
 
 fn get_spinner() -> Arc<ProgressBar> {
+    if progress::is_json() {
+        progress::emit(progress::Event::ScanStarted);
+        return Arc::new(ProgressBar::hidden());
+    }
+    if plain::is_plain() {
+        println!("Searching for virtual environments...");
+        return Arc::new(ProgressBar::hidden());
+    }
     let spinner = Arc::new(ProgressBar::new_spinner());
     spinner.set_style(ProgressStyle::with_template("{spinner:.green} {msg}").unwrap());
     spinner.set_message("Searching for virtual environments...");
     spinner.enable_steady_tick(Duration::from_millis(100));
     spinner
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_delete_targets() {
+        let input = r#"/home/me/.virtualenvs/old
+
+{"path":"/home/me/.cache/pypoetry/virtualenvs/proj-py3.9","name":"proj-py3.9"}
+/home/me/.virtualenvs/old
+"#;
+        let paths = parse_delete_targets(input.as_bytes()).expect("Failed to parse targets");
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/home/me/.virtualenvs/old"),
+                PathBuf::from("/home/me/.cache/pypoetry/virtualenvs/proj-py3.9"),
+            ]
+        );
+
+        assert!(parse_delete_targets(b"{\"name\":\"x\"}").is_err());
+        assert!(parse_delete_targets(b"\n\n").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_parse_non_utf8_delete_targets() {
+        use std::os::unix::ffi::OsStringExt;
+
+        // Paths that aren't UTF-8, as a line and as a JSON record
+        let paths = parse_delete_targets(b"/envs/caf\xe9\n{\"path\":[47,112,255]}\n")
+            .expect("Failed to parse targets");
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from(OsString::from_vec(b"/envs/caf\xe9".to_vec())),
+                PathBuf::from(OsString::from_vec(b"/p\xff".to_vec())),
+            ]
+        );
+    }
+}
@@ -0,0 +1,330 @@
+use crate::config::expand_home;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::{canonicalize, read_to_string};
+use std::path::{Path, PathBuf};
+
+/// The tool that created (and usually manages) a virtual environment
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Manager {
+    Pipx,
+    Virtualenvwrapper,
+    Pipenv,
+    Poetry,
+    Conda,
+    /// A conda-format environment created by micromamba or mamba
+    Mamba,
+    Pyenv,
+    /// A Python installed by pyenv, with `--include-pyenv-versions`
+    #[serde(rename = "pyenv-version")]
+    PyenvVersion,
+    Asdf,
+    Canopy,
+    Pdm,
+    Hatch,
+    /// A test environment in a project's `.tox` directory
+    Tox,
+    /// A session environment in a project's `.nox` directory
+    Nox,
+    /// direnv's `layout python` environment in a project's `.direnv` directory
+    Direnv,
+    /// A PEP 582 `__pypackages__/<version>` directory of a project
+    Pep582,
+    /// A Rye tool environment, or a project environment created by Rye
+    Rye,
+    /// A Python installed by Rye in `~/.rye/py`
+    #[serde(rename = "rye-toolchain")]
+    RyeToolchain,
+    /// A `uv tool` environment, or a project environment created by uv
+    Uv,
+    /// A plain `python -m venv` / `virtualenv` environment
+    #[default]
+    Venv,
+    /// `node_modules`, with `--ecosystem node`
+    Node,
+    /// A Rust `target/` directory, with `--ecosystem cargo`
+    Cargo,
+    /// The Gradle cache, with `--ecosystem gradle`
+    Gradle,
+}
+
+impl Manager {
+    pub const ALL: [Manager; 23] = [
+        Manager::Pipx,
+        Manager::Virtualenvwrapper,
+        Manager::Pipenv,
+        Manager::Poetry,
+        Manager::Conda,
+        Manager::Mamba,
+        Manager::Pyenv,
+        Manager::PyenvVersion,
+        Manager::Asdf,
+        Manager::Canopy,
+        Manager::Pdm,
+        Manager::Hatch,
+        Manager::Tox,
+        Manager::Nox,
+        Manager::Direnv,
+        Manager::Pep582,
+        Manager::Rye,
+        Manager::RyeToolchain,
+        Manager::Uv,
+        Manager::Venv,
+        Manager::Node,
+        Manager::Cargo,
+        Manager::Gradle,
+    ];
+
+    /// Whether this manages Python environments rather than another
+    /// ecosystem's caches
+    pub fn is_python(self) -> bool {
+        !matches!(self, Manager::Node | Manager::Cargo | Manager::Gradle)
+    }
+
+    /// Whether this is a Python environment, rather than another ecosystem's
+    /// cache, a Python installation or a bare package directory
+    pub fn is_environment(self) -> bool {
+        self.is_python()
+            && !matches!(
+                self,
+                Manager::PyenvVersion | Manager::RyeToolchain | Manager::Pep582
+            )
+    }
+}
+
+impl std::str::FromStr for Manager {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Manager::ALL
+            .into_iter()
+            .find(|manager| manager.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| anyhow::anyhow!("Unknown manager: {}", s))
+    }
+}
+
+/// Which managers' environments to show, from `--manager` and `--exclude-manager`
+#[derive(Debug, Clone, Default)]
+pub struct ManagerFilter {
+    /// Only these, unless empty
+    pub include: Vec<Manager>,
+    pub exclude: Vec<Manager>,
+}
+
+impl ManagerFilter {
+    pub fn matches(&self, manager: Manager) -> bool {
+        (self.include.is_empty() || self.include.contains(&manager))
+            && !self.exclude.contains(&manager)
+    }
+}
+
+impl fmt::Display for Manager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Manager::Pipx => "pipx",
+            Manager::Virtualenvwrapper => "virtualenvwrapper",
+            Manager::Pipenv => "pipenv",
+            Manager::Poetry => "poetry",
+            Manager::Conda => "conda",
+            Manager::Mamba => "mamba",
+            Manager::Pyenv => "pyenv",
+            Manager::PyenvVersion => "pyenv-version",
+            Manager::Asdf => "asdf",
+            Manager::Canopy => "canopy",
+            Manager::Pdm => "pdm",
+            Manager::Hatch => "hatch",
+            Manager::Tox => "tox",
+            Manager::Nox => "nox",
+            Manager::Direnv => "direnv",
+            Manager::Pep582 => "pep582",
+            Manager::Rye => "rye",
+            Manager::RyeToolchain => "rye-toolchain",
+            Manager::Uv => "uv",
+            Manager::Venv => "venv",
+            Manager::Node => "node",
+            Manager::Cargo => "cargo",
+            Manager::Gradle => "gradle",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Returns true if `path` contains `needle` as consecutive components
+fn has_components(path: &Path, needle: &[&str]) -> bool {
+    let components: Vec<&OsStr> = path.iter().collect();
+    components
+        .windows(needle.len())
+        .any(|window| window.iter().zip(needle).all(|(a, b)| *a == OsStr::new(b)))
+}
+
+/// Works out which tool manages the environment at `venv_root`, from
+/// markers inside it and the well-known location it lives in.
+pub fn detect_manager(venv_root: &Path) -> Manager {
+    if venv_root.join("conda-meta").is_dir() {
+        return match is_in_mamba_root(venv_root) {
+            true => Manager::Mamba,
+            false => Manager::Conda,
+        };
+    }
+
+    let locations: &[(&[&str], Manager)] = &[
+        (&["pipx", "venvs"], Manager::Pipx),
+        (&["pypoetry", "virtualenvs"], Manager::Poetry),
+        (&[".local", "share", "virtualenvs"], Manager::Pipenv),
+        (&[".virtualenvs"], Manager::Virtualenvwrapper),
+        (&[".pyenv", "versions"], Manager::Pyenv),
+        (&[".asdf", "installs", "python"], Manager::Asdf),
+        (&["Enthought", "Canopy"], Manager::Canopy),
+        (&["pdm", "venvs"], Manager::Pdm),
+        (&["hatch", "env", "virtual"], Manager::Hatch),
+        (&["uv", "tools"], Manager::Uv),
+        (&[".rye", "tools"], Manager::Rye),
+        (&[".tox"], Manager::Tox),
+        (&[".nox"], Manager::Nox),
+        (&[".direnv"], Manager::Direnv),
+        (&["__pypackages__"], Manager::Pep582),
+    ];
+
+    let by_location = locations
+        .iter()
+        .find(|(needle, _)| has_components(venv_root, needle))
+        .map(|(_, manager)| *manager);
+    match by_location {
+        Some(manager) => manager,
+        None if is_in_workon_home(venv_root) => Manager::Virtualenvwrapper,
+        // Rye creates its environments with uv, so check for it first
+        None if venv_root.join("rye-venv.json").is_file() => Manager::Rye,
+        None if is_uv_venv(venv_root) => Manager::Uv,
+        None => Manager::Venv,
+    }
+}
+
+/// micromamba's root prefix when moved with `$MAMBA_ROOT_PREFIX`
+pub fn get_mamba_root_prefix() -> Option<PathBuf> {
+    env::var_os("MAMBA_ROOT_PREFIX")
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| expand_home(Path::new(&dir)))
+}
+
+/// Whether the environment lives in the `envs` of micromamba's root prefix:
+/// `~/micromamba`, `~/.local/share/mamba` or `$MAMBA_ROOT_PREFIX`
+fn is_in_mamba_root(venv_root: &Path) -> bool {
+    let root = match venv_root.parent() {
+        Some(envs) if envs.file_name() == Some(OsStr::new("envs")) => envs.parent(),
+        _ => None,
+    };
+    let root = match root {
+        Some(root) => root,
+        None => return false,
+    };
+    has_components(root, &["micromamba"])
+        || has_components(root, &[".local", "share", "mamba"])
+        || get_mamba_root_prefix()
+            .and_then(|prefix| canonicalize(prefix).ok())
+            .is_some_and(|prefix| prefix == root)
+}
+
+/// virtualenvwrapper's `$WORKON_HOME`, when set to somewhere other than
+/// the default `~/.virtualenvs`
+pub fn get_workon_home() -> Option<PathBuf> {
+    env::var_os("WORKON_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| expand_home(Path::new(&dir)))
+}
+
+fn is_in_workon_home(venv_root: &Path) -> bool {
+    match (venv_root.parent(), get_workon_home()) {
+        (Some(parent), Some(workon_home)) => {
+            canonicalize(&workon_home).is_ok_and(|workon_home| workon_home == parent)
+        }
+        _ => false,
+    }
+}
+
+/// `uv venv` and `uv sync` record their version as `uv = ...` in `pyvenv.cfg`
+fn is_uv_venv(venv_root: &Path) -> bool {
+    read_to_string(venv_root.join("pyvenv.cfg")).is_ok_and(|cfg| {
+        cfg.lines()
+            .any(|line| line.split('=').next().is_some_and(|key| key.trim() == "uv"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_detect_manager() {
+        let cases = [
+            ("/home/me/.local/pipx/venvs/black", Manager::Pipx),
+            (
+                "/home/me/.cache/pypoetry/virtualenvs/proj-AbC123-py3.11",
+                Manager::Poetry,
+            ),
+            (
+                "/home/me/.local/share/virtualenvs/proj-a1B2c3D4",
+                Manager::Pipenv,
+            ),
+            ("/home/me/.virtualenvs/scratch", Manager::Virtualenvwrapper),
+            ("/home/me/.pyenv/versions/3.11.7/envs/tools", Manager::Pyenv),
+            ("/home/me/.local/share/uv/tools/ruff", Manager::Uv),
+            ("/home/me/.rye/tools/ruff", Manager::Rye),
+            (
+                "/home/me/.local/share/pdm/venvs/webapp-Fk3_xQ9a-3.11",
+                Manager::Pdm,
+            ),
+            (
+                "/home/me/.local/share/hatch/env/virtual/webapp/Fk3xQ9aB/default",
+                Manager::Hatch,
+            ),
+            ("/home/me/code/app/.tox/py311", Manager::Tox),
+            ("/home/me/code/app/.nox/tests-3-11", Manager::Nox),
+            ("/home/me/code/app/.direnv/python-3.11.7", Manager::Direnv),
+            ("/home/me/code/app/__pypackages__/3.11", Manager::Pep582),
+            ("/home/me/code/app/.venv", Manager::Venv),
+        ];
+        for (path, manager) in cases {
+            assert_eq!(detect_manager(Path::new(path)), manager, "{}", path);
+        }
+
+        let home = tempfile::tempdir().expect("Failed to create temporary directory");
+        for (env, manager) in [
+            ("micromamba/envs/sci", Manager::Mamba),
+            (".local/share/mamba/envs/sci", Manager::Mamba),
+            ("miniforge3/envs/sci", Manager::Conda),
+        ] {
+            let path = home.path().join(env);
+            std::fs::create_dir_all(path.join("conda-meta")).unwrap();
+            assert_eq!(detect_manager(&path), manager, "{}", env);
+        }
+
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+        std::fs::write(
+            dir.path().join("pyvenv.cfg"),
+            "home = /usr/bin\nimplementation = CPython\nuv = 0.4.18\nversion_info = 3.12.7\n",
+        )
+        .expect("Failed to write pyvenv.cfg");
+        assert_eq!(detect_manager(dir.path()), Manager::Uv);
+        std::fs::write(dir.path().join("rye-venv.json"), "{}").unwrap();
+        assert_eq!(detect_manager(dir.path()), Manager::Rye);
+    }
+
+    #[test]
+    pub fn test_manager_filter() {
+        let filter = ManagerFilter::default();
+        assert!(filter.matches(Manager::Pipx));
+
+        let filter = ManagerFilter {
+            include: vec!["Conda".parse().unwrap(), Manager::Poetry],
+            exclude: vec![Manager::Poetry],
+        };
+        assert!(filter.matches(Manager::Conda));
+        assert!(!filter.matches(Manager::Poetry));
+        assert!(!filter.matches(Manager::Pipx));
+    }
+}
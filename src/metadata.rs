@@ -0,0 +1,179 @@
+use crate::history::get_data_dir;
+use crate::venvs::VirtualEnv;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{canonicalize, read_to_string, write};
+use std::path::{Path, PathBuf};
+
+/// A free-form note attached to an environment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    pub text: String,
+    pub updated: DateTime<Local>,
+}
+
+/// What the user recorded about one environment
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<Note>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+}
+
+impl EnvMetadata {
+    fn is_empty(&self) -> bool {
+        self.note.is_none() && self.tags.is_empty()
+    }
+}
+
+/// Notes and tags of all environments, keyed by their canonical path
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Metadata(#[serde(with = "crate::paths::map")] BTreeMap<PathBuf, EnvMetadata>);
+
+/// `~/.local/share/venvpruner/metadata.json`
+fn get_metadata_path() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("metadata.json"))
+}
+
+/// Entries are keyed by canonical path, so any spelling of a path finds them
+fn get_key(path: &Path) -> PathBuf {
+    canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Tags are matched case-insensitively, like rules
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+impl Metadata {
+    pub fn load() -> Result<Metadata> {
+        let path = get_metadata_path()?;
+        if !path.exists() {
+            return Ok(Metadata::default());
+        }
+        let contents =
+            read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&contents).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = get_metadata_path()?;
+        write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&EnvMetadata> {
+        self.0.get(&get_key(path))
+    }
+
+    /// Every environment with metadata, by path
+    pub fn entries(&self) -> impl Iterator<Item = (&PathBuf, &EnvMetadata)> {
+        self.0.iter()
+    }
+
+    /// Applies `change` to the entry for `path`, dropping it if nothing is left
+    fn update<T>(&mut self, path: &Path, change: impl FnOnce(&mut EnvMetadata) -> T) -> T {
+        let key = get_key(path);
+        let entry = self.0.entry(key.clone()).or_default();
+        let result = change(entry);
+        if entry.is_empty() {
+            self.0.remove(&key);
+        }
+        result
+    }
+
+    pub fn get_note(&self, path: &Path) -> Option<&Note> {
+        self.get(path).and_then(|metadata| metadata.note.as_ref())
+    }
+
+    pub fn set_note(&mut self, path: &Path, text: &str) {
+        let note = Note {
+            text: text.to_string(),
+            updated: Local::now(),
+        };
+        self.update(path, |metadata| metadata.note = Some(note));
+    }
+
+    pub fn remove_note(&mut self, path: &Path) -> Option<Note> {
+        self.update(path, |metadata| metadata.note.take())
+    }
+
+    pub fn add_tags(&mut self, path: &Path, tags: &[String]) {
+        self.update(path, |metadata| {
+            metadata
+                .tags
+                .extend(tags.iter().map(|tag| normalize_tag(tag)))
+        });
+    }
+
+    /// Removes `tags` from `path`, returning how many it had
+    pub fn remove_tags(&mut self, path: &Path, tags: &[String]) -> usize {
+        self.update(path, |metadata| {
+            tags.iter()
+                .filter(|tag| metadata.tags.remove(&normalize_tag(tag)))
+                .count()
+        })
+    }
+
+    /// Fills in the `note` and `tags` of each environment
+    pub fn annotate(&self, venvs: &mut [VirtualEnv]) {
+        for venv in venvs {
+            let metadata = self.get(&venv.path);
+            venv.note = metadata
+                .and_then(|metadata| metadata.note.as_ref())
+                .map(|note| note.text.clone());
+            venv.tags = metadata
+                .map(|metadata| metadata.tags.iter().cloned().collect())
+                .unwrap_or_default();
+        }
+    }
+}
+
+/// Attaches the stored metadata to `venvs`; a broken metadata file only
+/// costs the notes and tags
+pub fn annotate_metadata(venvs: &mut [VirtualEnv]) {
+    match Metadata::load() {
+        Ok(metadata) => metadata.annotate(venvs),
+        Err(err) => eprintln!("Failed to load notes and tags: {:#}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_metadata() {
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+        let mut metadata = Metadata::default();
+        metadata.set_note(dir.path(), "training env for paper X");
+        metadata.add_tags(dir.path(), &["Experiments".to_string(), "gpu".to_string()]);
+
+        // Found through a different spelling of the same path
+        let dotted = dir.path().join(".");
+        assert_eq!(
+            metadata.get_note(&dotted).map(|note| note.text.as_str()),
+            Some("training env for paper X")
+        );
+
+        let mut venvs = vec![VirtualEnv {
+            path: dir.path().to_path_buf(),
+            ..Default::default()
+        }];
+        metadata.annotate(&mut venvs);
+        assert_eq!(venvs[0].note.as_deref(), Some("training env for paper X"));
+        assert_eq!(venvs[0].tags, vec!["experiments", "gpu"]);
+
+        assert!(metadata.remove_note(dir.path()).is_some());
+        assert_eq!(metadata.remove_tags(dir.path(), &["GPU".to_string()]), 1);
+        assert_eq!(
+            metadata.remove_tags(dir.path(), &["experiments".to_string()]),
+            1
+        );
+        // Nothing left, so the entry is gone
+        assert!(metadata.get(dir.path()).is_none());
+    }
+}
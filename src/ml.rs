@@ -0,0 +1,50 @@
+use crate::packages::get_site_packages_dirs;
+use crate::venvs::get_dir_size;
+use std::path::Path;
+
+/// Top-level site-packages directories of the heavyweight ML frameworks and
+/// the CUDA libraries they pull in (the `nvidia-*-cu12` wheels share `nvidia/`)
+const ML_PACKAGE_DIRS: &[&str] = &[
+    "torch",
+    "tensorflow",
+    "jax",
+    "jaxlib",
+    "nvidia",
+    "triton",
+    "tensorrt",
+    "cupy",
+];
+
+/// Bytes the ML frameworks installed in the environment at `venv_root` take up
+pub fn get_ml_size(venv_root: &Path) -> u64 {
+    get_site_packages_dirs(venv_root)
+        .iter()
+        .flat_map(|dir| ML_PACKAGE_DIRS.iter().map(move |name| dir.join(name)))
+        .filter(|path| path.is_dir())
+        .map(|path| get_dir_size(&path))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_get_ml_size() {
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+        let site_packages = dir.path().join("lib/python3.11/site-packages");
+        assert_eq!(get_ml_size(dir.path()), 0);
+
+        for (package, size) in [
+            ("torch/lib", 3000),
+            ("nvidia/cublas/lib", 2000),
+            ("requests", 500),
+        ] {
+            std::fs::create_dir_all(site_packages.join(package)).unwrap();
+            std::fs::write(site_packages.join(package).join("data"), vec![1u8; size]).unwrap();
+        }
+        let ml_size = get_ml_size(dir.path());
+        assert!(ml_size >= 5000);
+        assert!(ml_size < get_dir_size(dir.path()));
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// The `[notify]` section of the config file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct NotifyConfig {
+    /// URL to POST a JSON payload to (Slack incoming webhooks work as-is)
+    pub webhook: Option<String>,
+    /// Address to email through the local `sendmail`
+    pub email: Option<String>,
+}
+
+impl NotifyConfig {
+    pub fn is_configured(&self) -> bool {
+        self.webhook.is_some() || self.email.is_some()
+    }
+}
+
+/// Something worth telling the maintainers of a machine about
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Event {
+    /// Machine readable kind, e.g. `budget_exceeded`
+    pub event: &'static str,
+    /// Human readable summary, shown by Slack
+    pub text: String,
+    pub host: String,
+}
+
+impl Event {
+    pub fn new(event: &'static str, text: String) -> Event {
+        Event {
+            event,
+            text,
+            host: get_hostname(),
+        }
+    }
+}
+
+/// Name of this machine, for telling notifications from different hosts apart
+#[cfg(unix)]
+pub fn get_hostname() -> String {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer is valid for its whole length
+    let ret = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if ret != 0 {
+        return "unknown".to_string();
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(not(unix))]
+pub fn get_hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Sends `event` through every configured channel, reporting failures
+/// without stopping at the first one
+pub fn send(config: &NotifyConfig, event: &Event) {
+    if let Some(url) = &config.webhook {
+        if let Err(err) = post_webhook(url, event) {
+            eprintln!("Failed to send webhook notification: {}", err);
+        }
+    }
+    if let Some(address) = &config.email {
+        if let Err(err) = send_email(address, event) {
+            eprintln!("Failed to send email notification: {}", err);
+        }
+    }
+}
+
+fn post_webhook(url: &str, event: &Event) -> Result<()> {
+    let payload = serde_json::to_string(event)?;
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--max-time", "30"])
+        .args(["--header", "Content-Type: application/json"])
+        .args(["--data-binary", &payload, url])
+        .output()
+        .context("Failed to run curl")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "curl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+fn send_email(address: &str, event: &Event) -> Result<()> {
+    let mut child = Command::new("sendmail")
+        .args(["-t"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("Failed to run sendmail")?;
+    let message = format!(
+        "To: {}\nSubject: [venvpruner] {} on {}\n\n{}\n",
+        address, event.event, event.host, event.text
+    );
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("Failed to open sendmail stdin"))?
+        .write_all(message.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("sendmail exited with {}", status));
+    }
+    Ok(())
+}
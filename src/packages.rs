@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+/// A distribution installed in an environment, read from its metadata
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    /// Where the package was installed from, if not an index (PEP 610)
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Installed with `pip install -e`
+    #[serde(default)]
+    pub editable: bool,
+    /// Declared license, from the metadata's license expression, license
+    /// field or classifiers
+    #[serde(default)]
+    pub license: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DirectUrl {
+    url: String,
+    #[serde(default)]
+    dir_info: Option<DirInfo>,
+}
+
+#[derive(Deserialize)]
+struct DirInfo {
+    #[serde(default)]
+    editable: bool,
+}
+
+/// PEP 503 normalized project name, e.g. "Typing_Extensions" -> "typing-extensions"
+pub fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '-' | '_' | '.' => {
+                if !normalized.ends_with('-') {
+                    normalized.push('-');
+                }
+            }
+            c => normalized.push(c.to_ascii_lowercase()),
+        }
+    }
+    normalized
+}
+
+/// Whether `version` matches `pattern`, either exactly or as a release
+/// prefix: "1", "1.x" and "1.*" all match "1.13.1" but not "10.0"
+pub fn version_matches(version: &str, pattern: &str) -> bool {
+    let prefix = pattern.trim_end_matches(".x").trim_end_matches(".*");
+    version == prefix
+        || version
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// The site-packages directories of the environment at `venv_root`
+pub fn get_site_packages_dirs(venv_root: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = match read_dir(venv_root.join("lib")) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("python"))
+            .map(|entry| entry.path().join("site-packages"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    // Windows layout
+    dirs.push(venv_root.join("Lib/site-packages"));
+    dirs.retain(|dir| dir.is_dir());
+    dirs.sort();
+    dirs
+}
+
+/// Reads the name, version and license headers of a METADATA/PKG-INFO file
+pub fn parse_metadata(contents: &str) -> Option<Package> {
+    let mut name = None;
+    let mut version = None;
+    let mut expression = None;
+    let mut license = None;
+    let mut classifiers = Vec::new();
+    let mut license_is_text = false;
+    let mut in_license = false;
+    // Headers end at the first blank line, where the description begins
+    for line in contents.lines().take_while(|line| !line.trim().is_empty()) {
+        // Indented lines continue the previous header
+        if line.starts_with([' ', '\t']) {
+            license_is_text |= in_license;
+            continue;
+        }
+        in_license = line.starts_with("License:");
+        if let Some(value) = line.strip_prefix("Name:") {
+            name = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("Version:") {
+            version = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("License-Expression:") {
+            expression = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("License:") {
+            license = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("Classifier: License ::") {
+            // e.g. "OSI Approved :: MIT License"
+            if let Some(last) = value.rsplit("::").next() {
+                classifiers.push(last.trim().to_string());
+            }
+        }
+    }
+
+    // Some packages paste their whole license text into the License field
+    let license = license.filter(|license| {
+        !license_is_text && !license.is_empty() && license != "UNKNOWN" && license.len() <= 100
+    });
+    let classifiers = (!classifiers.is_empty()).then(|| classifiers.join(" OR "));
+    Some(Package {
+        name: name?,
+        version: version?,
+        license: expression.or(license).or(classifiers),
+        ..Default::default()
+    })
+}
+
+/// Lists the distributions installed in the environment at `venv_root`,
+/// sorted by name, without running its interpreter
+pub fn get_installed_packages(venv_root: &Path) -> Vec<Package> {
+    let mut packages = Vec::new();
+    for site_packages in get_site_packages_dirs(venv_root) {
+        let entries = match read_dir(&site_packages) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let metadata_path = if file_name.ends_with(".dist-info") {
+                path.join("METADATA")
+            } else if file_name.ends_with(".egg-info") && path.is_dir() {
+                path.join("PKG-INFO")
+            } else if file_name.ends_with(".egg-info") {
+                path.clone()
+            } else {
+                continue;
+            };
+            let package = match read_to_string(&metadata_path)
+                .ok()
+                .and_then(|contents| parse_metadata(&contents))
+            {
+                Some(package) => package,
+                None => continue,
+            };
+            let direct_url: Option<DirectUrl> = read_to_string(path.join("direct_url.json"))
+                .ok()
+                .and_then(|contents| serde_json::from_str(&contents).ok());
+            packages.push(Package {
+                editable: direct_url
+                    .as_ref()
+                    .and_then(|direct_url| direct_url.dir_info.as_ref())
+                    .is_some_and(|dir_info| dir_info.editable),
+                url: direct_url.map(|direct_url| direct_url.url),
+                ..package
+            });
+        }
+    }
+    packages.sort_by_key(|package| package.name.to_lowercase());
+    packages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+
+    #[test]
+    pub fn test_get_installed_packages() {
+        let root = tempfile::tempdir().expect("Failed to create tempdir");
+        let site_packages = root.path().join("lib/python3.12/site-packages");
+        create_dir_all(site_packages.join("Requests-2.31.0.dist-info")).unwrap();
+        write(
+            site_packages.join("Requests-2.31.0.dist-info/METADATA"),
+            "Metadata-Version: 2.1\nName: requests\nVersion: 2.31.0\nLicense: Apache 2.0\n\nName: not a header\n",
+        )
+        .unwrap();
+        create_dir_all(site_packages.join("myapp-0.1.dist-info")).unwrap();
+        write(
+            site_packages.join("myapp-0.1.dist-info/METADATA"),
+            "Name: myapp\nVersion: 0.1\nLicense: UNKNOWN\nClassifier: License :: OSI Approved :: MIT License\n",
+        )
+        .unwrap();
+        write(
+            site_packages.join("myapp-0.1.dist-info/direct_url.json"),
+            r#"{"url": "file:///src/myapp", "dir_info": {"editable": true}}"#,
+        )
+        .unwrap();
+
+        assert!(version_matches("1.13.1", "1.x"));
+        assert!(version_matches("1.13.1", "1.13"));
+        assert!(!version_matches("10.0", "1"));
+
+        let packages = get_installed_packages(root.path());
+        assert_eq!(
+            packages,
+            vec![
+                Package {
+                    name: "myapp".to_string(),
+                    version: "0.1".to_string(),
+                    url: Some("file:///src/myapp".to_string()),
+                    editable: true,
+                    license: Some("MIT License".to_string()),
+                },
+                Package {
+                    name: "requests".to_string(),
+                    version: "2.31.0".to_string(),
+                    license: Some("Apache 2.0".to_string()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+}
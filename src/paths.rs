@@ -0,0 +1,186 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::path::{Path, PathBuf};
+
+/// A path as written to JSON: a plain string when it is valid UTF-8, and an
+/// array of its raw bytes otherwise, which serde_json can't represent as a string
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum EncodedPath<'a> {
+    Text(std::borrow::Cow<'a, str>),
+    Bytes(Vec<u8>),
+}
+
+/// The path with the raw bytes `bytes`, as read from a pipe
+#[cfg(unix)]
+pub fn from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+/// Windows paths are Unicode, so bytes that aren't UTF-8 are replaced
+#[cfg(not(unix))]
+pub fn from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(unix)]
+fn encode(path: &Path) -> EncodedPath<'_> {
+    use std::os::unix::ffi::OsStrExt;
+
+    match path.to_str() {
+        Some(text) => EncodedPath::Text(text.into()),
+        None => EncodedPath::Bytes(path.as_os_str().as_bytes().to_vec()),
+    }
+}
+
+/// Windows paths that aren't valid Unicode are rare enough to be written lossily
+#[cfg(not(unix))]
+fn encode(path: &Path) -> EncodedPath<'_> {
+    EncodedPath::Text(path.to_string_lossy())
+}
+
+fn decode(encoded: EncodedPath) -> PathBuf {
+    match encoded {
+        EncodedPath::Text(text) => PathBuf::from(text.into_owned()),
+        EncodedPath::Bytes(bytes) => from_bytes(&bytes),
+    }
+}
+
+pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    encode(path).serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    EncodedPath::deserialize(deserializer).map(decode)
+}
+
+/// The same encoding for optional paths
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        path: &Option<PathBuf>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        path.as_deref().map(encode).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<PathBuf>, D::Error> {
+        Option::<EncodedPath>::deserialize(deserializer).map(|path| path.map(decode))
+    }
+}
+
+/// The same encoding for lists of paths
+pub mod vec {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(paths: &[PathBuf], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(paths.iter().map(|path| encode(path)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<PathBuf>, D::Error> {
+        Vec::<EncodedPath>::deserialize(deserializer)
+            .map(|paths| paths.into_iter().map(decode).collect())
+    }
+}
+
+/// The same encoding for maps keyed by path. JSON object keys can only be
+/// strings, so a map with a key that isn't valid UTF-8 is written as a list
+/// of `[path, value]` pairs instead.
+pub mod map {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum EncodedMap<'a, V> {
+        Text(BTreeMap<String, V>),
+        Pairs(Vec<(EncodedPath<'a>, V)>),
+    }
+
+    pub fn serialize<S: Serializer, V: Serialize>(
+        map: &BTreeMap<PathBuf, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match map.keys().all(|path| path.to_str().is_some()) {
+            true => serializer.collect_map(map.iter().map(|(path, value)| (encode(path), value))),
+            false => serializer.collect_seq(map.iter().map(|(path, value)| (encode(path), value))),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, V: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<PathBuf, V>, D::Error> {
+        Ok(match EncodedMap::deserialize(deserializer)? {
+            EncodedMap::Text(map) => map
+                .into_iter()
+                .map(|(path, value)| (PathBuf::from(path), value))
+                .collect(),
+            EncodedMap::Pairs(pairs) => pairs
+                .into_iter()
+                .map(|(path, value)| (decode(path), value))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        #[serde(with = "crate::paths")]
+        path: PathBuf,
+        #[serde(with = "crate::paths::option", default)]
+        project: Option<PathBuf>,
+        #[serde(with = "crate::paths::vec", default)]
+        dependents: Vec<PathBuf>,
+        #[serde(with = "crate::paths::map", default)]
+        sizes: BTreeMap<PathBuf, u64>,
+    }
+
+    #[test]
+    pub fn test_path_encoding() {
+        let record = Record {
+            path: PathBuf::from("/envs/plain"),
+            project: None,
+            dependents: vec![PathBuf::from("/p")],
+            sizes: BTreeMap::from([(PathBuf::from("/p"), 1)]),
+        };
+        let json = serde_json::to_string(&record).expect("Failed to serialize");
+        assert_eq!(
+            json,
+            r#"{"path":"/envs/plain","project":null,"dependents":["/p"],"sizes":{"/p":1}}"#
+        );
+        let parsed: Record = serde_json::from_str(&json).expect("Failed to parse");
+        assert_eq!(parsed, record);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_non_utf8_path_encoding() {
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
+
+        let odd = PathBuf::from(OsString::from_vec(b"/p\xff".to_vec()));
+        let record = Record {
+            path: PathBuf::from(OsString::from_vec(b"/envs/caf\xe9".to_vec())),
+            project: Some(odd.clone()),
+            dependents: vec![odd.clone()],
+            sizes: BTreeMap::from([(odd, 2)]),
+        };
+        let json = serde_json::to_string(&record).expect("Failed to serialize");
+        assert_eq!(
+            json,
+            r#"{"path":[47,101,110,118,115,47,99,97,102,233],"project":[47,112,255],"dependents":[[47,112,255]],"sizes":[[[47,112,255],2]]}"#
+        );
+        let parsed: Record = serde_json::from_str(&json).expect("Failed to parse");
+        assert_eq!(parsed, record);
+    }
+}
@@ -0,0 +1,95 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Where PDM keeps the environments it doesn't create inside the project:
+/// its data directory, or `$PDM_HOME`
+pub fn get_pdm_venv_dirs(home_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![
+        home_dir.join(".local/share/pdm/venvs"),
+        home_dir.join("Library/Application Support/pdm/venvs"),
+    ];
+    if let Some(pdm_home) = env::var_os("PDM_HOME").filter(|dir| !dir.is_empty()) {
+        dirs.push(PathBuf::from(pdm_home).join("venvs"));
+    }
+    dirs
+}
+
+/// Whether `path` is a PEP 582 package directory,
+/// `<project>/__pypackages__/<X.Y>`, as PDM's legacy mode leaves them
+pub fn is_pypackages_dir(path: &Path) -> bool {
+    get_pypackages_version(path).is_some() && path.join("lib").is_dir()
+}
+
+/// The Python version a PEP 582 package directory is for, from its name
+pub fn get_pypackages_version(path: &Path) -> Option<String> {
+    if path.parent()?.file_name()? != "__pypackages__" {
+        return None;
+    }
+    let name = path.file_name()?.to_str()?;
+    let parts: Vec<&str> = name.split('.').collect();
+    let is_version = parts.len() == 2
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit()));
+    is_version.then(|| name.to_string())
+}
+
+/// The project a PDM environment was created for, from its name:
+/// `<project>-<hash>-<env>`, where the hash is 8 URL-safe base64 characters
+/// of the project path and the env is usually the Python version
+pub fn get_project_name(venv_name: &str) -> Option<String> {
+    let bytes = venv_name.as_bytes();
+    (1..bytes.len().saturating_sub(10))
+        .rev()
+        .find(|&start| {
+            bytes[start] == b'-'
+                && bytes[start + 9] == b'-'
+                && bytes[start + 1..start + 9]
+                    .iter()
+                    .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_'))
+        })
+        .map(|start| venv_name[..start].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_get_project_name() {
+        assert_eq!(
+            get_project_name("webapp-Fk3_xQ9a-3.11").as_deref(),
+            Some("webapp")
+        );
+        // Hyphens in the project name and in the hash
+        assert_eq!(
+            get_project_name("my-api-a1-B2c3D-3.12").as_deref(),
+            Some("my-api")
+        );
+        assert_eq!(
+            get_project_name("tools-0aZ9_-xy-test").as_deref(),
+            Some("tools")
+        );
+        assert_eq!(get_project_name("scratch"), None);
+        assert_eq!(get_project_name("-Fk3_xQ9a-3.11"), None);
+    }
+
+    #[test]
+    pub fn test_pypackages_dir() {
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+        let packages = dir.path().join("app/__pypackages__");
+        std::fs::create_dir_all(packages.join("3.11/lib")).unwrap();
+        std::fs::create_dir_all(packages.join("3.12")).unwrap();
+        std::fs::create_dir_all(packages.join("cache/lib")).unwrap();
+
+        assert!(is_pypackages_dir(&packages.join("3.11")));
+        assert_eq!(
+            get_pypackages_version(&packages.join("3.11")).as_deref(),
+            Some("3.11")
+        );
+        // No packages installed
+        assert!(!is_pypackages_dir(&packages.join("3.12")));
+        assert!(!is_pypackages_dir(&packages.join("cache")));
+        assert_eq!(get_pypackages_version(&dir.path().join("app/3.11")), None);
+    }
+}
@@ -0,0 +1,18 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Switches to plain output: no colors, progress bars, spinners or symbols,
+/// and labelled fields instead, for screen readers and dumb terminals
+pub fn set_plain(plain: bool) {
+    PLAIN.store(plain, Ordering::Relaxed);
+    if plain {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+        inquire::set_global_render_config(inquire::ui::RenderConfig::empty());
+    }
+}
+
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
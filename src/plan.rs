@@ -0,0 +1,94 @@
+use crate::venvs::VirtualEnv;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs::{read_to_string, rename, write};
+use std::path::Path;
+
+/// A reviewed selection of environments to delete with `apply`, which
+/// records its progress in the same file so an interrupted run can resume
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plan {
+    pub created: DateTime<Local>,
+    pub entries: Vec<PlanEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanEntry {
+    pub venv: VirtualEnv,
+    /// Whether the environment has been deleted
+    #[serde(default)]
+    pub done: bool,
+}
+
+impl Plan {
+    pub fn new(venvs: &[VirtualEnv]) -> Plan {
+        Plan {
+            created: Local::now(),
+            entries: venvs
+                .iter()
+                .map(|venv| PlanEntry {
+                    venv: venv.clone(),
+                    done: false,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Plan> {
+        let contents =
+            read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&contents).with_context(|| format!("Invalid plan {}", path.display()))
+    }
+
+    /// Writes the plan through a temporary file, so an interruption never
+    /// leaves a truncated plan behind
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let partial = path.with_extension("partial");
+        write(&partial, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", partial.display()))?;
+        rename(&partial, path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The environments not deleted yet
+    pub fn pending(&self) -> Vec<VirtualEnv> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.done)
+            .map(|entry| entry.venv.clone())
+            .collect()
+    }
+
+    pub fn mark_done(&mut self, venv: &VirtualEnv) {
+        for entry in &mut self.entries {
+            if entry.venv.path == venv.path {
+                entry.done = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::venvs::build_virtualenv;
+    use dirs::home_dir;
+
+    #[test]
+    pub fn test_plan_progress() {
+        let home_dir = home_dir().expect("Failed to get home directory");
+        let venv = build_virtualenv(home_dir.join(".virtualenvs/sandbox-venv"))
+            .expect("Failed to build virtualenv");
+        let mut plan = Plan::new(std::slice::from_ref(&venv));
+        assert_eq!(plan.pending().len(), 1);
+
+        plan.mark_done(&venv);
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+        let path = dir.path().join("plan.json");
+        plan.save(&path).expect("Failed to save plan");
+
+        let plan = Plan::load(&path).expect("Failed to load plan");
+        assert!(plan.entries[0].done);
+        assert!(plan.pending().is_empty());
+    }
+}
@@ -0,0 +1,154 @@
+use crate::config::{ProjectBudget, SafetyConfig};
+use crate::venvs::VirtualEnv;
+use std::path::PathBuf;
+
+/// How much of a project budget its environments use
+pub struct BudgetUsage<'a> {
+    pub budget: &'a ProjectBudget,
+    pub used: u64,
+    /// Environments counted against the budget, oldest first
+    pub venvs: Vec<&'a VirtualEnv>,
+}
+
+impl BudgetUsage<'_> {
+    pub fn is_over(&self) -> bool {
+        self.used > self.budget.max.0
+    }
+}
+
+/// Whether `venv` lives in, or belongs to a project in, `budget`'s directory
+fn is_in_budget(venv: &VirtualEnv, budget: &ProjectBudget) -> bool {
+    venv.path.starts_with(&budget.path)
+        || venv
+            .project
+            .as_ref()
+            .is_some_and(|project| project.starts_with(&budget.path))
+}
+
+/// Computes the consumption of every project budget
+pub fn get_budget_usage<'a>(
+    budgets: &'a [ProjectBudget],
+    venvs: &'a [VirtualEnv],
+) -> Vec<BudgetUsage<'a>> {
+    budgets
+        .iter()
+        .map(|budget| {
+            let mut venvs: Vec<&VirtualEnv> = venvs
+                .iter()
+                .filter(|venv| is_in_budget(venv, budget))
+                .collect();
+            // Environments of unknown age sort first, i.e. are treated as oldest
+            venvs.sort_by_key(|venv| venv.modified);
+            BudgetUsage {
+                budget,
+                used: venvs.iter().map(|venv| venv.venv_size).sum(),
+                venvs,
+            }
+        })
+        .collect()
+}
+
+/// Picks the oldest environments of each over-budget project until what
+/// remains fits in its budget, never picking protected or pinned ones.
+/// Returns each picked environment's path with the budget it was picked for.
+pub fn pick_over_budget<'a>(
+    budgets: &'a [ProjectBudget],
+    venvs: &'a [VirtualEnv],
+    safety: &SafetyConfig,
+) -> Vec<(PathBuf, &'a ProjectBudget)> {
+    let mut picked = Vec::new();
+    for usage in get_budget_usage(budgets, venvs) {
+        let mut used = usage.used;
+        for venv in usage.venvs {
+            if used <= usage.budget.max.0 {
+                break;
+            }
+            if venv.pinned || safety.is_protected(venv) {
+                continue;
+            }
+            used = used.saturating_sub(venv.venv_size);
+            picked.push((venv.path.clone(), usage.budget));
+        }
+    }
+    picked
+}
+
+/// The environments picked by `pick_over_budget`, sorted
+pub fn select_over_budget(
+    budgets: &[ProjectBudget],
+    venvs: &[VirtualEnv],
+    safety: &SafetyConfig,
+) -> Vec<PathBuf> {
+    let mut selected: Vec<PathBuf> = pick_over_budget(budgets, venvs, safety)
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect();
+    selected.sort();
+    selected.dedup();
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Age;
+    use crate::size::ByteSize;
+    use chrono::{Duration, Local};
+
+    #[test]
+    pub fn test_select_over_budget() {
+        let now = Local::now();
+        let venv = |path: &str, venv_size: u64, days_old: i64| VirtualEnv {
+            path: PathBuf::from(path),
+            venv_size,
+            modified: Some(now - Duration::days(days_old)),
+            ..Default::default()
+        };
+        let venvs = vec![
+            venv("/code/ml/a/.venv", 4, 30),
+            venv("/code/ml/b/.venv", 4, 10),
+            venv("/code/ml/c/.venv", 4, 1),
+            venv("/code/web/.venv", 100, 90),
+        ];
+        let budgets = vec![
+            ProjectBudget {
+                path: PathBuf::from("/code/ml"),
+                max: ByteSize(5),
+            },
+            ProjectBudget {
+                path: PathBuf::from("/code/web"),
+                max: ByteSize(1000),
+            },
+        ];
+
+        let usage = get_budget_usage(&budgets, &venvs);
+        assert_eq!(usage[0].used, 12);
+        assert!(usage[0].is_over());
+        assert!(!usage[1].is_over());
+
+        assert_eq!(
+            select_over_budget(&budgets, &venvs, &SafetyConfig::default()),
+            vec![
+                PathBuf::from("/code/ml/a/.venv"),
+                PathBuf::from("/code/ml/b/.venv")
+            ]
+        );
+
+        // Only the 30 day old environment is older than the minimum age
+        let safety = SafetyConfig {
+            min_age: Age(Duration::days(20)),
+            ..Default::default()
+        };
+        assert_eq!(
+            select_over_budget(&budgets, &venvs, &safety),
+            vec![PathBuf::from("/code/ml/a/.venv")]
+        );
+        // A pinned environment is skipped; the day old one is still protected
+        let mut venvs = venvs;
+        venvs[0].pinned = true;
+        assert_eq!(
+            select_over_budget(&budgets, &venvs, &SafetyConfig::default()),
+            vec![PathBuf::from("/code/ml/b/.venv")]
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use console::style;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Whether `err` is macOS privacy protection (TCC) refusing access to a
+/// directory such as ~/Documents or ~/Desktop, which shows up as EPERM
+/// rather than the EACCES of ordinary permissions
+#[cfg(target_os = "macos")]
+pub fn is_privacy_denial(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn is_privacy_denial(_err: &io::Error) -> bool {
+    false
+}
+
+/// Collects the directories privacy protection kept a scan out of, so they
+/// can be reported instead of silently yielding fewer environments
+#[derive(Debug, Default)]
+pub struct BlockedDirs(Mutex<Vec<PathBuf>>);
+
+impl BlockedDirs {
+    /// Records the directory behind `err` if privacy protection denied it
+    pub fn note(&self, err: &walkdir::Error) {
+        if let (Some(path), Some(io_err)) = (err.path(), err.io_error()) {
+            if is_privacy_denial(io_err) {
+                self.0.lock().unwrap().push(path.to_path_buf());
+            }
+        }
+    }
+
+    /// Warns about every blocked directory, with how to grant access
+    pub fn report(self) {
+        let mut blocked = self.0.into_inner().unwrap();
+        if blocked.is_empty() {
+            return;
+        }
+        blocked.sort();
+        blocked.dedup();
+        eprintln!(
+            "{}",
+            style("macOS privacy protection blocked scanning these directories, so environments inside them are missing:").yellow()
+        );
+        for path in &blocked {
+            eprintln!("  {}", path.display());
+        }
+        eprintln!(
+            "Grant your terminal Full Disk Access in System Settings > Privacy & Security > Full Disk Access and restart it, or exclude these directories with --exclude."
+        );
+    }
+}
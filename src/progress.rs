@@ -0,0 +1,142 @@
+use crate::venvs::VirtualEnv;
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Whether progress is reported as JSON events instead of bars (`--progress json`)
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// When the last delete-progress event went out, to keep their rate down
+static LAST_DELETE_PROGRESS: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Minimum time between delete-progress events for the same deletion run
+const DELETE_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How progress of long operations is shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProgressMode {
+    /// Spinners and progress bars
+    #[default]
+    Bars,
+    /// One JSON event per line on stderr, for GUI frontends and scripts
+    Json,
+}
+
+pub fn set_mode(mode: ProgressMode) {
+    JSON.store(mode == ProgressMode::Json, Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// A machine-readable progress event, written as `{"event": "env-found", ...}`
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    ScanStarted,
+    EnvFound {
+        #[serde(with = "crate::paths")]
+        path: PathBuf,
+        name: String,
+        size: u64,
+    },
+    /// Environments sized so far, out of those discovered
+    SizingProgress {
+        done: usize,
+        total: usize,
+    },
+    /// Bytes removed so far across the whole deletion run
+    DeleteProgress {
+        #[serde(with = "crate::paths")]
+        path: PathBuf,
+        bytes_done: u64,
+        bytes_total: u64,
+    },
+    EnvDeleted {
+        #[serde(with = "crate::paths")]
+        path: PathBuf,
+        deleted: usize,
+        count: usize,
+    },
+    Done {
+        ok: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+/// Writes `event` as one line on stderr, when JSON progress is enabled
+pub fn emit(event: Event) {
+    if !is_json() {
+        return;
+    }
+    match serde_json::to_string(&event) {
+        Ok(line) => eprintln!("{}", line),
+        Err(err) => eprintln!("Failed to encode progress event: {}", err),
+    }
+}
+
+pub fn env_found(venv: &VirtualEnv) {
+    emit(Event::EnvFound {
+        path: venv.path.clone(),
+        name: venv.name.clone(),
+        size: venv.venv_size,
+    });
+}
+
+/// Reports the byte position of `pb` while deleting `path`, at most every
+/// DELETE_PROGRESS_INTERVAL
+pub fn delete_progress(path: &Path, pb: &ProgressBar) {
+    if !is_json() {
+        return;
+    }
+    let now = Instant::now();
+    {
+        let mut last = LAST_DELETE_PROGRESS
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        if last.is_some_and(|last| now - last < DELETE_PROGRESS_INTERVAL) {
+            return;
+        }
+        *last = Some(now);
+    }
+    emit(Event::DeleteProgress {
+        path: path.to_path_buf(),
+        bytes_done: pb.position(),
+        bytes_total: pb.length().unwrap_or_default(),
+    });
+}
+
+/// The final event of a run, carrying its error if it failed
+pub fn done<T>(result: &anyhow::Result<T>) {
+    emit(Event::Done {
+        ok: result.is_ok(),
+        error: result.as_ref().err().map(|err| format!("{:#}", err)),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_event_json() {
+        let event = Event::SizingProgress { done: 3, total: 10 };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"sizing-progress","done":3,"total":10}"#
+        );
+        let event = Event::Done {
+            ok: true,
+            error: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"done","ok":true}"#
+        );
+    }
+}
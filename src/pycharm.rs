@@ -0,0 +1,114 @@
+use crate::venvs::is_venv_root;
+use dirs::{config_dir, home_dir};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+/// An interpreter registered in a JetBrains IDE's SDK table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdeInterpreter {
+    /// Root directory of the virtual environment
+    pub venv_root: PathBuf,
+    /// Project the interpreter was created for, if recorded
+    pub project: Option<PathBuf>,
+}
+
+/// Reads the SDK tables of every installed JetBrains IDE (PyCharm, IntelliJ, ...)
+/// and returns the virtual environments registered there.
+pub fn get_pycharm_interpreters() -> Vec<IdeInterpreter> {
+    let (config_dir, home_dir) = match (config_dir(), home_dir()) {
+        (Some(config_dir), Some(home_dir)) => (config_dir, home_dir),
+        _ => return vec![],
+    };
+
+    let pattern = config_dir.join("JetBrains/*/options/jdk.table.xml");
+    let tables = match glob::glob(&pattern.to_string_lossy()) {
+        Ok(tables) => tables,
+        Err(_) => return vec![],
+    };
+
+    tables
+        .filter_map(Result::ok)
+        .filter_map(|path| read_to_string(path).ok())
+        .flat_map(|xml| parse_jdk_table(&xml, &home_dir))
+        .filter(|interpreter| is_venv_root(&interpreter.venv_root))
+        .collect()
+}
+
+/// Parses a `jdk.table.xml` file, expanding the `$USER_HOME$` macro
+pub fn parse_jdk_table(xml: &str, home_dir: &Path) -> Vec<IdeInterpreter> {
+    let document = match roxmltree::Document::parse(xml) {
+        Ok(document) => document,
+        Err(_) => return vec![],
+    };
+    let expand =
+        |value: &str| PathBuf::from(value.replace("$USER_HOME$", &home_dir.to_string_lossy()));
+
+    document
+        .descendants()
+        .filter(|node| node.has_tag_name("jdk"))
+        .filter(|jdk| {
+            child_value(jdk, "type").is_some_and(|sdk_type| sdk_type.starts_with("Python"))
+        })
+        .filter_map(|jdk| {
+            let interpreter = expand(child_value(&jdk, "homePath")?);
+            // <homePath> points at <venv>/bin/python or <venv>\Scripts\python.exe
+            let venv_root = interpreter.parent()?.parent()?.to_path_buf();
+            let project = jdk
+                .descendants()
+                .find_map(|node| node.attribute("ASSOCIATED_PROJECT_PATH"))
+                .map(expand);
+            Some(IdeInterpreter { venv_root, project })
+        })
+        .collect()
+}
+
+/// The `value` attribute of the first child element named `tag`
+fn child_value<'a>(node: &roxmltree::Node<'a, '_>, tag: &str) -> Option<&'a str> {
+    node.children()
+        .find(|child| child.has_tag_name(tag))
+        .and_then(|child| child.attribute("value"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_jdk_table() {
+        let xml = r#"<application>
+  <component name="ProjectJdkTable">
+    <jdk version="2">
+      <name value="Python 3.11 (ml-experiments)" />
+      <type value="Python SDK" />
+      <homePath value="$USER_HOME$/code/ml-experiments/venv/bin/python" />
+      <additional SDK_UUID="1234" ASSOCIATED_PROJECT_PATH="$USER_HOME$/code/ml-experiments" />
+    </jdk>
+    <jdk version="2">
+      <name value="corretto-17" />
+      <type value="JavaSDK" />
+      <homePath value="$USER_HOME$/.jdks/corretto-17" />
+    </jdk>
+    <jdk version="2">
+      <name value="Python 3.9" />
+      <type value="Python SDK" />
+      <homePath value="/opt/envs/legacy/bin/python" />
+    </jdk>
+  </component>
+</application>"#;
+
+        let interpreters = parse_jdk_table(xml, Path::new("/home/me"));
+        assert_eq!(
+            interpreters,
+            vec![
+                IdeInterpreter {
+                    venv_root: PathBuf::from("/home/me/code/ml-experiments/venv"),
+                    project: Some(PathBuf::from("/home/me/code/ml-experiments")),
+                },
+                IdeInterpreter {
+                    venv_root: PathBuf::from("/opt/envs/legacy"),
+                    project: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::manager::Manager;
+use crate::venvs::{build_virtualenv, get_interpreter_path, VirtualEnv};
+use std::env;
+use std::fs::{canonicalize, read_dir, read_to_string, remove_file, symlink_metadata};
+use std::path::{Path, PathBuf};
+
+/// `$PYENV_ROOT/versions`, defaulting to `~/.pyenv/versions`
+fn get_versions_dir(home_dir: &Path) -> PathBuf {
+    match env::var_os("PYENV_ROOT").filter(|root| !root.is_empty()) {
+        Some(root) => PathBuf::from(root).join("versions"),
+        None => home_dir.join(".pyenv/versions"),
+    }
+}
+
+/// The `versions/<python>/envs` directories where pyenv-virtualenv creates
+/// environments. The Python installations next to them are not searched.
+pub fn get_pyenv_envs_dirs(home_dir: &Path) -> Vec<PathBuf> {
+    let mut versions_dirs = vec![home_dir.join(".pyenv/versions")];
+    let versions_dir = get_versions_dir(home_dir);
+    if !versions_dirs.contains(&versions_dir) {
+        versions_dirs.push(versions_dir);
+    }
+    versions_dirs
+        .iter()
+        .filter_map(|versions_dir| read_dir(versions_dir).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        // Skip the `versions/<name>` links to the environments themselves
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .map(|entry| entry.path().join("envs"))
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+/// The `versions/<name>` symlink pyenv-virtualenv makes for the environment
+/// at `versions/<python>/envs/<name>`, which is how pyenv refers to it
+pub fn get_pyenv_link(venv_root: &Path) -> Option<PathBuf> {
+    let envs_dir = venv_root.parent()?;
+    if envs_dir.file_name()? != "envs" {
+        return None;
+    }
+    let link = envs_dir.parent()?.parent()?.join(venv_root.file_name()?);
+    let is_link = symlink_metadata(&link).is_ok_and(|metadata| metadata.is_symlink());
+    match is_link && canonicalize(&link).is_ok_and(|target| target == venv_root) {
+        true => Some(link),
+        false => None,
+    }
+}
+
+/// The Python installations in `versions/` of each home, for
+/// `--include-pyenv-versions`, with the environments in `venvs` that
+/// would break without them
+pub fn get_pyenv_versions(homes: &[PathBuf], venvs: &[VirtualEnv]) -> Vec<VirtualEnv> {
+    let mut versions = Vec::new();
+    for home in homes {
+        let entries = match read_dir(get_versions_dir(home)) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(Result::ok) {
+            // Links are pyenv-virtualenv's names for environments
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            let path = match canonicalize(entry.path()) {
+                Ok(path) if is_dir && get_interpreter_path(&path).exists() => path,
+                _ => continue,
+            };
+            match build_virtualenv(path) {
+                Ok(mut version) => {
+                    version.manager = Manager::PyenvVersion;
+                    version.dependents = get_dependents(&version.path, venvs);
+                    versions.push(version);
+                }
+                Err(err) => eprintln!("Error building pyenv version: {}", err),
+            }
+        }
+    }
+    versions
+}
+
+/// Environments inside the installation at `version_dir` (pyenv-virtualenv's
+/// `envs`) or whose interpreter or `pyvenv.cfg` home points into it
+pub fn get_dependents(version_dir: &Path, venvs: &[VirtualEnv]) -> Vec<PathBuf> {
+    venvs
+        .iter()
+        .filter(|venv| {
+            venv.path.starts_with(version_dir)
+                || canonicalize(&venv.python_path)
+                    .is_ok_and(|python| python.starts_with(version_dir))
+                || get_base_home(&venv.path).is_some_and(|home| home.starts_with(version_dir))
+        })
+        .map(|venv| venv.path.clone())
+        .collect()
+}
+
+/// The `home` of `pyvenv.cfg`: the `bin` directory of the base Python
+fn get_base_home(venv_root: &Path) -> Option<PathBuf> {
+    let cfg = read_to_string(venv_root.join("pyvenv.cfg")).ok()?;
+    cfg.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "home").then(|| PathBuf::from(value.trim()))
+    })
+}
+
+/// Removes `link` once the environment it points to is gone, so pyenv
+/// doesn't keep listing a broken version
+pub fn remove_dangling_link(link: &Path) {
+    let is_link = symlink_metadata(link).is_ok_and(|metadata| metadata.is_symlink());
+    if is_link && !link.exists() {
+        if let Err(err) = remove_file(link) {
+            eprintln!("Failed to remove {}: {}", link.display(), err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_pyenv_layout() {
+        let home = tempfile::tempdir().expect("Failed to create temporary directory");
+        let home = canonicalize(home.path()).unwrap();
+        let versions = home.join(".pyenv/versions");
+        let venv = versions.join("3.11.7/envs/tools");
+        std::fs::create_dir_all(&venv).unwrap();
+        std::fs::create_dir_all(versions.join("3.12.1/bin")).unwrap();
+        std::os::unix::fs::symlink(&venv, versions.join("tools")).unwrap();
+
+        assert_eq!(
+            get_pyenv_envs_dirs(&home),
+            vec![versions.join("3.11.7/envs")]
+        );
+        assert_eq!(get_pyenv_link(&venv), Some(versions.join("tools")));
+        assert_eq!(get_pyenv_link(&versions.join("3.12.1")), None);
+
+        remove_dangling_link(&versions.join("tools"));
+        assert!(versions.join("tools").exists());
+        std::fs::remove_dir_all(&venv).unwrap();
+        remove_dangling_link(&versions.join("tools"));
+        assert!(symlink_metadata(versions.join("tools")).is_err());
+    }
+
+    #[test]
+    pub fn test_get_dependents() {
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+        let root = canonicalize(dir.path()).unwrap();
+        let version_dir = root.join(".pyenv/versions/3.11.7");
+        let copied = root.join("code/app/.venv");
+        std::fs::create_dir_all(&copied).unwrap();
+        std::fs::write(
+            copied.join("pyvenv.cfg"),
+            format!("home = {}\n", version_dir.join("bin").display()),
+        )
+        .unwrap();
+
+        let venvs: Vec<VirtualEnv> = [
+            version_dir.join("envs/tools"),
+            copied.clone(),
+            root.join("code/other/.venv"),
+        ]
+        .into_iter()
+        .map(|path| VirtualEnv {
+            path,
+            ..Default::default()
+        })
+        .collect();
+        assert_eq!(
+            get_dependents(&version_dir, &venvs),
+            vec![version_dir.join("envs/tools"), copied]
+        );
+    }
+}
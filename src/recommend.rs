@@ -0,0 +1,214 @@
+use crate::config::Config;
+use crate::policy::pick_over_budget;
+use crate::score::reclaim_score;
+use crate::venvs::VirtualEnv;
+use chrono::{Duration, Local};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Why an environment is recommended for deletion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    /// The interpreter is missing
+    Broken,
+    /// The IDE project it belongs to is gone
+    Orphaned(PathBuf),
+    /// Not used for this many days
+    Unused(i64),
+    /// A conda environment with the same packages as this more recently used one
+    Clone(PathBuf),
+    /// The same project has this environment with a newer interpreter
+    Superseded(PathBuf),
+    /// The project directory with this budget needs it gone to fit
+    OverBudget(PathBuf),
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Broken => write!(f, "its interpreter is missing"),
+            Reason::Orphaned(project) => {
+                write!(f, "its project {} no longer exists", project.display())
+            }
+            Reason::Unused(days) => write!(f, "unused for {} days", days),
+            Reason::Clone(of) => write!(f, "redundant clone or copy of {}", of.display()),
+            Reason::Superseded(by) => {
+                write!(f, "its project has a newer interpreter in {}", by.display())
+            }
+            Reason::OverBudget(budget) => write!(f, "{} is over its budget", budget.display()),
+        }
+    }
+}
+
+/// An environment proposed for deletion, with every reason that applies
+#[derive(Debug, Clone)]
+pub struct Recommendation {
+    pub venv: VirtualEnv,
+    pub reasons: Vec<Reason>,
+}
+
+/// Proposes deleting broken, orphaned, cloned, superseded and unused environments, and
+/// the oldest ones of projects over their budget. Pinned environments and
+/// those modified within the safety minimum age are never proposed.
+///
+/// The most delete-worthy come first.
+pub fn recommend(
+    venvs: &[VirtualEnv],
+    unused_for: Duration,
+    config: &Config,
+) -> Vec<Recommendation> {
+    let now = Local::now();
+    let over_budget = pick_over_budget(&config.budgets, venvs, &config.safety);
+
+    let mut recommendations: Vec<Recommendation> = venvs
+        .iter()
+        .filter(|venv| !venv.pinned && !config.safety.is_protected(venv))
+        .filter_map(|venv| {
+            let mut reasons = Vec::new();
+            if venv.is_broken() {
+                reasons.push(Reason::Broken);
+            }
+            if venv.is_orphaned() {
+                reasons.extend(venv.project.clone().map(Reason::Orphaned));
+            }
+            if let Some(modified) = venv.modified {
+                let unused = now - modified;
+                if unused > unused_for {
+                    reasons.push(Reason::Unused(unused.num_days()));
+                }
+            }
+            if let Some(of) = &venv.clone_of {
+                reasons.push(Reason::Clone(of.clone()));
+            }
+            if let Some(by) = &venv.superseded_by {
+                reasons.push(Reason::Superseded(by.clone()));
+            }
+            reasons.extend(
+                over_budget
+                    .iter()
+                    .filter(|(path, _)| *path == venv.path)
+                    .map(|(_, budget)| Reason::OverBudget(budget.path.clone())),
+            );
+            match reasons.is_empty() {
+                true => None,
+                false => Some(Recommendation {
+                    venv: venv.clone(),
+                    reasons,
+                }),
+            }
+        })
+        .collect();
+    recommendations.sort_by(|a, b| reclaim_score(&b.venv).total_cmp(&reclaim_score(&a.venv)));
+    recommendations
+}
+
+/// Keeps the recommendations, in order, whose reclaimable sizes add up to
+/// at most `max`, skipping any that would overshoot it
+pub fn take_up_to(recommendations: Vec<Recommendation>, max: u64) -> Vec<Recommendation> {
+    let mut total = 0;
+    recommendations
+        .into_iter()
+        .filter(|recommendation| {
+            let size = recommendation.venv.reclaimable_size();
+            let fits = total + size <= max;
+            if fits {
+                total += size;
+            }
+            fits
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProjectBudget;
+    use crate::size::ByteSize;
+    use std::path::Path;
+
+    #[test]
+    pub fn test_recommend() {
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+        let python = dir.path().join("python");
+        std::fs::write(&python, "").expect("Failed to write file");
+
+        let now = Local::now();
+        let venv = |path: &str, days_old: i64| VirtualEnv {
+            path: PathBuf::from(path),
+            python_path: python.clone(),
+            venv_size: 4,
+            modified: Some(now - Duration::days(days_old)),
+            ..Default::default()
+        };
+        let venvs = vec![
+            venv("/code/ml/a/.venv", 200),
+            venv("/code/ml/b/.venv", 20),
+            venv("/code/ml/c/.venv", 10),
+            VirtualEnv {
+                project: Some(dir.path().join("deleted-project")),
+                orphaned: true,
+                ..venv("/envs/orphan", 10)
+            },
+            VirtualEnv {
+                python_path: dir.path().join("missing"),
+                broken: true,
+                pinned: true,
+                ..venv("/envs/pinned", 400)
+            },
+            venv("/envs/fresh", 1),
+        ];
+        let config = Config {
+            budgets: vec![ProjectBudget {
+                path: PathBuf::from("/code/ml"),
+                max: ByteSize(5),
+            }],
+            ..Default::default()
+        };
+
+        let recommendations = recommend(&venvs, Duration::days(90), &config);
+        let reasons = |path: &str| {
+            recommendations
+                .iter()
+                .find(|recommendation| recommendation.venv.path.as_path() == Path::new(path))
+                .map(|recommendation| recommendation.reasons.clone())
+        };
+        assert_eq!(
+            reasons("/code/ml/a/.venv"),
+            Some(vec![
+                Reason::Unused(200),
+                Reason::OverBudget(PathBuf::from("/code/ml"))
+            ])
+        );
+        assert_eq!(
+            reasons("/code/ml/b/.venv"),
+            Some(vec![Reason::OverBudget(PathBuf::from("/code/ml"))])
+        );
+        // The remaining 4 bytes fit in the budget
+        assert_eq!(reasons("/code/ml/c/.venv"), None);
+        assert_eq!(
+            reasons("/envs/orphan"),
+            Some(vec![Reason::Orphaned(dir.path().join("deleted-project"))])
+        );
+        assert_eq!(reasons("/envs/pinned"), None);
+        // Within the safety minimum age
+        assert_eq!(reasons("/envs/fresh"), None);
+        assert_eq!(recommendations.len(), 3);
+    }
+
+    #[test]
+    pub fn test_take_up_to() {
+        let recommendation = |venv_size: u64| Recommendation {
+            venv: VirtualEnv {
+                venv_size,
+                ..Default::default()
+            },
+            reasons: vec![Reason::Broken],
+        };
+        let recommendations = vec![recommendation(6), recommendation(5), recommendation(3)];
+        let sizes: Vec<u64> = take_up_to(recommendations, 10)
+            .iter()
+            .map(|recommendation| recommendation.venv.venv_size)
+            .collect();
+        assert_eq!(sizes, vec![6, 3]);
+    }
+}
@@ -0,0 +1,259 @@
+use crate::remove::DeleteOptions;
+use crate::venvs::VirtualEnv;
+use anyhow::{anyhow, Context, Result};
+use std::env::{self, current_exe};
+use std::ffi::{OsStr, OsString};
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+/// Where an uploaded copy of this binary is placed, relative to the remote home
+const REMOTE_UPLOAD_PATH: &str = ".cache/venvpruner-remote";
+
+/// A host reachable over `ssh` that runs venvpruner on our behalf
+pub struct Remote {
+    host: String,
+    program: String,
+}
+
+impl Remote {
+    /// Prepares a remote host. With `upload`, this binary is copied over with
+    /// `scp` (the remote must share our OS and architecture); otherwise the
+    /// remote's own `venvpruner` on `PATH` is used.
+    pub fn connect(host: &str, upload: bool) -> Result<Remote> {
+        // ssh and scp would take such a host for one of their own options
+        if host.is_empty() || host.starts_with('-') {
+            return Err(anyhow!("Invalid SSH host: {:?}", host));
+        }
+        if !upload {
+            return Ok(Remote {
+                host: host.to_string(),
+                program: "venvpruner".to_string(),
+            });
+        }
+
+        let exe = current_exe().context("Failed to locate the venvpruner binary")?;
+        let remote = Remote {
+            host: host.to_string(),
+            program: format!("./{}", REMOTE_UPLOAD_PATH),
+        };
+        remote.run_checked("mkdir -p .cache")?;
+
+        let status = Command::new("scp")
+            .arg("-q")
+            .arg(&exe)
+            .arg(format!("{}:{}", host, REMOTE_UPLOAD_PATH))
+            .status()
+            .context("Failed to run scp")?;
+        if !status.success() {
+            return Err(anyhow!("Failed to copy venvpruner to {}", host));
+        }
+        remote.run_checked(&format!("chmod +x {}", shell_quote(REMOTE_UPLOAD_PATH)))?;
+
+        Ok(remote)
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Builds the remote command line for running venvpruner with `args`
+    fn remote_command<S: AsRef<OsStr>>(&self, args: &[S]) -> String {
+        let mut command = shell_quote(&self.program);
+        for arg in args {
+            command.push(' ');
+            command.push_str(&shell_quote_os(arg.as_ref()));
+        }
+        command
+    }
+
+    fn ssh(&self) -> Command {
+        let mut command = Command::new("ssh");
+        command.arg(&self.host).arg("--");
+        command
+    }
+
+    fn run_checked(&self, remote_command: &str) -> Result<()> {
+        let status = self
+            .ssh()
+            .arg(remote_command)
+            .status()
+            .with_context(|| format!("Failed to run ssh for {}", self.host))?;
+        if !status.success() {
+            return Err(anyhow!("`{}` failed on {}", remote_command, self.host));
+        }
+        Ok(())
+    }
+
+    /// Runs discovery on the remote host, calling `on_found` as each
+    /// environment is streamed back.
+    pub fn list(
+        &self,
+        scan_args: &[OsString],
+        mut on_found: impl FnMut(&VirtualEnv),
+    ) -> Result<Vec<VirtualEnv>> {
+        let mut args: Vec<OsString> = vec!["list".into(), "--output".into(), "jsonl".into()];
+        args.extend(scan_args.iter().cloned());
+
+        let mut child = self
+            .ssh()
+            .arg(self.remote_command(&args))
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run ssh for {}", self.host))?;
+
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Failed to capture ssh output"))?;
+        let mut venvs = Vec::new();
+        for line in BufReader::new(stdout).lines() {
+            let line = line.context("Failed to read from ssh")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let venv: VirtualEnv = serde_json::from_str(&line)
+                .with_context(|| format!("Unexpected output from {}: {}", self.host, line))?;
+            on_found(&venv);
+            venvs.push(venv);
+        }
+
+        let status = child.wait().context("Failed to wait for ssh")?;
+        if !status.success() {
+            return Err(anyhow!(
+                "Remote discovery failed on {} (is venvpruner installed there? try --ssh-upload)",
+                self.host
+            ));
+        }
+        Ok(venvs)
+    }
+
+    /// Deletes the given environments on the remote host without prompting.
+    /// They have been confirmed, or checked for protection, locally.
+    pub fn delete(&self, venvs: &[VirtualEnv], options: &DeleteOptions) -> Result<()> {
+        let mut args: Vec<OsString> = vec![
+            "delete".into(),
+            "--yes".into(),
+            "--override-protection".into(),
+        ];
+        // Paths such as the archive directory are on the remote host
+        args.extend(options.to_args());
+        args.extend(venvs.iter().map(|venv| venv.path.clone().into_os_string()));
+        self.run(&args)
+    }
+
+    /// Runs venvpruner on the remote host with a terminal attached
+    pub fn run<S: AsRef<OsStr>>(&self, args: &[S]) -> Result<()> {
+        let status = Command::new("ssh")
+            .arg("-t")
+            .arg(&self.host)
+            .arg("--")
+            .arg(self.remote_command(args))
+            .status()
+            .with_context(|| format!("Failed to run ssh for {}", self.host))?;
+        if !status.success() {
+            return Err(anyhow!("venvpruner failed on {}", self.host));
+        }
+        Ok(())
+    }
+}
+
+/// This process' arguments without the SSH options, for running the same
+/// command on the remote host
+pub fn get_forwarded_args() -> Vec<OsString> {
+    let mut args = env::args_os().skip(1);
+    let mut forwarded = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--ssh" {
+            args.next();
+        } else if arg != "--ssh-upload" && !arg.to_string_lossy().starts_with("--ssh=") {
+            forwarded.push(arg);
+        }
+    }
+    forwarded
+}
+
+/// Quotes `arg` for a POSIX shell
+pub fn shell_quote(arg: &str) -> String {
+    let is_safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@,+".contains(c));
+    if is_safe {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Quotes `arg`, which need not be valid UTF-8, for a POSIX shell. Bytes
+/// that aren't UTF-8 are produced with `printf` octal escapes.
+#[cfg(unix)]
+pub fn shell_quote_os(arg: &OsStr) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let bytes = arg.as_bytes();
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return shell_quote(text);
+    }
+    let mut quoted = String::new();
+    for chunk in bytes.utf8_chunks() {
+        if !chunk.valid().is_empty() {
+            quoted.push_str(&shell_quote(chunk.valid()));
+        }
+        if !chunk.invalid().is_empty() {
+            let escapes: String = chunk
+                .invalid()
+                .iter()
+                .map(|byte| format!("\\{:03o}", byte))
+                .collect();
+            quoted.push_str(&format!("\"$(printf '{}')\"", escapes));
+        }
+    }
+    quoted
+}
+
+/// Quotes `arg` for the POSIX shell of a remote host. Windows arguments
+/// that aren't valid Unicode are quoted lossily.
+#[cfg(not(unix))]
+pub fn shell_quote_os(arg: &OsStr) -> String {
+    shell_quote(&arg.to_string_lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_connect_rejects_option_hosts() {
+        assert!(Remote::connect("-oProxyCommand=touch /tmp/pwned", false).is_err());
+        assert!(Remote::connect("", false).is_err());
+        assert_eq!(
+            Remote::connect("user@box", false).unwrap().host(),
+            "user@box"
+        );
+    }
+
+    #[test]
+    pub fn test_shell_quote() {
+        assert_eq!(shell_quote("--output"), "--output");
+        assert_eq!(shell_quote("/home/me/.venv"), "/home/me/.venv");
+        assert_eq!(shell_quote("my env"), "'my env'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_shell_quote_os() {
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+        let path = OsString::from_vec(b"/envs/caf\xe9 1".to_vec());
+        let quoted = shell_quote_os(&path);
+        assert_eq!(quoted, r#"/envs/caf"$(printf '\351')"' 1'"#);
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf %s {}", quoted))
+            .output()
+            .expect("Failed to run sh");
+        assert_eq!(output.stdout, path.as_bytes());
+    }
+}
@@ -0,0 +1,413 @@
+use crate::hooks::HooksConfig;
+#[cfg(unix)]
+use rayon::prelude::*;
+use std::ffi::OsString;
+#[cfg(unix)]
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::{ffi::OsStrExt, io::RawFd};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use walkdir::WalkDir;
+
+/// Removal is bound by the filesystem, so more threads than this rarely help
+#[cfg(unix)]
+const MAX_REMOVE_THREADS: usize = 8;
+
+/// Files deleted between pauses when I/O priority can't be lowered
+const THROTTLE_BATCH: u32 = 100;
+const THROTTLE_PAUSE: Duration = Duration::from_millis(20);
+
+/// How environments are deleted
+#[derive(Debug, Clone, Default)]
+pub struct DeleteOptions {
+    /// Archive each environment here before deleting it
+    pub archive: Option<PathBuf>,
+    /// Delete with idle I/O priority, or throttled where that isn't available
+    pub nice_io: bool,
+    /// Also delete environments pinned with a `.venvpruner-keep` marker
+    pub force: bool,
+    /// Commands run around each deletion; a remote venvpruner uses its own
+    pub hooks: HooksConfig,
+}
+
+impl DeleteOptions {
+    /// The command line arguments recreating these options, for a remote venvpruner
+    pub fn to_args(&self) -> Vec<OsString> {
+        let mut args = Vec::new();
+        if let Some(dir) = &self.archive {
+            args.push("--archive".into());
+            args.push(dir.clone().into_os_string());
+        }
+        if self.nice_io {
+            args.push("--nice-io".into());
+        }
+        if self.force {
+            args.push("--force".into());
+        }
+        args
+    }
+}
+
+/// Moves the calling thread (and threads it spawns later) to the idle I/O
+/// class, so deletions only use disk time nothing else wants
+#[cfg(target_os = "linux")]
+pub fn lower_io_priority() -> bool {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+    // SAFETY: ioprio_set only takes integer arguments
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        )
+    };
+    ret == 0
+}
+
+/// Marks the process's disk I/O as throttleable
+#[cfg(target_os = "macos")]
+pub fn lower_io_priority() -> bool {
+    const IOPOL_TYPE_DISK: libc::c_int = 0;
+    const IOPOL_SCOPE_PROCESS: libc::c_int = 0;
+    const IOPOL_THROTTLE: libc::c_int = 3;
+    extern "C" {
+        fn setiopolicy_np(
+            iotype: libc::c_int,
+            scope: libc::c_int,
+            policy: libc::c_int,
+        ) -> libc::c_int;
+    }
+    // SAFETY: setiopolicy_np only takes integer arguments
+    unsafe { setiopolicy_np(IOPOL_TYPE_DISK, IOPOL_SCOPE_PROCESS, IOPOL_THROTTLE) == 0 }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn lower_io_priority() -> bool {
+    false
+}
+
+/// Pauses briefly after every batch of deleted files
+#[derive(Debug, Default)]
+pub struct Throttle {
+    count: u32,
+}
+
+impl Throttle {
+    pub fn tick(&mut self) {
+        self.count += 1;
+        if self.count.is_multiple_of(THROTTLE_BATCH) {
+            thread::sleep(THROTTLE_PAUSE);
+        }
+    }
+}
+
+/// Removes the directory at `path` like `fs::remove_dir_all`, calling
+/// `on_removed` with the size of every file as it is deleted
+pub fn remove_dir_with_progress(path: &Path, mut on_removed: impl FnMut(u64)) -> io::Result<()> {
+    match remove_tree(path, &mut on_removed) {
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+            make_tree_writable(path);
+            remove_tree(path, &mut on_removed)
+        }
+        result => result,
+    }
+}
+
+fn remove_tree(path: &Path, on_removed: &mut impl FnMut(u64)) -> io::Result<()> {
+    let walker = WalkDir::new(path)
+        .contents_first(true)
+        .follow_links(false)
+        .follow_root_links(false);
+    for entry in walker {
+        let entry = entry.map_err(io::Error::from)?;
+        if entry.file_type().is_dir() {
+            fs::remove_dir(entry.path())?;
+        } else {
+            let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            fs::remove_file(entry.path())?;
+            on_removed(size);
+        }
+    }
+    Ok(())
+}
+
+/// Removes the directory at `path` using several threads, calling
+/// `on_removed` with the size of every file as it is deleted.
+///
+/// Directories are emptied bottom-up with `unlinkat` relative to an open
+/// handle, never following symlinks. If anything goes wrong, whatever is
+/// left is handed to `fs::remove_dir_all`.
+///
+/// The worker threads are created on each call, so they inherit an I/O
+/// priority lowered by the caller.
+#[cfg(unix)]
+pub fn remove_dir_parallel(path: &Path, on_removed: impl Fn(u64) + Sync) -> io::Result<()> {
+    let threads = thread::available_parallelism()
+        .map_or(1, |threads| threads.get())
+        .min(MAX_REMOVE_THREADS);
+    let result = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(io::Error::other)
+        .and_then(|pool| {
+            pool.install(|| {
+                let path = CString::new(path.as_os_str().as_bytes())?;
+                let dir = Dir::open_at(libc::AT_FDCWD, &path)?;
+                remove_contents(&dir, &on_removed)
+            })
+        })
+        .and_then(|()| fs::remove_dir(path));
+    match result {
+        Ok(()) => Ok(()),
+        Err(_) => fs::remove_dir_all(path).or_else(|_| {
+            make_tree_writable(path);
+            fs::remove_dir_all(path)
+        }),
+    }
+}
+
+/// Without `unlinkat`, read-only entries are made writable and the directory
+/// is left to `fs::remove_dir_all`, which reports no progress
+#[cfg(not(unix))]
+pub fn remove_dir_parallel(path: &Path, _on_removed: impl Fn(u64) + Sync) -> io::Result<()> {
+    make_tree_writable(path);
+    fs::remove_dir_all(path)
+}
+
+/// Gives the owner write permission on everything below `path`, so that
+/// read-only directories (and, on Windows, read-only files, as pip and git
+/// leave behind) no longer stop their deletion. Entries of other users are
+/// left alone.
+pub fn make_tree_writable(path: &Path) {
+    for entry in WalkDir::new(path).follow_links(false).into_iter().flatten() {
+        if entry.file_type().is_symlink() {
+            continue;
+        }
+        if let Ok(metadata) = entry.metadata() {
+            if let Some(permissions) = get_writable_permissions(metadata.permissions()) {
+                let _ = fs::set_permissions(entry.path(), permissions);
+            }
+        }
+    }
+}
+
+/// `permissions` with owner write access added, if it was missing
+#[cfg(unix)]
+fn get_writable_permissions(permissions: fs::Permissions) -> Option<fs::Permissions> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = permissions.mode();
+    (mode & 0o200 == 0).then(|| fs::Permissions::from_mode(mode | 0o200))
+}
+
+#[cfg(not(unix))]
+fn get_writable_permissions(mut permissions: fs::Permissions) -> Option<fs::Permissions> {
+    if !permissions.readonly() {
+        return None;
+    }
+    // Only clears FILE_ATTRIBUTE_READONLY on Windows
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    Some(permissions)
+}
+
+#[cfg(unix)]
+fn remove_contents(dir: &Dir, on_removed: &(impl Fn(u64) + Sync)) -> io::Result<()> {
+    let fd = dir.fd();
+    dir.entries().par_iter().try_for_each(|name| {
+        let stat = stat_at(fd, name)?;
+        match stat.st_mode & libc::S_IFMT == libc::S_IFDIR {
+            true => {
+                remove_contents(&Dir::open_at(fd, name)?, on_removed)?;
+                unlink_at(fd, name, libc::AT_REMOVEDIR)
+            }
+            false => {
+                unlink_at(fd, name, 0)?;
+                on_removed(stat.st_size as u64);
+                Ok(())
+            }
+        }
+    })
+}
+
+/// An open directory stream
+#[cfg(unix)]
+struct Dir(*mut libc::DIR);
+
+#[cfg(unix)]
+impl Dir {
+    /// Opens `name` relative to `parent`, refusing to follow a symlink
+    fn open_at(parent: RawFd, name: &CStr) -> io::Result<Dir> {
+        let flags = libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW | libc::O_CLOEXEC;
+        // SAFETY: `name` is NUL-terminated; the descriptor is owned by the stream below
+        let fd = unsafe { libc::openat(parent, name.as_ptr(), flags) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `fd` is an open directory descriptor
+        let stream = unsafe { libc::fdopendir(fd) };
+        if stream.is_null() {
+            let err = io::Error::last_os_error();
+            // SAFETY: `fd` was not taken over by a stream
+            unsafe { libc::close(fd) };
+            return Err(err);
+        }
+        Ok(Dir(stream))
+    }
+
+    fn fd(&self) -> RawFd {
+        // SAFETY: the stream is open until drop
+        unsafe { libc::dirfd(self.0) }
+    }
+
+    /// Names of the entries, except `.` and `..`. A read error ends the
+    /// listing early, which surfaces when the directory won't go away.
+    fn entries(&self) -> Vec<CString> {
+        let mut names = Vec::new();
+        loop {
+            // SAFETY: the stream is open and only read from this thread
+            let entry = unsafe { libc::readdir(self.0) };
+            if entry.is_null() {
+                break;
+            }
+            // SAFETY: `d_name` is NUL-terminated
+            let name = unsafe { CStr::from_ptr((*entry).d_name.as_ptr()) };
+            if name.to_bytes() != b"." && name.to_bytes() != b".." {
+                names.push(name.to_owned());
+            }
+        }
+        names
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Dir {
+    fn drop(&mut self) {
+        // SAFETY: the stream is open and closed only here
+        unsafe { libc::closedir(self.0) };
+    }
+}
+
+#[cfg(unix)]
+fn stat_at(fd: RawFd, name: &CStr) -> io::Result<libc::stat> {
+    // SAFETY: `stat` is plain old data
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    // SAFETY: `name` is NUL-terminated and `stat` is a valid out pointer
+    let ret = unsafe { libc::fstatat(fd, name.as_ptr(), &mut stat, libc::AT_SYMLINK_NOFOLLOW) };
+    match ret {
+        0 => Ok(stat),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(unix)]
+fn unlink_at(fd: RawFd, name: &CStr, flags: libc::c_int) -> io::Result<()> {
+    // SAFETY: `name` is NUL-terminated
+    match unsafe { libc::unlinkat(fd, name.as_ptr(), flags) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+
+    #[test]
+    pub fn test_remove_dir_with_progress() {
+        let root = tempfile::tempdir().expect("Failed to create tempdir");
+        let venv = root.path().join(".venv");
+        create_dir_all(venv.join("lib/python3.12/site-packages/pkg")).unwrap();
+        write(venv.join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        write(
+            venv.join("lib/python3.12/site-packages/pkg/data.bin"),
+            [0u8; 1000],
+        )
+        .unwrap();
+
+        let mut removed = 0;
+        remove_dir_with_progress(&venv, |bytes| removed += bytes).expect("Failed to remove");
+        assert!(!venv.exists());
+        assert_eq!(removed, 1016);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_remove_read_only_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().expect("Failed to create tempdir");
+        let venv = root.path().join(".venv");
+        // A read-only directory can't have entries removed, unless running as root
+        create_dir_all(venv.join("Lib")).unwrap();
+        write(venv.join("Lib/site.py"), "").unwrap();
+        fs::set_permissions(venv.join("Lib"), fs::Permissions::from_mode(0o555)).unwrap();
+        remove_dir_with_progress(&venv, |_| {}).expect("Failed to remove read-only tree");
+        assert!(!venv.exists());
+    }
+
+    /// Creates `count` small files spread over nested package-like directories
+    fn create_tree(root: &Path, count: usize) {
+        for i in 0..count {
+            let dir = root.join(format!("pkg{}/sub{}", i % 50, i % 7));
+            create_dir_all(&dir).unwrap();
+            write(dir.join(format!("module{}.py", i)), [0u8; 100]).unwrap();
+        }
+    }
+
+    #[test]
+    pub fn test_remove_dir_parallel() {
+        let root = tempfile::tempdir().expect("Failed to create tempdir");
+        let venv = root.path().join(".venv");
+        create_tree(&venv, 500);
+        // Symlinks are removed, not followed
+        let outside = root.path().join("outside");
+        create_dir_all(&outside).unwrap();
+        write(outside.join("keep"), "x").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&outside, venv.join("link")).unwrap();
+
+        let removed = std::sync::atomic::AtomicU64::new(0);
+        remove_dir_parallel(&venv, |bytes| {
+            removed.fetch_add(bytes, std::sync::atomic::Ordering::Relaxed);
+        })
+        .expect("Failed to remove");
+        assert!(!venv.exists());
+        assert!(outside.join("keep").exists());
+        assert!(removed.into_inner() >= 500 * 100);
+    }
+
+    /// Compares against `fs::remove_dir_all`; run with
+    /// `cargo test --release bench_remove -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    pub fn bench_remove() {
+        let root = tempfile::tempdir().expect("Failed to create tempdir");
+        let count = 50_000;
+
+        create_tree(&root.path().join("std"), count);
+        let start = std::time::Instant::now();
+        fs::remove_dir_all(root.path().join("std")).unwrap();
+        println!(
+            "fs::remove_dir_all: {:?} for {} files",
+            start.elapsed(),
+            count
+        );
+
+        create_tree(&root.path().join("parallel"), count);
+        let start = std::time::Instant::now();
+        remove_dir_parallel(&root.path().join("parallel"), |_| {}).unwrap();
+        println!(
+            "remove_dir_parallel: {:?} for {} files",
+            start.elapsed(),
+            count
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::venvs::get_python_version;
+use anyhow::{anyhow, Context, Result};
+use std::env;
+use std::fs::{read_dir, remove_file};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Interpreter names to look for on `PATH`, most specific first
+fn get_interpreter_names(version: Option<&str>) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Some(version) = version {
+        let minor: Vec<&str> = version.split('.').take(2).collect();
+        if minor.len() == 2 {
+            names.push(format!("python{}", minor.join(".")));
+        }
+    }
+    names.push("python3".to_string());
+    names
+}
+
+/// Finds an interpreter on `PATH` matching `version` (e.g. "3.11.7") as closely as possible
+pub fn find_interpreter(version: Option<&str>) -> Option<PathBuf> {
+    let path_var = env::var_os("PATH")?;
+    get_interpreter_names(version).into_iter().find_map(|name| {
+        env::split_paths(&path_var)
+            .map(|dir| dir.join(&name))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+/// Relinks the environment at `venv_root` against `interpreter` (or the
+/// closest match for its recorded Python version) with `python -m venv --upgrade`,
+/// leaving installed packages in place
+pub fn repair_venv(
+    venv_root: &Path,
+    interpreter: Option<&Path>,
+    upgrade_deps: bool,
+) -> Result<PathBuf> {
+    if !venv_root.join("pyvenv.cfg").is_file() {
+        return Err(anyhow!(
+            "{} has no pyvenv.cfg; only venv/virtualenv environments can be repaired",
+            venv_root.display()
+        ));
+    }
+    let interpreter = match interpreter {
+        Some(interpreter) => interpreter.to_path_buf(),
+        None => {
+            let version = get_python_version(venv_root)?;
+            find_interpreter(version.as_deref()).ok_or_else(|| {
+                anyhow!("No Python interpreter found on PATH; pass one with --python")
+            })?
+        }
+    };
+
+    remove_dangling_interpreters(venv_root)?;
+
+    let mut command = Command::new(&interpreter);
+    command.args(["-m", "venv", "--upgrade"]);
+    if upgrade_deps {
+        command.arg("--upgrade-deps");
+    }
+    let status = command
+        .arg(venv_root)
+        .status()
+        .with_context(|| format!("Failed to run {}", interpreter.display()))?;
+    if !status.success() {
+        return Err(anyhow!(
+            "{} -m venv --upgrade failed for {}",
+            interpreter.display(),
+            venv_root.display()
+        ));
+    }
+
+    // Make sure the result actually runs
+    let python = venv_root.join("bin/python");
+    let status = Command::new(&python)
+        .arg("--version")
+        .status()
+        .with_context(|| format!("Failed to run {}", python.display()))?;
+    if !status.success() {
+        return Err(anyhow!("{} is still broken", venv_root.display()));
+    }
+    Ok(interpreter)
+}
+
+/// Removes `bin/python*` symlinks that lead nowhere; `venv --upgrade`
+/// trips over them and recreates them anyway
+fn remove_dangling_interpreters(venv_root: &Path) -> Result<()> {
+    let bin_dir = venv_root.join("bin");
+    for entry in
+        read_dir(&bin_dir).with_context(|| format!("Failed to read {}", bin_dir.display()))?
+    {
+        let path = entry?.path();
+        let is_interpreter = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("python"));
+        if is_interpreter && path.is_symlink() && !path.exists() {
+            remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_get_interpreter_names() {
+        assert_eq!(
+            get_interpreter_names(Some("3.11.7")),
+            vec!["python3.11", "python3"]
+        );
+        assert_eq!(get_interpreter_names(Some("Unknown")), vec!["python3"]);
+        assert_eq!(get_interpreter_names(None), vec!["python3"]);
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+use std::fs::symlink_metadata;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Why part of an environment could not be deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cause {
+    /// The file has the immutable or append-only attribute (`chattr +i` / `chflags uchg`)
+    Immutable,
+    /// Another filesystem is mounted here
+    MountPoint,
+    /// The directory isn't writable, so its entries can't be unlinked
+    ReadOnlyDir,
+}
+
+impl fmt::Display for Cause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cause::Immutable => write!(f, "immutable or append-only"),
+            Cause::MountPoint => write!(f, "mount point"),
+            Cause::ReadOnlyDir => write!(f, "directory not writable"),
+        }
+    }
+}
+
+/// What is left of a directory after trying to delete it
+#[derive(Debug, Default)]
+pub struct Residue {
+    pub files: u64,
+    pub bytes: u64,
+    /// Entries that explain why the deletion couldn't finish
+    pub blockers: Vec<(PathBuf, Cause)>,
+}
+
+impl Residue {
+    pub fn is_empty(&self) -> bool {
+        self.files == 0
+    }
+}
+
+/// Takes stock of anything left at `path`, without crossing into other
+/// filesystems mounted inside it
+pub fn find_residue(path: &Path) -> Residue {
+    let mut residue = Residue::default();
+    let root_dev = match symlink_metadata(path) {
+        Ok(metadata) => get_device(&metadata),
+        Err(_) => return residue,
+    };
+
+    let mut entries = WalkDir::new(path).follow_links(false).into_iter();
+    while let Some(entry) = entries.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        residue.files += 1;
+        residue.bytes += metadata.len();
+
+        let cause = match (get_device(&metadata) != root_dev, metadata.is_dir()) {
+            (true, _) => Some(Cause::MountPoint),
+            _ if is_immutable(entry.path(), &metadata) => Some(Cause::Immutable),
+            (false, true) if !is_writable(entry.path()) => Some(Cause::ReadOnlyDir),
+            _ => None,
+        };
+        if let Some(cause) = cause {
+            residue.blockers.push((entry.path().to_path_buf(), cause));
+        }
+        if cause == Some(Cause::MountPoint) {
+            entries.skip_current_dir();
+        }
+    }
+    residue
+}
+
+#[cfg(unix)]
+fn get_device(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.dev()
+}
+
+/// Mount points aren't told apart on Windows
+#[cfg(not(unix))]
+fn get_device(_metadata: &std::fs::Metadata) -> u64 {
+    0
+}
+
+#[cfg(unix)]
+fn is_writable(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    let path = match std::ffi::CString::new(path.as_os_str().as_bytes()) {
+        Ok(path) => path,
+        Err(_) => return true,
+    };
+    // SAFETY: `path` is a valid NUL-terminated string
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+/// A read-only directory doesn't stop its entries being deleted on Windows
+#[cfg(not(unix))]
+fn is_writable(_path: &Path) -> bool {
+    true
+}
+
+#[cfg(target_os = "linux")]
+fn is_immutable(path: &Path, metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::AsRawFd;
+
+    const FS_IMMUTABLE_FL: libc::c_long = 0x10;
+    const FS_APPEND_FL: libc::c_long = 0x20;
+
+    // Attribute flags only exist on regular files and directories
+    if !(metadata.is_file() || metadata.is_dir()) {
+        return false;
+    }
+    let file = match std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK | libc::O_NOFOLLOW)
+        .open(path)
+    {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+    let mut flags: libc::c_long = 0;
+    // SAFETY: FS_IOC_GETFLAGS writes a single long into `flags`
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) };
+    ret == 0 && flags & (FS_IMMUTABLE_FL | FS_APPEND_FL) != 0
+}
+
+#[cfg(target_os = "macos")]
+fn is_immutable(_path: &Path, metadata: &std::fs::Metadata) -> bool {
+    use std::os::macos::fs::MetadataExt;
+    const IMMUTABLE_FLAGS: u32 = 0x0000_0002 | 0x0000_0004 | 0x0002_0000 | 0x0004_0000;
+    metadata.st_flags() & IMMUTABLE_FLAGS != 0
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn is_immutable(_path: &Path, _metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// Bytes available to unprivileged users on the filesystem holding `path`
+#[cfg(unix)]
+pub fn get_available_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid out pointer
+    match unsafe { libc::statvfs(path.as_ptr(), &mut stat) } {
+        0 => Some(stat.f_bavail as u64 * stat.f_frsize as u64),
+        _ => None,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn get_available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_find_residue() {
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+        assert!(find_residue(&dir.path().join("missing")).is_empty());
+
+        std::fs::write(dir.path().join("leftover"), "abc").expect("Failed to write file");
+        let residue = find_residue(dir.path());
+        // The directory itself and the file
+        assert_eq!(residue.files, 2);
+        assert!(residue.bytes >= 3);
+        assert!(residue.blockers.is_empty());
+
+        assert!(get_available_space(dir.path()).is_some());
+    }
+}
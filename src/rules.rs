@@ -0,0 +1,157 @@
+use crate::manager::Manager;
+use crate::metadata::normalize_tag;
+use crate::size::parse_size;
+use crate::venvs::VirtualEnv;
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Local};
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// Examples shown when asking for a rule
+pub const RULE_HELP: &str =
+    "e.g. \"older than 90d\", \"larger than 1GB\", \"manager is conda\", \"tagged experiments\", \"broken\", \"orphaned\", \"superseded\", \"ml\"";
+
+/// A condition for selecting environments in bulk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    /// Not modified within the duration
+    OlderThan(Duration),
+    /// Larger than this many bytes
+    LargerThan(u64),
+    Manager(Manager),
+    /// Has this tag
+    Tagged(String),
+    /// The interpreter is missing
+    Broken,
+    /// The project the environment belongs to is gone
+    Orphaned,
+    /// Another environment of the same project has a newer interpreter
+    Superseded,
+    /// Has ML frameworks like torch installed
+    Ml,
+}
+
+/// Parses ages like "90d", "6w", "3m" (30-day months) or "1y"
+pub fn parse_age(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: i64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid age: {:?}", input))?;
+    let days = match unit.trim() {
+        "d" | "day" | "days" => 1,
+        "w" | "week" | "weeks" => 7,
+        "m" | "month" | "months" => 30,
+        "y" | "year" | "years" => 365,
+        _ => return Err(anyhow!("Unknown age unit in {:?}", input)),
+    };
+    Ok(Duration::days(number * days))
+}
+
+/// An age written like "7d" in the config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Age(pub Duration);
+
+impl TryFrom<String> for Age {
+    type Error = anyhow::Error;
+
+    fn try_from(text: String) -> Result<Self> {
+        parse_age(&text).map(Age)
+    }
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        if let Some(age) = s.strip_prefix("older than") {
+            return Ok(Rule::OlderThan(parse_age(age)?));
+        }
+        if let Some(size) = s.strip_prefix("larger than") {
+            return Ok(Rule::LargerThan(parse_size(size)?));
+        }
+        if let Some(manager) = s.strip_prefix("manager is") {
+            return Ok(Rule::Manager(manager.parse()?));
+        }
+        if let Some(tag) = s.strip_prefix("tagged") {
+            let tag = normalize_tag(tag);
+            if !tag.is_empty() {
+                return Ok(Rule::Tagged(tag));
+            }
+        }
+        if s == "broken" {
+            return Ok(Rule::Broken);
+        }
+        if s == "orphaned" {
+            return Ok(Rule::Orphaned);
+        }
+        if s == "superseded" {
+            return Ok(Rule::Superseded);
+        }
+        if s == "ml" {
+            return Ok(Rule::Ml);
+        }
+        Err(anyhow!("Unknown rule {:?}, {}", s, RULE_HELP))
+    }
+}
+
+impl Rule {
+    pub fn matches(&self, venv: &VirtualEnv) -> bool {
+        match self {
+            // Environments of unknown age are never considered old
+            Rule::OlderThan(age) => venv
+                .modified
+                .is_some_and(|modified| Local::now() - modified > *age),
+            Rule::LargerThan(size) => venv.venv_size > *size,
+            Rule::Manager(manager) => venv.manager == *manager,
+            Rule::Tagged(tag) => venv.tags.contains(tag),
+            Rule::Broken => venv.is_broken(),
+            Rule::Orphaned => venv.is_orphaned(),
+            Rule::Superseded => venv.superseded_by.is_some(),
+            Rule::Ml => venv.ml_size > 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_rule() {
+        assert_eq!(
+            "older than 90d".parse::<Rule>().unwrap(),
+            Rule::OlderThan(Duration::days(90))
+        );
+        assert_eq!(
+            "Larger than 1GB".parse::<Rule>().unwrap(),
+            Rule::LargerThan(1_000_000_000)
+        );
+        assert_eq!(
+            "manager is conda".parse::<Rule>().unwrap(),
+            Rule::Manager(Manager::Conda)
+        );
+        assert_eq!(
+            "tagged Experiments".parse::<Rule>().unwrap(),
+            Rule::Tagged("experiments".to_string())
+        );
+        assert_eq!("broken".parse::<Rule>().unwrap(), Rule::Broken);
+        assert_eq!("Orphaned".parse::<Rule>().unwrap(), Rule::Orphaned);
+        assert_eq!("superseded".parse::<Rule>().unwrap(), Rule::Superseded);
+        assert_eq!("ML".parse::<Rule>().unwrap(), Rule::Ml);
+        assert!("bigger than 1GB".parse::<Rule>().is_err());
+
+        let venv = VirtualEnv {
+            venv_size: 2_000_000_000,
+            modified: Some(Local::now() - Duration::days(100)),
+            ..Default::default()
+        };
+        assert!(Rule::OlderThan(Duration::days(90)).matches(&venv));
+        assert!(!Rule::LargerThan(3_000_000_000).matches(&venv));
+    }
+}
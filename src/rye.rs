@@ -0,0 +1,96 @@
+use crate::manager::Manager;
+use crate::pyenv::get_dependents;
+use crate::venvs::{build_virtualenv, VirtualEnv};
+use std::env;
+use std::fs::{canonicalize, read_dir};
+use std::path::{Path, PathBuf};
+
+/// `$RYE_HOME`, defaulting to `~/.rye`
+pub fn get_rye_home(home_dir: &Path) -> PathBuf {
+    match env::var_os("RYE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => home_dir.join(".rye"),
+    }
+}
+
+/// The environments `rye install` creates for global tools
+pub fn get_rye_tools_dir(home_dir: &Path) -> PathBuf {
+    get_rye_home(home_dir).join("tools")
+}
+
+/// The Python version of a toolchain directory like `cpython@3.12.3`
+fn get_toolchain_version(toolchain_dir: &Path) -> Option<String> {
+    let name = toolchain_dir.file_name()?.to_str()?;
+    let (_, version) = name.split_once('@')?;
+    Some(version.to_string())
+}
+
+/// The Python installations Rye manages in `py/` of each home, with the
+/// environments in `venvs` that would break without them
+pub fn get_rye_toolchains(homes: &[PathBuf], venvs: &[VirtualEnv]) -> Vec<VirtualEnv> {
+    let mut toolchains = Vec::new();
+    for home in homes {
+        let entries = match read_dir(get_rye_home(home).join("py")) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            let path = match canonicalize(entry.path()) {
+                Ok(path) if is_dir => path,
+                _ => continue,
+            };
+            match build_virtualenv(path) {
+                Ok(mut toolchain) => {
+                    toolchain.manager = Manager::RyeToolchain;
+                    if let Some(version) = get_toolchain_version(&toolchain.path) {
+                        toolchain.python_version = version;
+                    }
+                    toolchain.dependents = get_dependents(&toolchain.path, venvs);
+                    toolchains.push(toolchain);
+                }
+                Err(err) => eprintln!("Error building Rye toolchain: {}", err),
+            }
+        }
+    }
+    toolchains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_get_rye_toolchains() {
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+        let home = canonicalize(dir.path()).unwrap();
+        let toolchain = home.join(".rye/py/cpython@3.12.3");
+        // python-build-standalone installs have no `python` alias
+        std::fs::create_dir_all(toolchain.join("bin")).unwrap();
+        std::fs::create_dir_all(toolchain.join("lib/python3.12")).unwrap();
+        std::fs::File::create(toolchain.join("bin/python3")).unwrap();
+        std::fs::create_dir_all(home.join(".rye/py/pypy@3.10.14")).unwrap();
+        let venv = home.join("code/app/.venv");
+        std::fs::create_dir_all(&venv).unwrap();
+        std::fs::write(
+            venv.join("pyvenv.cfg"),
+            format!("home = {}\n", toolchain.join("bin").display()),
+        )
+        .unwrap();
+
+        let venvs = vec![VirtualEnv {
+            path: venv.clone(),
+            ..Default::default()
+        }];
+        let mut toolchains = get_rye_toolchains(&[home], &venvs);
+        toolchains.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(toolchains.len(), 2);
+        assert_eq!(toolchains[0].path, toolchain);
+        assert_eq!(toolchains[0].manager, Manager::RyeToolchain);
+        assert_eq!(toolchains[0].python_version, "3.12.3");
+        assert_eq!(toolchains[0].dependents, vec![venv]);
+        assert!(!toolchains[0].is_broken());
+        assert_eq!(toolchains[1].python_version, "3.10.14");
+        assert!(toolchains[1].dependents.is_empty());
+    }
+}
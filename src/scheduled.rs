@@ -0,0 +1,195 @@
+use crate::venvs::VirtualEnv;
+use std::fs::{canonicalize, read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where other users' crontabs are kept on Debian, Red Hat and macOS
+const CRONTAB_SPOOLS: [&str; 3] = [
+    "/var/spool/cron/crontabs",
+    "/var/spool/cron",
+    "/usr/lib/cron/tabs",
+];
+
+/// A crontab or systemd unit, which may run an environment's interpreter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledJob {
+    /// Shown next to environments it refers to, e.g. "crontab"
+    pub source: String,
+    pub text: String,
+    /// Home directory `~` and `%h` refer to
+    pub home: Option<PathBuf>,
+}
+
+/// Reads the current user's crontab, the crontabs of other users when
+/// readable, and the systemd user units in `homes`
+pub fn get_scheduled_jobs(homes: &[PathBuf]) -> Vec<ScheduledJob> {
+    let mut jobs = Vec::new();
+    if let Ok(output) = Command::new("crontab").arg("-l").output() {
+        if output.status.success() {
+            jobs.push(ScheduledJob {
+                source: "crontab".to_string(),
+                text: String::from_utf8_lossy(&output.stdout).into_owned(),
+                home: dirs::home_dir(),
+            });
+        }
+    }
+    for spool in CRONTAB_SPOOLS {
+        for path in get_files(Path::new(spool)) {
+            let user = path.file_name().unwrap_or_default().to_string_lossy();
+            if let Ok(text) = read_to_string(&path) {
+                jobs.push(ScheduledJob {
+                    source: format!("crontab of {}", user),
+                    text,
+                    home: None,
+                });
+            }
+        }
+    }
+    for home in homes {
+        let units = get_files(&home.join(".config/systemd/user"))
+            .into_iter()
+            .filter(|path| path.extension().is_some_and(|ext| ext == "service"));
+        for unit in units {
+            if let Ok(text) = read_to_string(&unit) {
+                jobs.push(ScheduledJob {
+                    source: unit.display().to_string(),
+                    text,
+                    home: Some(home.clone()),
+                });
+            }
+        }
+    }
+    jobs
+}
+
+/// Regular files directly inside `dir`
+fn get_files(dir: &Path) -> Vec<PathBuf> {
+    match read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// The absolute paths mentioned in `job`, expanding `~` and `%h`
+fn extract_paths(job: &ScheduledJob) -> Vec<PathBuf> {
+    job.text
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .flat_map(|line| line.split(|c: char| c.is_whitespace() || "\"'=:;|&()<>`".contains(c)))
+        .filter_map(|word| {
+            let relative = word.strip_prefix("~/").or_else(|| word.strip_prefix("%h/"));
+            match (relative, &job.home) {
+                (Some(relative), Some(home)) => Some(home.join(relative)),
+                _ if word.starts_with('/') => Some(PathBuf::from(word)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// `path` with symlinks resolved in the directories leading to it, but not
+/// in its last component, which is usually an interpreter linking elsewhere
+fn resolve_parent(path: &Path) -> Option<PathBuf> {
+    let parent = canonicalize(path.parent()?).ok()?;
+    Some(parent.join(path.file_name()?))
+}
+
+/// Sets `scheduled_by` on every environment a job refers to a path inside of,
+/// directly, through the environment's symlink or through other symlinks
+pub fn mark_scheduled(venvs: &mut [VirtualEnv], jobs: &[ScheduledJob]) {
+    for job in jobs {
+        for path in extract_paths(job) {
+            let resolved = resolve_parent(&path);
+            for venv in venvs.iter_mut() {
+                let refers = |path: &Path| {
+                    path.starts_with(&venv.path)
+                        || venv
+                            .link
+                            .as_ref()
+                            .is_some_and(|link| path.starts_with(link))
+                };
+                if venv.scheduled_by.is_none()
+                    && (refers(&path) || resolved.as_deref().is_some_and(refers))
+                {
+                    venv.scheduled_by = Some(job.source.clone());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_mark_scheduled() {
+        let job = ScheduledJob {
+            source: "crontab".to_string(),
+            text: "\
+# 0 * * * * /home/me/.virtualenvs/old/bin/python backup.py
+*/5 * * * * cd /srv && /home/me/.virtualenvs/etl/bin/python -m etl >/tmp/etl.log
+0 3 * * * PATH=~/envs/report/bin:/usr/bin report
+"
+            .to_string(),
+            home: Some(PathBuf::from("/home/me")),
+        };
+        let venv = |path: &str| VirtualEnv {
+            path: PathBuf::from(path),
+            ..Default::default()
+        };
+        let mut venvs = vec![
+            venv("/home/me/.virtualenvs/etl"),
+            venv("/home/me/.virtualenvs/old"),
+            venv("/home/me/envs/report"),
+            venv("/home/me/.virtualenvs/et"),
+        ];
+        mark_scheduled(&mut venvs, &[job]);
+        let scheduled: Vec<bool> = venvs
+            .iter()
+            .map(|venv| venv.scheduled_by.is_some())
+            .collect();
+        // Commented-out lines and mere string prefixes don't count
+        assert_eq!(scheduled, vec![true, false, true, false]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_mark_scheduled_symlinks() {
+        let dir = tempfile::tempdir().expect("Failed to create tempdir");
+        let home = canonicalize(dir.path()).unwrap();
+        let venv = home.join(".pyenv/versions/3.12.4/envs/etl");
+        std::fs::create_dir_all(venv.join("bin")).unwrap();
+        std::os::unix::fs::symlink("/usr/bin/python3", venv.join("bin/python")).unwrap();
+        let link = home.join(".pyenv/versions/etl");
+        std::os::unix::fs::symlink(&venv, &link).unwrap();
+        std::os::unix::fs::symlink(home.join(".pyenv"), home.join("pyenv")).unwrap();
+
+        for text in [
+            "0 3 * * * ~/.pyenv/versions/etl/bin/python -m etl",
+            "0 3 * * * ~/pyenv/versions/3.12.4/envs/etl/bin/python -m etl",
+        ] {
+            let job = ScheduledJob {
+                source: "crontab".to_string(),
+                text: text.to_string(),
+                home: Some(home.clone()),
+            };
+            let mut venvs = vec![VirtualEnv {
+                path: venv.clone(),
+                link: Some(link.clone()),
+                ..Default::default()
+            }];
+            mark_scheduled(&mut venvs, &[job]);
+            assert_eq!(
+                venvs[0].scheduled_by.as_deref(),
+                Some("crontab"),
+                "{}",
+                text
+            );
+        }
+    }
+}
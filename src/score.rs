@@ -0,0 +1,68 @@
+use crate::venvs::VirtualEnv;
+use chrono::Local;
+
+const GIB: f64 = (1u64 << 30) as f64;
+
+/// How worthwhile deleting `venv` is: higher for big, long unused, broken,
+/// orphaned and duplicated environments. Pinned environments score 0.
+///
+/// Size counts logarithmically (1 GiB adds 10, 3 GiB 20, 7 GiB 30) so that
+/// one huge environment in daily use doesn't outrank a pile of abandoned ones.
+pub fn reclaim_score(venv: &VirtualEnv) -> f64 {
+    if venv.pinned {
+        return 0.0;
+    }
+    let size = (1.0 + venv.reclaimable_size() as f64 / GIB).log2() * 10.0;
+    // Two points per month unused, up to two years
+    let months_unused = venv.modified.map_or(0.0, |modified| {
+        (Local::now() - modified).num_days() as f64 / 30.0
+    });
+    let staleness = months_unused.clamp(0.0, 24.0) * 2.0;
+
+    let mut score = size + staleness;
+    if venv.is_broken() {
+        score += 30.0;
+    }
+    if venv.is_orphaned() {
+        score += 20.0;
+    }
+    if venv.clone_of.is_some() {
+        score += 15.0;
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::path::PathBuf;
+
+    #[test]
+    pub fn test_reclaim_score() {
+        let now = Local::now();
+        let venv = |size: u64, days_old: i64| VirtualEnv {
+            // Never exists, so every test environment counts as broken
+            python_path: PathBuf::from("/nonexistent/bin/python"),
+            venv_size: size,
+            modified: Some(now - Duration::days(days_old)),
+            ..Default::default()
+        };
+
+        let fresh_huge = venv(7 << 30, 0);
+        let stale_small = venv(100 << 20, 730);
+        assert!(reclaim_score(&stale_small) > reclaim_score(&fresh_huge));
+
+        let clone = VirtualEnv {
+            clone_of: Some(PathBuf::from("/envs/original")),
+            ..fresh_huge.clone()
+        };
+        assert!(reclaim_score(&clone) > reclaim_score(&fresh_huge));
+
+        let pinned = VirtualEnv {
+            pinned: true,
+            ..stale_small
+        };
+        assert_eq!(reclaim_score(&pinned), 0.0);
+    }
+}
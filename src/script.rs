@@ -0,0 +1,73 @@
+use crate::notify::get_hostname;
+use crate::remote::shell_quote_os;
+use crate::size::format_size;
+use crate::venvs::VirtualEnv;
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::fs;
+use std::path::Path;
+
+/// Renders a commented shell script removing `venvs`, for review before it is run
+pub fn render_cleanup_script(venvs: &[VirtualEnv], host: &str) -> String {
+    let total_size: u64 = venvs.iter().map(|venv| venv.reclaimable_size()).sum();
+    let mut script = format!(
+        "#!/bin/sh\n\
+         # Generated by venvpruner on {} for {}.\n\
+         # Removes {} virtual environments, reclaiming about {}.\n\
+         # Review before running. To move them to the trash instead, run with\n\
+         # e.g. REMOVE=\"trash\" or REMOVE=\"gio trash\".\n\
+         set -eu\n\
+         REMOVE=\"${{REMOVE:-rm -rf}}\"\n",
+        Local::now().format("%Y-%m-%d %H:%M"),
+        host,
+        venvs.len(),
+        format_size(total_size)
+    );
+    for venv in venvs {
+        script.push_str(&format!(
+            "\n# {}\n$REMOVE {}\n",
+            venv.to_string().replace('\n', " "),
+            shell_quote_os(venv.path.as_os_str())
+        ));
+    }
+    script
+}
+
+/// Writes the cleanup script for `venvs` to `path` and makes it executable
+pub fn write_cleanup_script(path: &Path, venvs: &[VirtualEnv]) -> Result<()> {
+    fs::write(path, render_cleanup_script(venvs, &get_hostname()))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    make_executable(path)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("Failed to make {} executable", path.display()))
+}
+
+/// Windows has no executable bit; the script is run with `sh` there
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    pub fn test_render_cleanup_script() {
+        let venvs = vec![VirtualEnv {
+            name: ".venv".to_string(),
+            path: PathBuf::from("/home/me/it's here/.venv"),
+            ..Default::default()
+        }];
+        let script = render_cleanup_script(&venvs, "devbox");
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("for devbox."));
+        assert!(script.contains("\n$REMOVE '/home/me/it'\\''s here/.venv'\n"));
+    }
+}
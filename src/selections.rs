@@ -0,0 +1,92 @@
+use crate::history::get_data_dir;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{read_to_string, write};
+use std::path::PathBuf;
+
+/// Environments picked in a review session, kept for a later one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Selection {
+    pub updated: DateTime<Local>,
+    #[serde(with = "crate::paths::vec")]
+    pub paths: Vec<PathBuf>,
+}
+
+/// Saved selections by name
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Selections(BTreeMap<String, Selection>);
+
+/// `~/.local/share/venvpruner/selections.json`
+fn get_selections_path() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("selections.json"))
+}
+
+impl Selections {
+    pub fn load() -> Result<Selections> {
+        let path = get_selections_path()?;
+        if !path.exists() {
+            return Ok(Selections::default());
+        }
+        let contents =
+            read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&contents).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = get_selections_path()?;
+        write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Selection> {
+        self.0.get(name)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &Selection)> {
+        self.0.iter()
+    }
+
+    /// Saves `paths` under `name`, replacing what was there
+    pub fn set(&mut self, name: &str, mut paths: Vec<PathBuf>) {
+        paths.sort();
+        paths.dedup();
+        let selection = Selection {
+            updated: Local::now(),
+            paths,
+        };
+        self.0.insert(name.to_string(), selection);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Selection> {
+        self.0.remove(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_selections() {
+        let mut selections = Selections::default();
+        selections.set(
+            "ml-cleanup",
+            vec![PathBuf::from("/envs/b"), PathBuf::from("/envs/a")],
+        );
+        selections.set("ml-cleanup", vec![PathBuf::from("/envs/b")]);
+        assert_eq!(
+            selections
+                .get("ml-cleanup")
+                .map(|selection| &selection.paths),
+            Some(&vec![PathBuf::from("/envs/b")])
+        );
+
+        let json = serde_json::to_string(&selections).expect("Failed to serialize");
+        let mut selections: Selections = serde_json::from_str(&json).expect("Failed to parse");
+        assert_eq!(selections.entries().count(), 1);
+        assert!(selections.remove("ml-cleanup").is_some());
+        assert!(selections.get("ml-cleanup").is_none());
+    }
+}
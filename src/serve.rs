@@ -0,0 +1,198 @@
+// Requests are only read from a Unix socket
+#![cfg_attr(not(unix), allow(dead_code))]
+
+use crate::cache;
+use crate::venvs::{load_venvs, VirtualEnv};
+use crate::{check_not_protected, App};
+use anyhow::{anyhow, Context, Result};
+use indicatif::ProgressBar;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+#[cfg(unix)]
+use std::fs::remove_file;
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::{
+    fs::FileTypeExt,
+    net::{UnixListener, UnixStream},
+};
+use std::path::{Path, PathBuf};
+
+/// One line of input: a JSON-RPC 2.0 style call
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteParams {
+    paths: Vec<PathBuf>,
+}
+
+/// One line of output, answering the request with the same `id`
+#[derive(Debug, Serialize)]
+pub struct Response {
+    pub jsonrpc: &'static str,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+impl Response {
+    fn new(id: Value, result: Result<Value>) -> Response {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(err) => (
+                None,
+                Some(json!({"code": -32000, "message": format!("{:#}", err)})),
+            ),
+        };
+        Response {
+            jsonrpc: "2.0",
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+/// Listens on `socket` and answers newline-delimited JSON requests until killed.
+///
+/// Methods:
+/// - `scan`: rescan and return the environments
+/// - `list`: return the environments of the previous scan, scanning if there is none
+/// - `delete` with `{"paths": [...]}`: delete those environments
+///
+/// Clients are served one at a time so a delete never races a scan.
+#[cfg(unix)]
+pub fn serve(app: &App, socket: &Path) -> Result<()> {
+    if let Ok(metadata) = socket.symlink_metadata() {
+        if !metadata.file_type().is_socket() {
+            return Err(anyhow!("{} exists and is not a socket", socket.display()));
+        }
+        if UnixStream::connect(socket).is_ok() {
+            return Err(anyhow!(
+                "A server is already listening on {}",
+                socket.display()
+            ));
+        }
+        // Left behind by a server that was killed
+        remove_file(socket).with_context(|| format!("Failed to remove {}", socket.display()))?;
+    }
+    let listener = UnixListener::bind(socket)
+        .with_context(|| format!("Failed to listen on {}", socket.display()))?;
+    eprintln!("Listening on {}", socket.display());
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(err) = handle_client(app, stream) {
+                    eprintln!("Client error: {:#}", err);
+                }
+            }
+            Err(err) => eprintln!("Failed to accept connection: {}", err),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn serve(_app: &App, _socket: &Path) -> Result<()> {
+    Err(anyhow!(
+        "serve needs Unix domain sockets, which this platform lacks"
+    ))
+}
+
+#[cfg(unix)]
+fn handle_client(app: &App, stream: UnixStream) -> Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = handle_line(app, &line);
+        writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+    }
+    Ok(())
+}
+
+pub fn handle_line(app: &App, line: &str) -> Response {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(err) => return Response::new(Value::Null, Err(anyhow!("Invalid request: {}", err))),
+    };
+    let result = match request.method.as_str() {
+        "scan" => scan(app).and_then(to_value),
+        "list" => list(app).and_then(to_value),
+        "delete" => delete(app, request.params).and_then(to_value),
+        method => Err(anyhow!("Unknown method: {}", method)),
+    };
+    Response::new(request.id, result)
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value> {
+    Ok(serde_json::to_value(value)?)
+}
+
+fn scan(app: &App) -> Result<Vec<VirtualEnv>> {
+    app.scan(&ProgressBar::hidden())
+}
+
+fn list(app: &App) -> Result<Vec<VirtualEnv>> {
+    match cache::load_last_scan()? {
+        Some(cache) => Ok(cache.venvs),
+        None => scan(app),
+    }
+}
+
+fn delete(app: &App, params: Value) -> Result<Vec<VirtualEnv>> {
+    let params: DeleteParams =
+        serde_json::from_value(params).context("delete expects {\"paths\": [...]}")?;
+    let venvs = load_venvs(&params.paths)?;
+    check_not_protected(app, &venvs)?;
+    app.delete(&venvs)?;
+    Ok(venvs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::venvs::ScanOptions;
+    use crate::Target;
+
+    #[test]
+    pub fn test_handle_line_errors() {
+        let app = App {
+            target: Target::Local(ScanOptions::default()),
+            config: Config::default(),
+            delete_options: Default::default(),
+            managers: Default::default(),
+            tags: vec![],
+            broken_only: false,
+            orphaned_only: false,
+            ml_only: false,
+            absolute_times: false,
+            sort: crate::SortKey::Size,
+        };
+
+        let response = handle_line(&app, "not json");
+        assert_eq!(response.id, Value::Null);
+        assert!(response.error.is_some());
+
+        let response = handle_line(&app, r#"{"id": 7, "method": "format_disk"}"#);
+        assert_eq!(response.id, json!(7));
+        assert!(response.result.is_none());
+
+        let response = handle_line(&app, r#"{"id": 8, "method": "delete", "params": {}}"#);
+        assert!(response.error.is_some());
+    }
+}
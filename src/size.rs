@@ -0,0 +1,158 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether sizes are printed as exact byte counts (`--bytes`)
+static EXACT_BYTES: AtomicBool = AtomicBool::new(false);
+
+/// Whether sizes are printed in powers of 1000 (`--units si`)
+static SI_UNITS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_exact_bytes(exact: bool) {
+    EXACT_BYTES.store(exact, Ordering::Relaxed);
+}
+
+/// Units of human-readable sizes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    /// Powers of 1000: kB, MB, GB, as disk vendors count
+    Si,
+    /// Powers of 1024: KiB, MiB, GiB, as most filesystems tools count
+    #[default]
+    Binary,
+}
+
+pub fn set_units(units: Units) {
+    SI_UNITS.store(units == Units::Si, Ordering::Relaxed);
+}
+
+/// Formats a byte count for display, like "1.5 GiB", or as the exact number
+/// of bytes with `--bytes`
+pub fn format_size(bytes: u64) -> String {
+    match EXACT_BYTES.load(Ordering::Relaxed) {
+        true => bytes.to_string(),
+        false => format_human(bytes),
+    }
+}
+
+/// Formats a byte count like "1.5 GiB" or "1.6 GB", depending on `--units`
+pub fn format_human(bytes: u64) -> String {
+    let units = match SI_UNITS.load(Ordering::Relaxed) {
+        true => Units::Si,
+        false => Units::Binary,
+    };
+    format_in_units(bytes, units)
+}
+
+fn format_in_units(bytes: u64, units: Units) -> String {
+    let (base, suffixes) = match units {
+        Units::Si => (1000.0, ["B", "kB", "MB", "GB", "TB", "PB", "EB"]),
+        Units::Binary => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"]),
+    };
+    // f64 is exact up to 8 PiB, unlike f32 which is off by megabytes at a few GB
+    let mut value = bytes as f64;
+    let mut suffix = 0;
+    // Step up once the value would round to a full unit, so there is no "1024 KiB"
+    while (value * 10.0).round() / 10.0 >= base && suffix + 1 < suffixes.len() {
+        value /= base;
+        suffix += 1;
+    }
+    let number = format!("{:.1}", value);
+    format!("{} {}", number.trim_end_matches(".0"), suffixes[suffix])
+}
+
+/// A byte count that can be written as a number or as a string like "40GB"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "RawByteSize")]
+pub struct ByteSize(pub u64);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawByteSize {
+    Bytes(u64),
+    Text(String),
+}
+
+impl TryFrom<RawByteSize> for ByteSize {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawByteSize) -> Result<Self> {
+        match raw {
+            RawByteSize::Bytes(bytes) => Ok(ByteSize(bytes)),
+            RawByteSize::Text(text) => parse_size(&text).map(ByteSize),
+        }
+    }
+}
+
+impl std::str::FromStr for ByteSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_size(s).map(ByteSize)
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_size(self.0))
+    }
+}
+
+/// Parses sizes such as "40GB", "1.5 GiB", "500M" or "1024".
+///
+/// KB/MB/GB/TB are decimal, KiB/MiB/GiB/TiB and the single-letter
+/// K/M/G/T (as in `du -h`) are binary.
+pub fn parse_size(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid size: {:?}", input))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        _ => return Err(anyhow!("Unknown size unit in {:?}", input)),
+    };
+
+    Ok((number * multiplier as f64).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_size() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("40GB").unwrap(), 40_000_000_000);
+        assert_eq!(parse_size("1.5 GiB").unwrap(), 1_610_612_736);
+        assert_eq!(parse_size("500M").unwrap(), 500 * 1024 * 1024);
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+    }
+
+    #[test]
+    pub fn test_format_in_units() {
+        assert_eq!(format_in_units(0, Units::Binary), "0 B");
+        assert_eq!(format_in_units(1_610_612_736, Units::Binary), "1.5 GiB");
+        assert_eq!(format_in_units(1_610_612_736, Units::Si), "1.6 GB");
+        assert_eq!(format_in_units(40_000_000_000, Units::Si), "40 GB");
+        assert_eq!(format_in_units(1023, Units::Binary), "1023 B");
+        assert_eq!(format_in_units(1_048_575, Units::Binary), "1 MiB");
+        // Both round-trip through parse_size
+        assert_eq!(parse_size(&format_in_units(1500, Units::Si)).unwrap(), 1500);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::venvs::get_dir_size;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs::{read_to_string, remove_dir_all, remove_file};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Directories of installed packages removed by aggressive slimming
+const STRIPPABLE_DIRS: &[&str] = &["tests", "testing", "docs", "doc", "examples"];
+
+/// Packages whose test helpers other packages import at runtime
+/// (e.g. `numpy.testing`), never stripped
+const DEFAULT_KEEP: &[&str] = &["numpy", "torch", "pandas"];
+
+/// The `[slim]` section of the config file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SlimConfig {
+    /// Top-level packages to leave alone when stripping tests and docs,
+    /// in addition to the built-in ones
+    pub keep: Vec<String>,
+}
+
+/// What `plan_slim` is allowed to remove
+#[derive(Debug, Clone, Default)]
+pub struct SlimOptions {
+    /// Also strip tests, docs and examples bundled with installed packages
+    pub aggressive: bool,
+    /// Top-level packages exempt from aggressive stripping
+    pub keep: Vec<String>,
+}
+
+impl SlimOptions {
+    pub fn new(aggressive: bool, config: &SlimConfig) -> SlimOptions {
+        let mut keep: Vec<String> = DEFAULT_KEEP.iter().map(|name| name.to_string()).collect();
+        keep.extend(config.keep.iter().cloned());
+        SlimOptions { aggressive, keep }
+    }
+}
+
+/// Files and directories inside a venv that can be removed without breaking it
+#[derive(Debug, Default)]
+pub struct SlimPlan {
+    pub paths: Vec<PathBuf>,
+    pub size: u64,
+}
+
+/// Finds regenerable content inside `venv_root`: bytecode caches, pip's
+/// leftover temporary directories and self-check state, and `.dist-info`
+/// directories whose RECORD files are all gone, and, when aggressive,
+/// the tests and docs shipped inside packages
+pub fn plan_slim(venv_root: &Path, options: &SlimOptions) -> SlimPlan {
+    let mut paths = Vec::new();
+    let mut walker = WalkDir::new(venv_root).follow_links(false).into_iter();
+    while let Some(entry) = walker.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        let is_dir = entry.file_type().is_dir();
+        if is_regenerable(entry.path(), is_dir) || (is_dir && is_strippable(entry.path(), options))
+        {
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            paths.push(entry.into_path());
+        }
+    }
+
+    let size = paths.iter().map(|path| get_dir_size(path)).sum();
+    SlimPlan { paths, size }
+}
+
+fn is_regenerable(path: &Path, is_dir: bool) -> bool {
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return false,
+    };
+    let in_site_packages = path
+        .parent()
+        .and_then(|parent| parent.file_name())
+        .is_some_and(|parent| parent == "site-packages");
+
+    match is_dir {
+        true => {
+            name == "__pycache__"
+                // pip renames packages to "~name" while uninstalling them
+                || (in_site_packages && name.starts_with('~'))
+                || (in_site_packages && name.ends_with(".dist-info") && is_orphaned_dist_info(path))
+        }
+        false => {
+            // Python 2 style bytecode next to its source; without the source
+            // it may be a sourceless distribution that still needs it
+            ((name.ends_with(".pyc") || name.ends_with(".pyo"))
+                && path.with_extension("py").exists())
+                || name == "pip-selfcheck.json"
+                || name == "selfcheck.json"
+        }
+    }
+}
+
+fn is_strippable(path: &Path, options: &SlimOptions) -> bool {
+    if !options.aggressive
+        || !path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| STRIPPABLE_DIRS.contains(&name))
+    {
+        return false;
+    }
+    // Only inside site-packages, exempting the allowlisted packages
+    let mut components = path.components().map(|component| component.as_os_str());
+    if !components.any(|component| component == "site-packages") {
+        return false;
+    }
+    match components.next().and_then(|package| package.to_str()) {
+        Some(package) => !options.keep.iter().any(|keep| keep == package),
+        None => false,
+    }
+}
+
+/// A `.dist-info` left behind by a botched uninstall: none of the package
+/// files its RECORD lists exist any more
+fn is_orphaned_dist_info(dist_info: &Path) -> bool {
+    let (site_packages, record) =
+        match (dist_info.parent(), read_to_string(dist_info.join("RECORD"))) {
+            (Some(site_packages), Ok(record)) => (site_packages, record),
+            _ => return false,
+        };
+    let dist_info_name = dist_info.file_name().unwrap_or_default();
+    let files: Vec<PathBuf> = record
+        .lines()
+        .filter_map(|line| line.split(',').next())
+        .filter(|file| !file.is_empty())
+        .map(PathBuf::from)
+        // Ignore the metadata itself and scripts installed outside site-packages
+        .filter(|file| {
+            !file.starts_with(dist_info_name) && !file.starts_with("..") && file.is_relative()
+        })
+        .collect();
+    !files.is_empty() && files.iter().all(|file| !site_packages.join(file).exists())
+}
+
+/// Removes everything in `plan`, returning the number of bytes freed
+pub fn apply_slim(plan: &SlimPlan) -> Result<u64> {
+    let mut freed = 0;
+    for path in &plan.paths {
+        let size = get_dir_size(path);
+        let result = match path.is_dir() {
+            true => remove_dir_all(path),
+            false => remove_file(path),
+        };
+        result.with_context(|| format!("Failed to remove {}", path.display()))?;
+        freed += size;
+    }
+    Ok(freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+
+    #[test]
+    pub fn test_plan_slim() {
+        let root = tempfile::tempdir().expect("Failed to create tempdir");
+        let site_packages = root.path().join("lib/python3.11/site-packages");
+        create_dir_all(site_packages.join("requests/__pycache__")).unwrap();
+        write(site_packages.join("requests/__init__.py"), "").unwrap();
+        write(
+            site_packages.join("requests/__pycache__/__init__.cpython-311.pyc"),
+            "x",
+        )
+        .unwrap();
+        create_dir_all(site_packages.join("~umpy")).unwrap();
+        write(site_packages.join("sourceless.pyc"), "x").unwrap();
+
+        // One live and one orphaned distribution
+        create_dir_all(site_packages.join("requests-2.31.0.dist-info")).unwrap();
+        write(
+            site_packages.join("requests-2.31.0.dist-info/RECORD"),
+            "requests/__init__.py,,\nrequests-2.31.0.dist-info/RECORD,,\n",
+        )
+        .unwrap();
+        create_dir_all(site_packages.join("six-1.16.0.dist-info")).unwrap();
+        write(
+            site_packages.join("six-1.16.0.dist-info/RECORD"),
+            "six.py,,\n../../../bin/six,,\n",
+        )
+        .unwrap();
+
+        create_dir_all(site_packages.join("requests/tests")).unwrap();
+        create_dir_all(site_packages.join("numpy/testing")).unwrap();
+
+        let mut paths = plan_slim(root.path(), &SlimOptions::default()).paths;
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                site_packages.join("requests/__pycache__"),
+                site_packages.join("six-1.16.0.dist-info"),
+                site_packages.join("~umpy"),
+            ]
+        );
+
+        let options = SlimOptions::new(true, &SlimConfig::default());
+        let paths = plan_slim(root.path(), &options).paths;
+        assert!(paths.contains(&site_packages.join("requests/tests")));
+        assert!(!paths.contains(&site_packages.join("numpy/testing")));
+    }
+}
@@ -0,0 +1,204 @@
+use crate::cow::CowUsage;
+use crate::policy::BudgetUsage;
+use crate::size::format_size;
+use crate::venvs::{get_dir_size, VirtualEnv};
+use console::style;
+use dirs::home_dir;
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Site-packages directories of system, Homebrew and user-level Python installs
+const SITE_PACKAGES_PATTERNS: &[&str] = &[
+    // Homebrew (Apple Silicon and Intel)
+    "/opt/homebrew/lib/python3*/site-packages",
+    "/usr/local/lib/python3*/site-packages",
+    // python.org framework builds
+    "/Library/Frameworks/Python.framework/Versions/*/lib/python3*/site-packages",
+    // Linux distributions
+    "/usr/lib/python3/dist-packages",
+    "/usr/lib/python3*/site-packages",
+    "/usr/lib64/python3*/site-packages",
+    "/usr/local/lib/python3*/dist-packages",
+];
+
+/// User site directories, relative to the home directory
+const USER_SITE_PACKAGES_PATTERNS: &[&str] = &[
+    ".local/lib/python3*/site-packages",
+    "Library/Python/3*/lib/python/site-packages",
+];
+
+/// A site-packages directory outside of any virtual environment
+pub struct SitePackages {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Finds and sizes the site-packages directories of non-virtualenv Python installs
+pub fn get_system_site_packages() -> Vec<SitePackages> {
+    let mut patterns: Vec<String> = SITE_PACKAGES_PATTERNS
+        .iter()
+        .map(|pattern| pattern.to_string())
+        .collect();
+    if let Some(home_dir) = home_dir() {
+        patterns.extend(
+            USER_SITE_PACKAGES_PATTERNS
+                .iter()
+                .map(|pattern| home_dir.join(pattern).to_string_lossy().into_owned()),
+        );
+    }
+
+    let mut paths: Vec<PathBuf> = patterns
+        .iter()
+        .filter_map(|pattern| glob::glob(pattern).ok())
+        .flat_map(|paths| paths.filter_map(Result::ok))
+        .filter(|path| path.is_dir())
+        .filter_map(|path| path.canonicalize().ok())
+        .collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut site_packages: Vec<SitePackages> = paths
+        .into_par_iter()
+        .map(|path| {
+            let size = get_dir_size(&path);
+            SitePackages { path, size }
+        })
+        .filter(|site_packages| site_packages.size > 0)
+        .collect();
+    site_packages.sort_by_key(|site_packages| std::cmp::Reverse(site_packages.size));
+    site_packages
+}
+
+/// Reduces "3.11.7" to "3.11"
+fn minor_version(version: &str) -> String {
+    version.split('.').take(2).collect::<Vec<_>>().join(".")
+}
+
+pub fn print_stats(
+    venvs: &[VirtualEnv],
+    site_packages: &[SitePackages],
+    budgets: &[BudgetUsage],
+    cow_usage: &[CowUsage],
+) {
+    let total_size: u64 = venvs.iter().map(|venv| venv.venv_size).sum();
+    let reclaimable: u64 = venvs.iter().map(|venv| venv.reclaimable_size()).sum();
+
+    println!("{}", style("Virtual environments").bold());
+    println!("  Count:       {}", venvs.len());
+    println!("  Total size:  {}", format_size(total_size));
+    println!("  Reclaimable: {}", format_size(reclaimable));
+    let sparse: u64 = venvs.iter().map(|venv| venv.sparse_size).sum();
+    if sparse > 0 {
+        println!(
+            "  Sparse:      {} {}",
+            format_size(sparse),
+            style("(apparent size not on disk, excluded above)").dim()
+        );
+    }
+    if let Some(largest) = venvs.iter().max_by_key(|venv| venv.venv_size) {
+        println!(
+            "  Largest:     {} ({})",
+            largest.path.display(),
+            format_size(largest.venv_size)
+        );
+    }
+
+    let mut by_version: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+    for venv in venvs.iter().filter(|venv| venv.manager.is_environment()) {
+        let entry = by_version
+            .entry(minor_version(&venv.python_version))
+            .or_default();
+        entry.0 += 1;
+        entry.1 += venv.venv_size;
+    }
+    if !by_version.is_empty() {
+        println!();
+        println!("{}", style("By Python version").bold());
+        for (version, (count, size)) in &by_version {
+            println!(
+                "  {:<10} {:>4} envs  {}",
+                version,
+                count,
+                format_size(*size)
+            );
+        }
+    }
+
+    if !budgets.is_empty() {
+        println!();
+        println!("{}", style("Project budgets").bold());
+        for usage in budgets {
+            let line = format!(
+                "  {:>10} of {:<10} {:>4.0}%  {}",
+                format_size(usage.used),
+                usage.budget.max.to_string(),
+                usage.used as f64 * 100.0 / usage.budget.max.0.max(1) as f64,
+                usage.budget.path.display()
+            );
+            match usage.is_over() {
+                true => println!("{}", style(line).red()),
+                false => println!("{}", line),
+            }
+        }
+    }
+
+    if !cow_usage.is_empty() {
+        println!();
+        println!(
+            "{} {}",
+            style("Copy-on-write filesystems").bold(),
+            style("(snapshots and reflinks may keep deleted data)").dim()
+        );
+        for usage in cow_usage {
+            let location = match &usage.subvolume {
+                Some(subvolume) => format!("subvolume {}", subvolume.display()),
+                None => "".to_string(),
+            };
+            let exclusive = match usage.exclusive {
+                Some(exclusive) => {
+                    format!(", {} exclusive to the subvolume", format_size(exclusive))
+                }
+                None => "".to_string(),
+            };
+            println!(
+                "  {:<9} {:>4} envs  {:>10}{}  {}",
+                usage.filesystem.to_string(),
+                usage.venvs,
+                format_size(usage.size),
+                exclusive,
+                location
+            );
+        }
+    }
+
+    if !site_packages.is_empty() {
+        let total: u64 = site_packages.iter().map(|site| site.size).sum();
+        println!();
+        println!(
+            "{} {}",
+            style("System and Homebrew site-packages").bold(),
+            style("(read-only, not deletable by venvpruner)").dim()
+        );
+        for site in site_packages {
+            println!("  {:>10}  {}", format_size(site.size), site.path.display());
+        }
+        println!("  Total:      {}", format_size(total));
+        println!(
+            "  {}",
+            style("Manage these with your system package manager, brew or pip --user.").dim()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_minor_version() {
+        assert_eq!(minor_version("3.11.7"), "3.11");
+        assert_eq!(minor_version("3.9"), "3.9");
+        assert_eq!(minor_version("Unknown"), "Unknown");
+    }
+}
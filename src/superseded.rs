@@ -0,0 +1,109 @@
+use crate::manager::Manager;
+use crate::venvs::VirtualEnv;
+use std::collections::HashMap;
+
+/// What identifies the project an environment belongs to: its directory,
+/// or the hashed name prefix of a Poetry or PDM environment, which names
+/// one environment per interpreter like `proj-AbC123xY-py3.11`
+fn get_project_key(venv: &VirtualEnv) -> Option<String> {
+    match (&venv.project, venv.manager) {
+        (Some(project), _) => Some(project.display().to_string()),
+        (None, Manager::Poetry | Manager::Pdm) => venv
+            .name
+            .rsplit_once('-')
+            .map(|(prefix, _)| format!("{}:{}", venv.manager, prefix)),
+        _ => None,
+    }
+}
+
+/// The numeric components of a version like `3.11.9`, for ordering
+fn parse_version(version: &str) -> Vec<u32> {
+    version
+        .split('.')
+        .map_while(|part| {
+            let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse().ok()
+        })
+        .collect()
+}
+
+/// Marks environments of a project that has another one with a newer
+/// interpreter as superseded by it. tox, nox and hatch keep one environment
+/// per interpreter on purpose, so they are left alone.
+pub fn mark_superseded(venvs: &mut [VirtualEnv]) {
+    let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, venv) in venvs.iter().enumerate() {
+        let intended = matches!(venv.manager, Manager::Tox | Manager::Nox | Manager::Hatch);
+        if intended || !venv.manager.is_environment() {
+            continue;
+        }
+        if let Some(key) = get_project_key(venv) {
+            groups.entry(key).or_default().push(i);
+        }
+    }
+
+    for mut members in groups.into_values().filter(|members| members.len() > 1) {
+        members.sort_by_key(|&i| {
+            std::cmp::Reverse((parse_version(&venvs[i].python_version), venvs[i].modified))
+        });
+        let newest = venvs[members[0]].path.clone();
+        for &i in &members[1..] {
+            venvs[i].superseded_by = Some(newest.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    pub fn test_mark_superseded() {
+        let venv = |path: &str, python_version: &str, manager: Manager| VirtualEnv {
+            name: path.rsplit('/').next().unwrap().to_string(),
+            path: PathBuf::from(path),
+            python_version: python_version.to_string(),
+            manager,
+            ..Default::default()
+        };
+        let poetry = "/home/me/.cache/pypoetry/virtualenvs";
+        let mut venvs = vec![
+            venv(
+                &format!("{}/proj-AbC123xY-py3.9", poetry),
+                "3.9.18",
+                Manager::Poetry,
+            ),
+            venv(
+                &format!("{}/proj-AbC123xY-py3.11", poetry),
+                "3.11.9",
+                Manager::Poetry,
+            ),
+            venv(
+                &format!("{}/proj-AbC123xY-py3.10", poetry),
+                "3.10.14",
+                Manager::Poetry,
+            ),
+            // Same name, different project
+            venv(
+                &format!("{}/proj-Zz9_8Yx7-py3.8", poetry),
+                "3.8.19",
+                Manager::Poetry,
+            ),
+            venv("/home/me/code/app/.tox/py38", "3.8.19", Manager::Tox),
+            venv("/home/me/code/app/.tox/py312", "3.12.4", Manager::Tox),
+        ];
+        venvs[4].project = Some(PathBuf::from("/home/me/code/app"));
+        venvs[5].project = Some(PathBuf::from("/home/me/code/app"));
+        mark_superseded(&mut venvs);
+
+        let newest = PathBuf::from(format!("{}/proj-AbC123xY-py3.11", poetry));
+        assert_eq!(venvs[0].superseded_by, Some(newest.clone()));
+        assert_eq!(venvs[1].superseded_by, None);
+        assert_eq!(venvs[2].superseded_by, Some(newest));
+        assert!(venvs[3..].iter().all(|venv| venv.superseded_by.is_none()));
+
+        assert!(parse_version("3.10.14") > parse_version("3.9.18"));
+        assert_eq!(parse_version("Unknown"), Vec::<u32>::new());
+    }
+}
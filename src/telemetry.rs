@@ -0,0 +1,161 @@
+use crate::history::{get_data_dir, HistoryEntry};
+use crate::manager::Manager;
+use crate::venvs::VirtualEnv;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{read_to_string, write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Reports are sent at most this often
+const REPORT_INTERVAL_DAYS: i64 = 7;
+
+/// The `[telemetry]` section of the config file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// Where reports are POSTed; telemetry cannot be enabled without one
+    pub url: Option<String>,
+}
+
+/// Whether the user opted in, and when a report was last sent
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TelemetryState {
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_sent: Option<DateTime<Local>>,
+}
+
+/// Everything a report contains: no paths, names, hosts or identifiers
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Report {
+    pub version: &'static str,
+    pub os: &'static str,
+    /// Environments found by the last scan, per manager
+    pub venvs_by_manager: BTreeMap<Manager, usize>,
+    /// Bytes reclaimed by all recorded prunes
+    pub total_reclaimed: u64,
+}
+
+/// `~/.local/share/venvpruner/telemetry.json`
+fn get_state_path() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("telemetry.json"))
+}
+
+impl TelemetryState {
+    /// Missing or unreadable state means telemetry stays off
+    pub fn load() -> Result<TelemetryState> {
+        let path = get_state_path()?;
+        if !path.exists() {
+            return Ok(TelemetryState::default());
+        }
+        let contents =
+            read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(serde_json::from_str(&contents).unwrap_or_default())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = get_state_path()?;
+        write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    fn is_due(&self, now: DateTime<Local>) -> bool {
+        self.last_sent
+            .is_none_or(|last_sent| now - last_sent >= Duration::days(REPORT_INTERVAL_DAYS))
+    }
+}
+
+/// The conventional opt-out shared by many CLIs, honoured even after `enable`
+pub fn is_do_not_track() -> bool {
+    std::env::var_os("DO_NOT_TRACK").is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+pub fn build_report(venvs: &[VirtualEnv], history: &[HistoryEntry]) -> Report {
+    let mut venvs_by_manager = BTreeMap::new();
+    for venv in venvs {
+        *venvs_by_manager.entry(venv.manager).or_default() += 1;
+    }
+    let total_reclaimed = history
+        .iter()
+        .map(|entry| match entry {
+            HistoryEntry::Prune { reclaimed, .. } => *reclaimed,
+            HistoryEntry::Scan { .. } => 0,
+        })
+        .sum();
+    Report {
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        venvs_by_manager,
+        total_reclaimed,
+    }
+}
+
+/// Sends a report built from a completed scan when the user opted in, a URL
+/// is configured and the last one is old enough. curl runs detached, so a
+/// slow or unreachable endpoint never holds up the command.
+pub fn maybe_send(config: &TelemetryConfig, venvs: &[VirtualEnv]) -> Result<()> {
+    let url = match &config.url {
+        Some(url) => url,
+        None => return Ok(()),
+    };
+    let mut state = TelemetryState::load()?;
+    let now = Local::now();
+    if !state.enabled || is_do_not_track() || !state.is_due(now) {
+        return Ok(());
+    }
+    let history = crate::history::load_history()?;
+    let payload = serde_json::to_string(&build_report(venvs, &history))?;
+    Command::new("curl")
+        .args(["--silent", "--fail", "--max-time", "10"])
+        .args(["--header", "Content-Type: application/json"])
+        .args(["--data-binary", &payload, url])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to run curl")?;
+    state.last_sent = Some(now);
+    state.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_build_report() {
+        let venvs = vec![
+            VirtualEnv {
+                manager: Manager::Poetry,
+                ..Default::default()
+            },
+            VirtualEnv {
+                manager: Manager::Poetry,
+                ..Default::default()
+            },
+        ];
+        let history = vec![
+            HistoryEntry::Prune {
+                timestamp: Local::now(),
+                count: 1,
+                reclaimed: 100,
+            },
+            HistoryEntry::Prune {
+                timestamp: Local::now(),
+                count: 2,
+                reclaimed: 50,
+            },
+        ];
+        let report = build_report(&venvs, &history);
+        assert_eq!(report.venvs_by_manager.get(&Manager::Poetry), Some(&2));
+        assert_eq!(report.total_reclaimed, 150);
+
+        let mut state = TelemetryState::default();
+        assert!(state.is_due(Local::now()));
+        state.last_sent = Some(Local::now() - Duration::days(1));
+        assert!(!state.is_due(Local::now()));
+    }
+}
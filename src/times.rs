@@ -0,0 +1,95 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+
+/// Renders `time` like "3 months ago", relative to `now`
+pub fn format_relative(time: DateTime<Local>, now: DateTime<Local>) -> String {
+    let seconds = (now - time).num_seconds();
+    if seconds < 60 {
+        return "just now".to_string();
+    }
+    let (count, unit) = match seconds {
+        s if s < 3600 => (s / 60, "minute"),
+        s if s < 86_400 => (s / 3600, "hour"),
+        s if s < 14 * 86_400 => (s / 86_400, "day"),
+        s if s < 60 * 86_400 => (s / (7 * 86_400), "week"),
+        s if s < 365 * 86_400 => (s / (30 * 86_400), "month"),
+        s => (s / (365 * 86_400), "year"),
+    };
+    match count {
+        1 => format!("1 {} ago", unit),
+        _ => format!("{} {}s ago", count, unit),
+    }
+}
+
+/// Renders `time` as relative or as an ISO 8601 date
+pub fn format_time(time: DateTime<Local>, absolute: bool) -> String {
+    match absolute {
+        true => time.format("%Y-%m-%d").to_string(),
+        false => format_relative(time, Local::now()),
+    }
+}
+
+/// Parses short durations like "30s", "5m" or "1h"; a bare number is seconds
+pub fn parse_duration(input: &str) -> Result<std::time::Duration> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid duration: {:?}", input))?;
+    let seconds = match unit.trim() {
+        "" | "s" | "sec" | "secs" => 1,
+        "m" | "min" | "mins" => 60,
+        "h" | "hour" | "hours" => 3600,
+        _ => return Err(anyhow!("Unknown duration unit in {:?}", input)),
+    };
+    let total = number
+        .checked_mul(seconds)
+        .ok_or_else(|| anyhow!("Duration too long: {:?}", input))?;
+    Ok(std::time::Duration::from_secs(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    pub fn test_format_relative() {
+        let now = Local::now();
+        assert_eq!(format_relative(now - Duration::seconds(5), now), "just now");
+        assert_eq!(
+            format_relative(now - Duration::minutes(1), now),
+            "1 minute ago"
+        );
+        assert_eq!(
+            format_relative(now - Duration::hours(5), now),
+            "5 hours ago"
+        );
+        assert_eq!(
+            format_relative(now - Duration::days(20), now),
+            "2 weeks ago"
+        );
+        assert_eq!(
+            format_relative(now - Duration::days(95), now),
+            "3 months ago"
+        );
+        assert_eq!(
+            format_relative(now - Duration::days(800), now),
+            "2 years ago"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_duration() {
+        use std::time::Duration;
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("45").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+        assert!(parse_duration("2d").is_err());
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("9999999999999999h").is_err());
+    }
+}
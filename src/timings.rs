@@ -0,0 +1,215 @@
+use console::style;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Whether scan phases are timed (`--timings`)
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static RECORDS: Mutex<Vec<Record>> = Mutex::new(Vec::new());
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// A stage of a scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// Walking a search path or project root for environments
+    Walk,
+    /// Adding up the size of an environment
+    Sizing,
+    /// Finding an environment's Python version
+    Version,
+    /// Scheduled jobs, IDE projects, clones, notes and tags
+    Annotate,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Walk => write!(f, "walk"),
+            Phase::Sizing => write!(f, "sizing"),
+            Phase::Version => write!(f, "version detection"),
+            Phase::Annotate => write!(f, "annotation"),
+        }
+    }
+}
+
+/// How long one phase took for one search root or environment
+#[derive(Debug, Clone)]
+struct Record {
+    phase: Phase,
+    path: Option<PathBuf>,
+    elapsed: Duration,
+}
+
+/// Runs `f`, recording how long it took under `phase` for `path` when
+/// timings are enabled
+pub fn time<T>(phase: Phase, path: Option<&Path>, f: impl FnOnce() -> T) -> T {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return f();
+    }
+    let start = Instant::now();
+    let result = f();
+    record(phase, path, start.elapsed());
+    result
+}
+
+/// Records that `phase` took `elapsed` for `path`, when timings are enabled
+pub fn record(phase: Phase, path: Option<&Path>, elapsed: Duration) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let record = Record {
+        phase,
+        path: path.map(Path::to_path_buf),
+        elapsed,
+    };
+    RECORDS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .push(record);
+}
+
+/// Durations of one search root, including the environments found below it
+#[derive(Debug, Default, PartialEq)]
+struct RootTimings {
+    walk: Duration,
+    sizing: Duration,
+    version: Duration,
+    venvs: usize,
+}
+
+impl RootTimings {
+    fn total(&self) -> Duration {
+        self.walk + self.sizing + self.version
+    }
+}
+
+/// Totals per phase, and per root with each environment's sizing and version
+/// detection charged to the closest walked root above it. Environments
+/// outside every root (e.g. registered by an IDE) are grouped under None.
+fn summarize(
+    records: &[Record],
+) -> (
+    BTreeMap<Phase, Duration>,
+    BTreeMap<Option<PathBuf>, RootTimings>,
+) {
+    let mut phases: BTreeMap<Phase, Duration> = BTreeMap::new();
+    let mut roots: BTreeMap<Option<PathBuf>, RootTimings> = BTreeMap::new();
+    for record in records {
+        *phases.entry(record.phase).or_default() += record.elapsed;
+        if record.phase == Phase::Walk {
+            roots.entry(record.path.clone()).or_default().walk += record.elapsed;
+        }
+    }
+    let walked: Vec<PathBuf> = roots.keys().flatten().cloned().collect();
+    for record in records {
+        let path = match (record.phase, &record.path) {
+            (Phase::Sizing | Phase::Version, Some(path)) => path,
+            _ => continue,
+        };
+        let root = walked
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+            .cloned();
+        let timings = roots.entry(root).or_default();
+        match record.phase {
+            Phase::Sizing => {
+                timings.sizing += record.elapsed;
+                timings.venvs += 1;
+            }
+            _ => timings.version += record.elapsed,
+        }
+    }
+    (phases, roots)
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2}s", duration.as_secs_f64())
+}
+
+/// Prints what was recorded, slowest roots first, to stderr
+pub fn report() {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let records = RECORDS.lock().unwrap_or_else(|err| err.into_inner());
+    if records.is_empty() {
+        eprintln!("No scan ran, so there are no timings to report.");
+        return;
+    }
+    let (phases, roots) = summarize(&records);
+
+    eprintln!(
+        "{}",
+        style("Scan timings (summed across threads, so they can exceed the wall time):").bold()
+    );
+    for (phase, elapsed) in &phases {
+        eprintln!(
+            "  {:<18} {:>9}",
+            phase.to_string(),
+            format_duration(*elapsed)
+        );
+    }
+
+    let mut roots: Vec<_> = roots.into_iter().collect();
+    roots.sort_by_key(|(_, timings)| std::cmp::Reverse(timings.total()));
+    eprintln!(
+        "\n  {:>9} {:>9} {:>9} {:>5}  ROOT",
+        "WALK", "SIZING", "VERSION", "ENVS"
+    );
+    for (root, timings) in roots {
+        let root = match root {
+            Some(root) => root.display().to_string(),
+            None => "(other locations)".to_string(),
+        };
+        eprintln!(
+            "  {:>9} {:>9} {:>9} {:>5}  {}",
+            format_duration(timings.walk),
+            format_duration(timings.sizing),
+            format_duration(timings.version),
+            timings.venvs,
+            root
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_summarize() {
+        let record = |phase, path: Option<&str>, millis| Record {
+            phase,
+            path: path.map(PathBuf::from),
+            elapsed: Duration::from_millis(millis),
+        };
+        let records = vec![
+            record(Phase::Walk, Some("/home/me/.virtualenvs"), 10),
+            record(Phase::Walk, Some("/home/me/code"), 500),
+            record(Phase::Sizing, Some("/home/me/code/api/.venv"), 200),
+            record(Phase::Version, Some("/home/me/code/api/.venv"), 5),
+            record(Phase::Sizing, Some("/opt/pycharm-env"), 30),
+            record(Phase::Annotate, None, 7),
+        ];
+        let (phases, roots) = summarize(&records);
+        assert_eq!(phases[&Phase::Walk], Duration::from_millis(510));
+        assert_eq!(phases[&Phase::Annotate], Duration::from_millis(7));
+
+        let code = &roots[&Some(PathBuf::from("/home/me/code"))];
+        assert_eq!(code.total(), Duration::from_millis(705));
+        assert_eq!(code.venvs, 1);
+        assert_eq!(
+            roots[&Some(PathBuf::from("/home/me/.virtualenvs"))].venvs,
+            0
+        );
+        assert_eq!(roots[&None].sizing, Duration::from_millis(30));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::caches::get_cache_dirs;
+use crate::size::format_size;
+use crate::venvs::{build_virtualenv, discover_venv_paths, get_dir_size, ScanOptions};
+use anyhow::Result;
+use console::{measure_text_width, pad_str, style, Alignment, Key, Term};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the screen is redrawn
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A sized environment or cache directory
+struct Row {
+    path: PathBuf,
+    size: u64,
+    kind: String,
+}
+
+/// Shows a continuously refreshing view of the largest environments and
+/// caches, filled in as background sizing completes. Every `interval` after
+/// a full pass, everything is rescanned.
+pub fn run_top(options: &ScanOptions, interval: Duration) -> Result<()> {
+    let term = Term::stdout();
+    let quit = Arc::new(AtomicBool::new(false));
+    {
+        let quit = Arc::clone(&quit);
+        thread::spawn(move || loop {
+            match Term::stdout().read_key() {
+                Ok(Key::Char('q')) | Ok(Key::Escape) | Ok(Key::CtrlC) | Err(_) => {
+                    quit.store(true, Ordering::SeqCst);
+                    break;
+                }
+                _ => {}
+            }
+        });
+    }
+
+    term.hide_cursor()?;
+    term.clear_screen()?;
+    let result = refresh_loop(&term, options, interval, &quit);
+    term.show_cursor()?;
+    result
+}
+
+fn refresh_loop(
+    term: &Term,
+    options: &ScanOptions,
+    interval: Duration,
+    quit: &AtomicBool,
+) -> Result<()> {
+    let mut rows: HashMap<PathBuf, Row> = HashMap::new();
+
+    loop {
+        let cycle_started = Instant::now();
+        let venv_paths = discover_venv_paths(options)?;
+        let cache_dirs = get_cache_dirs();
+        let total = venv_paths.len() + cache_dirs.len();
+
+        let (sender, receiver) = mpsc::channel::<Option<Row>>();
+        thread::spawn(move || {
+            venv_paths
+                .into_par_iter()
+                .for_each_with(sender.clone(), |sender, path| {
+                    let row = build_virtualenv(path).ok().map(|venv| Row {
+                        path: venv.path,
+                        size: venv.venv_size,
+                        kind: venv.manager.to_string(),
+                    });
+                    let _ = sender.send(row);
+                });
+            cache_dirs
+                .into_par_iter()
+                .for_each_with(sender, |sender, cache| {
+                    let _ = sender.send(Some(Row {
+                        size: get_dir_size(&cache.path),
+                        path: cache.path,
+                        kind: format!("{} cache", cache.tool),
+                    }));
+                });
+        });
+
+        let mut done = 0;
+        let mut seen = HashSet::new();
+        loop {
+            while let Ok(row) = receiver.try_recv() {
+                done += 1;
+                if let Some(row) = row {
+                    seen.insert(row.path.clone());
+                    rows.insert(row.path.clone(), row);
+                }
+            }
+            if done == total {
+                // Forget whatever disappeared since the previous pass
+                rows.retain(|path, _| seen.contains(path));
+            }
+
+            render(term, &rows, done, total)?;
+
+            if quit.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            if done == total && cycle_started.elapsed() >= interval {
+                break;
+            }
+            thread::sleep(REDRAW_INTERVAL);
+        }
+    }
+}
+
+/// Shortens `text` to at most `width` terminal columns by replacing its
+/// start with "…", counting wide characters such as CJK and emoji as two
+fn truncate_start(text: &str, width: usize) -> String {
+    if measure_text_width(text) <= width {
+        return text.to_string();
+    }
+    let mut kept = String::new();
+    let mut kept_width = 1;
+    for c in text.chars().rev() {
+        let c_width = measure_text_width(c.encode_utf8(&mut [0; 4]));
+        if kept_width + c_width > width {
+            break;
+        }
+        kept.insert(0, c);
+        kept_width += c_width;
+    }
+    format!("…{}", kept)
+}
+
+fn render(term: &Term, rows: &HashMap<PathBuf, Row>, done: usize, total: usize) -> Result<()> {
+    let (height, width) = term.size();
+    let mut sorted: Vec<&Row> = rows.values().collect();
+    sorted.sort_by_key(|row| std::cmp::Reverse(row.size));
+    let total_size: u64 = sorted.iter().map(|row| row.size).sum();
+
+    let status = match done < total {
+        true => format!("sizing {}/{}", done, total),
+        false => "up to date".to_string(),
+    };
+    let mut lines = vec![
+        format!(
+            "{} {} items, {} total ({})  {}",
+            style("venvpruner top").bold(),
+            sorted.len(),
+            format_size(total_size),
+            status,
+            style("q to quit").dim()
+        ),
+        String::new(),
+        style(format!("{:>10}  {:<18} {}", "SIZE", "KIND", "PATH"))
+            .bold()
+            .to_string(),
+    ];
+
+    let path_width = (width as usize).saturating_sub(31);
+    for row in sorted
+        .iter()
+        .take((height as usize).saturating_sub(lines.len() + 1))
+    {
+        lines.push(format!(
+            "{:>10}  {} {}",
+            format_size(row.size),
+            pad_str(&row.kind, 18, Alignment::Left, None),
+            truncate_start(&row.path.display().to_string(), path_width)
+        ));
+    }
+
+    term.move_cursor_to(0, 0)?;
+    for line in lines {
+        term.clear_line()?;
+        term.write_line(&line)?;
+    }
+    term.clear_to_end_of_screen()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_truncate_start() {
+        assert_eq!(truncate_start("/envs/short", 20), "/envs/short");
+        assert_eq!(truncate_start("/envs/a-long-name", 8), "…ng-name");
+        // Each of these characters takes two columns
+        let truncated = truncate_start("/envs/数据分析环境", 9);
+        assert_eq!(truncated, "…分析环境");
+        assert_eq!(measure_text_width(&truncated), 9);
+        assert!(measure_text_width(&truncate_start("/envs/🐍🐍🐍🐍", 6)) <= 6);
+    }
+}
@@ -0,0 +1,203 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::env::{consts, current_exe};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// GitHub repository publishing release binaries
+const RELEASES_REPO: &str = "burstMembrane/virtualenvpruner";
+
+/// Release asset listing the SHA-256 of every binary, in `sha256sum` format
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Result<&Asset> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| anyhow!("Release {} has no {} asset", self.tag_name, name))
+    }
+}
+
+/// Name of the release binary for this platform, e.g. `venvpruner-x86_64-linux`
+pub fn get_asset_name() -> String {
+    format!("venvpruner-{}-{}", consts::ARCH, consts::OS)
+}
+
+/// Parses versions like "v1.2.3" or "0.4" into comparable numbers
+pub fn parse_version(version: &str) -> Option<Vec<u64>> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect()
+}
+
+/// Runs curl, failing on HTTP errors
+fn curl(url: &str) -> Command {
+    let mut command = Command::new("curl");
+    command
+        .args(["--silent", "--show-error", "--fail", "--location"])
+        .args(["--max-time", "300"])
+        .arg(url);
+    command
+}
+
+pub fn get_latest_release() -> Result<Release> {
+    let url = format!(
+        "https://api.github.com/repos/{}/releases/latest",
+        RELEASES_REPO
+    );
+    let output = curl(&url).output().context("Failed to run curl")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to fetch the latest release: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    serde_json::from_slice(&output.stdout).context("Unexpected response from GitHub")
+}
+
+/// Whether `release` is newer than this binary
+pub fn is_newer(release: &Release) -> bool {
+    match (
+        parse_version(&release.tag_name),
+        parse_version(env!("CARGO_PKG_VERSION")),
+    ) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// Finds the checksum of `name` in a `sha256sum` listing
+pub fn find_checksum<'a>(checksums: &'a str, name: &str) -> Option<&'a str> {
+    checksums.lines().find_map(|line| {
+        let (checksum, file) = line.split_once(char::is_whitespace)?;
+        // `sha256sum -b` marks binary files with a leading '*'
+        match file.trim_start().trim_start_matches('*') == name {
+            true => Some(checksum),
+            false => None,
+        }
+    })
+}
+
+/// SHA-256 of `path` in hex, from `sha256sum` or macOS' `shasum`
+fn get_sha256(path: &Path) -> Result<String> {
+    let output = Command::new("sha256sum")
+        .arg(path)
+        .output()
+        .or_else(|_| {
+            Command::new("shasum")
+                .args(["-a", "256"])
+                .arg(path)
+                .output()
+        })
+        .context("Failed to run sha256sum or shasum")?;
+    if !output.status.success() {
+        return Err(anyhow!("Failed to hash {}", path.display()));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()
+        .map(str::to_lowercase)
+        .ok_or_else(|| anyhow!("Failed to hash {}", path.display()))
+}
+
+fn download(url: &str, destination: &Path) -> Result<()> {
+    let status = curl(url)
+        .arg("--output")
+        .arg(destination)
+        .status()
+        .context("Failed to run curl")?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(anyhow!("Failed to download {}", url)),
+    }
+}
+
+/// Downloads this platform's binary from `release`, checks it against the
+/// published checksum, and replaces the running executable with it
+pub fn install(release: &Release) -> Result<PathBuf> {
+    let name = get_asset_name();
+    let binary = release.asset(&name)?;
+    let checksums = release.asset(CHECKSUMS_ASSET)?;
+
+    let output = curl(&checksums.browser_download_url)
+        .output()
+        .context("Failed to run curl")?;
+    if !output.status.success() {
+        return Err(anyhow!("Failed to download {}", CHECKSUMS_ASSET));
+    }
+    let checksums = String::from_utf8_lossy(&output.stdout);
+    let expected = find_checksum(&checksums, &name)
+        .ok_or_else(|| anyhow!("{} has no checksum for {}", CHECKSUMS_ASSET, name))?
+        .to_lowercase();
+
+    // Next to the executable, so that the final rename stays on one filesystem
+    let exe = current_exe()
+        .and_then(fs::canonicalize)
+        .context("Failed to locate the venvpruner binary")?;
+    let download_path = exe.with_file_name(".venvpruner-update");
+    let result = download(&binary.browser_download_url, &download_path).and_then(|_| {
+        let actual = get_sha256(&download_path)?;
+        if actual != expected {
+            return Err(anyhow!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                name,
+                expected,
+                actual
+            ));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&download_path, fs::Permissions::from_mode(0o755))?;
+        }
+        fs::rename(&download_path, &exe)
+            .with_context(|| format!("Failed to replace {}", exe.display()))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&download_path);
+    }
+    result.map(|_| exe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_update_helpers() {
+        assert_eq!(parse_version("v1.10.2"), Some(vec![1, 10, 2]));
+        assert!(parse_version("v1.10.2") > parse_version("1.9.9"));
+        assert_eq!(parse_version("nightly"), None);
+
+        let checksums = "\
+0a1b2c  venvpruner-x86_64-linux
+3d4e5f *venvpruner-aarch64-macos
+";
+        assert_eq!(
+            find_checksum(checksums, "venvpruner-x86_64-linux"),
+            Some("0a1b2c")
+        );
+        assert_eq!(
+            find_checksum(checksums, "venvpruner-aarch64-macos"),
+            Some("3d4e5f")
+        );
+        assert_eq!(find_checksum(checksums, "venvpruner-x86_64-windows"), None);
+    }
+}
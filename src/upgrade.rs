@@ -0,0 +1,157 @@
+use crate::packages::{get_installed_packages, Package};
+use anyhow::{anyhow, Context, Result};
+use std::fs::{remove_dir_all, rename};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Packages `venv` installs by itself; pinning their old versions would
+/// downgrade them or fail on a newer Python
+const SEED_PACKAGES: &[&str] = &["pip", "setuptools", "wheel", "distribute"];
+
+/// Parses "3.11.7" into (3, 11)
+pub fn parse_minor_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// Runs `interpreter --version` and returns e.g. "3.12.1"
+pub fn get_interpreter_version(interpreter: &Path) -> Result<String> {
+    let output = Command::new(interpreter)
+        .arg("--version")
+        .output()
+        .with_context(|| format!("Failed to run {}", interpreter.display()))?;
+    // Python 2 printed its version to stderr
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    text.trim()
+        .strip_prefix("Python ")
+        .map(|version| version.trim().to_string())
+        .ok_or_else(|| anyhow!("{} is not a Python interpreter", interpreter.display()))
+}
+
+/// pip arguments reinstalling `packages`, pinned to their current versions if `pin`
+pub fn get_requirements(packages: &[Package], pin: bool) -> Vec<String> {
+    let mut args = Vec::new();
+    for package in packages {
+        if SEED_PACKAGES.contains(&package.name.to_lowercase().as_str()) {
+            continue;
+        }
+        match (&package.url, package.editable) {
+            (Some(url), true) => {
+                args.push("-e".to_string());
+                args.push(url.strip_prefix("file://").unwrap_or(url).to_string());
+            }
+            (Some(url), false) => args.push(format!("{} @ {}", package.name, url)),
+            (None, _) if pin => args.push(format!("{}=={}", package.name, package.version)),
+            (None, _) => args.push(package.name.clone()),
+        }
+    }
+    args
+}
+
+/// Recreates the environment at `venv_root` with `interpreter` and reinstalls
+/// its packages. The old environment is kept aside until the new one is
+/// complete and put back if anything fails.
+pub fn upgrade_venv(
+    venv_root: &Path,
+    interpreter: &Path,
+    pin: bool,
+    keep_backup: bool,
+) -> Result<()> {
+    let requirements = get_requirements(&get_installed_packages(venv_root), pin);
+    let backup = get_backup_path(venv_root)?;
+    if backup.exists() {
+        return Err(anyhow!("{} already exists", backup.display()));
+    }
+    rename(venv_root, &backup)
+        .with_context(|| format!("Failed to move {} aside", venv_root.display()))?;
+
+    if let Err(err) = recreate(venv_root, interpreter, &requirements) {
+        if venv_root.exists() {
+            remove_dir_all(venv_root)
+                .with_context(|| format!("Failed to clean up {}", venv_root.display()))?;
+        }
+        rename(&backup, venv_root).with_context(|| {
+            format!(
+                "Failed to restore {} from {}",
+                venv_root.display(),
+                backup.display()
+            )
+        })?;
+        return Err(err);
+    }
+
+    if !keep_backup {
+        remove_dir_all(&backup)
+            .with_context(|| format!("Failed to remove {}", backup.display()))?;
+    }
+    Ok(())
+}
+
+/// `<venv>.venvpruner-backup` next to the environment
+pub fn get_backup_path(venv_root: &Path) -> Result<PathBuf> {
+    let name = venv_root
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid environment path {}", venv_root.display()))?;
+    Ok(venv_root.with_file_name(format!("{}.venvpruner-backup", name.to_string_lossy())))
+}
+
+fn recreate(venv_root: &Path, interpreter: &Path, requirements: &[String]) -> Result<()> {
+    run(Command::new(interpreter)
+        .args(["-m", "venv"])
+        .arg(venv_root))?;
+    if !requirements.is_empty() {
+        run(Command::new(venv_root.join("bin/python"))
+            .args(["-m", "pip", "install"])
+            .args(requirements))?;
+    }
+    Ok(())
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to run {:?}", command))?;
+    if !status.success() {
+        return Err(anyhow!("{:?} failed with {}", command, status));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_get_requirements() {
+        let package = |name: &str, version: &str| Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            ..Default::default()
+        };
+        let packages = vec![
+            package("pip", "23.2.1"),
+            package("requests", "2.31.0"),
+            Package {
+                url: Some("file:///src/myapp".to_string()),
+                editable: true,
+                ..package("myapp", "0.1")
+            },
+        ];
+        assert_eq!(
+            get_requirements(&packages, true),
+            vec!["requests==2.31.0", "-e", "/src/myapp"]
+        );
+        assert_eq!(
+            get_requirements(&packages, false),
+            vec!["requests", "-e", "/src/myapp"]
+        );
+        assert_eq!(parse_minor_version("3.11.7"), Some((3, 11)));
+        assert_eq!(parse_minor_version("Unknown"), None);
+    }
+}
@@ -0,0 +1,114 @@
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+
+/// Directories holding one home directory per user
+const HOME_PARENTS: &[&str] = &["/home", "/Users"];
+
+/// Returns true if running with root privileges
+#[cfg(unix)]
+pub fn is_root() -> bool {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+pub fn is_root() -> bool {
+    false
+}
+
+/// Home directories of every user on the machine
+pub fn get_user_homes() -> Vec<PathBuf> {
+    let parents: Vec<&Path> = HOME_PARENTS.iter().map(Path::new).collect();
+    get_homes_in(&parents)
+}
+
+/// Home directories directly below each of `parents`
+fn get_homes_in(parents: &[&Path]) -> Vec<PathBuf> {
+    parents
+        .iter()
+        .filter_map(|parent| read_dir(parent).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .map(|entry| entry.path())
+        // Skip macOS' /Users/Shared and dotfiles such as /home/.snapshots
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| !name.starts_with('.') && name != "Shared")
+        })
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+/// Name of the user owning `path`, falling back to the numeric uid
+#[cfg(unix)]
+pub fn get_owner(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let uid = std::fs::symlink_metadata(path).ok()?.uid();
+    Some(user_name(uid).unwrap_or_else(|| uid.to_string()))
+}
+
+#[cfg(not(unix))]
+pub fn get_owner(_path: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(unix)]
+fn user_name(uid: u32) -> Option<String> {
+    use std::ffi::CStr;
+
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+    // SAFETY: all pointers reference live, correctly sized storage, and the
+    // returned name is copied out before `buf` is dropped.
+    let ret =
+        unsafe { libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if ret != 0 || result.is_null() {
+        return None;
+    }
+
+    let name = unsafe { CStr::from_ptr(passwd.pw_name) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir, write};
+
+    #[test]
+    pub fn test_get_homes_in() {
+        let home = tempfile::tempdir().expect("Failed to create tempdir");
+        let users = tempfile::tempdir().expect("Failed to create tempdir");
+        for dir in ["alice", "bob", ".snapshots"] {
+            create_dir(home.path().join(dir)).unwrap();
+        }
+        create_dir(users.path().join("carol")).unwrap();
+        create_dir(users.path().join("Shared")).unwrap();
+        write(home.path().join("notes.txt"), "").unwrap();
+
+        let missing = home.path().join("missing");
+        let mut homes = get_homes_in(&[home.path(), users.path(), &missing]);
+        homes.sort();
+        let mut expected = vec![
+            home.path().join("alice"),
+            home.path().join("bob"),
+            users.path().join("carol"),
+        ];
+        expected.sort();
+        assert_eq!(homes, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_get_owner() {
+        let home = tempfile::tempdir().expect("Failed to create tempdir");
+        // SAFETY: geteuid has no preconditions and cannot fail
+        let uid = unsafe { libc::geteuid() };
+        let expected = user_name(uid).unwrap_or_else(|| uid.to_string());
+        assert_eq!(get_owner(home.path()), Some(expected));
+        assert_eq!(get_owner(&home.path().join("missing")), None);
+    }
+}
@@ -1,48 +1,387 @@
+use crate::apfs::get_shared_size;
+use crate::conda::{get_conda_envs_dirs, get_listed_envs, get_registered_envs, mark_clones};
+use crate::copies::mark_copies;
+use crate::ecosystems::{get_ecosystem_dirs, Ecosystem};
+use crate::excludes::ExcludeMatcher;
+use crate::hatch::{self, get_hatch_project_dirs};
+use crate::jupyter::{get_kernel_dirs, get_kernels, mark_kernels, JupyterKernel};
+use crate::locate::find_indexed_venv_roots;
+use crate::manager::{detect_manager, get_mamba_root_prefix, get_workon_home, Manager};
+use crate::metadata::annotate_metadata;
+use crate::ml::get_ml_size;
+use crate::pdm::{self, get_pdm_venv_dirs};
+use crate::privacy::BlockedDirs;
+use crate::progress;
+use crate::pycharm::get_pycharm_interpreters;
+use crate::pyenv::{get_pyenv_envs_dirs, get_pyenv_link, get_pyenv_versions};
+use crate::rye::{get_rye_toolchains, get_rye_tools_dir};
+use crate::scheduled::{get_scheduled_jobs, mark_scheduled};
+use crate::size::format_size;
+use crate::superseded::mark_superseded;
+use crate::timings::{self, Phase};
+use crate::users::{get_owner, get_user_homes};
+use crate::vscode::{
+    get_editor_references, get_user_settings, mark_editor_configs, EditorReference,
+};
 use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local};
 use dirs::home_dir;
-use human_bytes::human_bytes;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::ffi::OsStr;
-
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fs::canonicalize;
 use std::fs::symlink_metadata;
-use std::fs::{read_dir, File};
+use std::fs::{read_dir, read_to_string, File};
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use walkdir::WalkDir;
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+/// Marker file that pins an environment against deletion
+pub const KEEP_MARKER: &str = ".venvpruner-keep";
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct VirtualEnv {
+    #[serde(with = "crate::paths")]
     pub path: PathBuf,
+    /// The last component of `path`, for display
     pub name: String,
+    #[serde(with = "crate::paths")]
     pub python_path: PathBuf,
     pub python_version: String,
     pub venv_size: u64,
     pub venv_size_str: String,
+    /// Bytes shared with APFS clones that deleting this env would not free
+    #[serde(default)]
+    pub shared_size: u64,
+    /// Apparent size of sparse files beyond what they occupy on disk, which
+    /// is not part of `venv_size`
+    #[serde(default)]
+    pub sparse_size: u64,
+    /// Bytes of ML frameworks like torch and the CUDA libraries, which are
+    /// part of `venv_size`
+    #[serde(default)]
+    pub ml_size: u64,
+    /// User owning the environment, set when scanning all users' homes
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Project the environment belongs to, when known
+    #[serde(default, with = "crate::paths::option")]
+    pub project: Option<PathBuf>,
+    /// Name of the project the environment belongs to, when only that is
+    /// known, e.g. from a PDM environment's name
+    #[serde(default)]
+    pub project_name: Option<String>,
+    /// Symlink the environment is known by, like pyenv-virtualenv's
+    /// `~/.pyenv/versions/<name>`; removed along with the environment
+    #[serde(default, with = "crate::paths::option")]
+    pub link: Option<PathBuf>,
+    /// Environments that break when this Python installation is deleted
+    #[serde(default, with = "crate::paths::vec")]
+    pub dependents: Vec<PathBuf>,
+    #[serde(default)]
+    pub manager: Manager,
+    /// Most recent modification of the environment's top-level entries
+    #[serde(default)]
+    pub modified: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub created: Option<DateTime<Local>>,
+    /// Environment this one duplicates: a more recently used conda environment
+    /// with the identical package set, or the original of a copied directory
+    #[serde(default, with = "crate::paths::option")]
+    pub clone_of: Option<PathBuf>,
+    /// Environment of the same project with a newer interpreter
+    #[serde(default, with = "crate::paths::option")]
+    pub superseded_by: Option<PathBuf>,
+    /// Contains a `.venvpruner-keep` marker: never preselected, deleted only with `--force`
+    #[serde(default)]
+    pub pinned: bool,
+    /// Set with `venvpruner note`
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Set with `venvpruner tag add`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Crontab or systemd unit referring to the environment, which protects it
+    #[serde(default)]
+    pub scheduled_by: Option<String>,
+    /// VS Code settings files selecting the environment's interpreter
+    #[serde(default, with = "crate::paths::vec")]
+    pub editor_configs: Vec<PathBuf>,
+    /// Jupyter kernels running the environment's interpreter
+    #[serde(default)]
+    pub kernels: Vec<String>,
+    /// Where the interpreter's dangling symlink points, e.g. a Python
+    /// uninstalled by pyenv or Homebrew
+    #[serde(default, with = "crate::paths::option")]
+    pub missing_interpreter: Option<PathBuf>,
+    /// The interpreter is gone. Checked while scanning, on the host the
+    /// environment lives on.
+    #[serde(default)]
+    pub broken: bool,
+    /// The project directory no longer exists, checked like `broken`
+    #[serde(default)]
+    pub orphaned: bool,
+}
+
+impl VirtualEnv {
+    /// The interpreter is gone, e.g. a symlink to an uninstalled base Python
+    pub fn is_broken(&self) -> bool {
+        self.broken
+    }
+
+    /// Belongs to a project whose directory no longer exists: an IDE project,
+    /// or the editable install of a poetry or pipenv environment
+    pub fn is_orphaned(&self) -> bool {
+        self.orphaned
+    }
+
+    /// Estimated space freed by deleting this environment
+    pub fn reclaimable_size(&self) -> u64 {
+        self.venv_size.saturating_sub(self.shared_size)
+    }
 }
 
 impl fmt::Display for VirtualEnv {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(owner) = &self.owner {
+            write!(f, " @{}", owner)?;
+        }
         write!(
             f,
-            "{} - {} ({}) [{}]",
-            self.name,
+            " - {} ({}",
             self.path.display(),
-            self.venv_size_str,
-            self.python_version
-        )
+            format_size(self.venv_size)
+        )?;
+        if self.shared_size > 0 {
+            write!(f, ", {} shared", format_size(self.shared_size))?;
+        }
+        // Only worth pointing out when `ls -l` would suggest a very different size
+        if self.sparse_size > self.venv_size / 10 {
+            write!(
+                f,
+                ", {} more apparent in sparse files",
+                format_size(self.sparse_size)
+            )?;
+        }
+        match self.manager.is_python() {
+            true => write!(f, ") [{}, {}]", self.python_version, self.manager)?,
+            false => write!(f, ") [{}]", self.manager)?,
+        }
+        if self.ml_size > 0 {
+            write!(f, " [ML ~{}]", format_size(self.ml_size))?;
+        }
+        if let Some(link) = &self.link {
+            write!(f, " (linked as {})", link.display())?;
+        }
+        match (self.is_broken(), &self.missing_interpreter) {
+            (true, Some(target)) => write!(f, " (broken: {} is gone)", target.display())?,
+            (true, None) => write!(f, " (broken)")?,
+            (false, _) => {}
+        }
+        if !self.dependents.is_empty() {
+            write!(f, " (used by {} environments)", self.dependents.len())?;
+        }
+        match (&self.project, &self.project_name) {
+            (Some(project), _) if self.is_orphaned() => {
+                write!(f, " (orphaned: {} is gone)", project.display())?
+            }
+            (Some(project), _) => write!(f, " (project: {})", project.display())?,
+            (None, Some(name)) => write!(f, " (project: {})", name)?,
+            (None, None) => {}
+        }
+        if let Some(clone_of) = &self.clone_of {
+            write!(f, " (clone of {})", clone_of.display())?;
+        }
+        if let Some(newer) = &self.superseded_by {
+            write!(f, " (superseded by {})", newer.display())?;
+        }
+        if self.pinned {
+            write!(f, " (pinned)")?;
+        }
+        if let Some(source) = &self.scheduled_by {
+            write!(f, " (used by {})", source)?;
+        }
+        for settings in &self.editor_configs {
+            write!(f, " (selected in {})", settings.display())?;
+        }
+        if !self.kernels.is_empty() {
+            write!(f, " (kernel-backed: {})", self.kernels.join(", "))?;
+        }
+        for tag in &self.tags {
+            write!(f, " #{}", tag)?;
+        }
+        if let Some(note) = &self.note {
+            write!(f, " — {:?}", note)?;
+        }
+        Ok(())
     }
 }
 
-pub fn get_venv_paths() -> Result<Vec<PathBuf>> {
+/// Where an environment's interpreter lives: `bin/python`, or
+/// `Scripts\python.exe` in environments created on Windows
+const INTERPRETER_PATHS: [&str; 2] = ["bin/python", "Scripts/python.exe"];
+
+/// The interpreters the environment at `venv_root` may have, preferred
+/// first: the usual names, then `bin/python3` and any `bin/python3.X` for
+/// environments without the `python` alias
+fn get_interpreter_candidates(venv_root: &Path) -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = INTERPRETER_PATHS
+        .iter()
+        .chain(["bin/python3"].iter())
+        .map(|interpreter| venv_root.join(interpreter))
+        .collect();
+    if let Ok(entries) = read_dir(venv_root.join("bin")) {
+        let mut versioned: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                // Not `python3.11-config` and the like
+                entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| name.strip_prefix("python3."))
+                    .is_some_and(|minor| {
+                        !minor.is_empty() && minor.bytes().all(|byte| byte.is_ascii_digit())
+                    })
+            })
+            .map(|entry| entry.path())
+            .collect();
+        versioned.sort();
+        candidates.extend(versioned);
+    }
+    candidates
+}
+
+/// The interpreter of the environment at `venv_root`, including a dangling
+/// symlink; `bin/python` when there is none at all
+pub fn get_interpreter_path(venv_root: &Path) -> PathBuf {
+    get_interpreter_candidates(venv_root)
+        .into_iter()
+        .find(|interpreter| interpreter.exists() || interpreter.is_symlink())
+        .unwrap_or_else(|| venv_root.join(INTERPRETER_PATHS[0]))
+}
+
+/// How deep below a search path environments are looked for, unless
+/// configured for that path
+const SEARCH_DEPTH: usize = 3;
+
+/// How deep to walk below a project root looking for local environments
+const PROJECT_SCAN_DEPTH: usize = 6;
+
+/// Names a project gives its local environment
+const PROJECT_VENV_NAMES: &[&str] = &[".venv", "venv", "env"];
+
+/// Files marking a directory as a Python project
+const PROJECT_FILES: &[&str] = &["pyproject.toml", "requirements.txt"];
+
+/// Options controlling where and how virtual environments are discovered
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// Extra directories searched like the well-known locations (`--path`)
+    pub search_paths: Vec<PathBuf>,
+    /// Project directories to walk for project-local environments (`.venv` etc.)
+    pub project_roots: Vec<PathBuf>,
+    /// Gitignore-style patterns of paths to skip while walking search paths
+    /// and project roots
+    pub excludes: Vec<String>,
+    /// Skip `node_modules`, `.git` and other trees that never contain environments
+    pub skip_known_dirs: bool,
+    /// Search every user's home directory and record each environment's owner
+    pub all_users: bool,
+    /// Give up on discovery and sizing after this long, keeping what is done
+    pub timeout: Option<std::time::Duration>,
+    /// Stop walking roots at this point, keeping what was found so far
+    pub walk_deadline: Option<std::time::Instant>,
+    /// Other ecosystems' caches to include, such as `node_modules`
+    pub ecosystems: Vec<Ecosystem>,
+    /// Also list the Python installations in `~/.pyenv/versions`
+    pub include_pyenv_versions: bool,
+    /// Also ask `conda env list` for environments
+    pub query_conda: bool,
+    /// Find the environments below project roots through the filesystem
+    /// index instead of walking, where there is one
+    pub use_index: bool,
+    /// Only look below this directory, instead of the usual locations and
+    /// project roots
+    pub only: Option<PathBuf>,
+    /// How deep to walk below project roots, instead of `PROJECT_SCAN_DEPTH`
+    pub max_depth: Option<usize>,
+    /// How deep to walk below individual search or project roots, instead
+    /// of `SEARCH_DEPTH` or `PROJECT_SCAN_DEPTH`
+    pub root_depths: HashMap<PathBuf, usize>,
+}
+
+impl ScanOptions {
+    /// Whether walks should stop where they are, as the deadline has passed
+    fn walk_expired(&self) -> bool {
+        self.walk_deadline
+            .is_some_and(|deadline| std::time::Instant::now() >= deadline)
+    }
+
+    /// The options with `only` in place of the configured roots
+    fn restricted(&self) -> ScanOptions {
+        match &self.only {
+            Some(only) => ScanOptions {
+                project_roots: vec![only.clone()],
+                ..self.clone()
+            },
+            None => self.clone(),
+        }
+    }
+
+    /// How deep to walk below the project root at canonical path `root`
+    fn get_project_depth(&self, root: &Path) -> usize {
+        self.max_depth
+            .or_else(|| self.get_root_depth(root))
+            .unwrap_or(PROJECT_SCAN_DEPTH)
+    }
+
+    /// The depth configured for the root at canonical path `root`, which
+    /// may have been given through a symlink
+    fn get_root_depth(&self, root: &Path) -> Option<usize> {
+        self.root_depths
+            .iter()
+            .find(|(path, _)| canonicalize(path).is_ok_and(|path| path == root))
+            .map(|(_, depth)| *depth)
+    }
+}
+
+/// The environments found by a scan
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub venvs: Vec<VirtualEnv>,
+    /// The scan ran out of time, so some environments may be missing
+    pub partial: bool,
+}
+
+pub fn get_venv_paths(options: &ScanOptions) -> Result<Vec<PathBuf>> {
     let home_dir = home_dir().ok_or_else(|| anyhow!("Could not find home directory"))?;
+    let mut venv_paths = find_venv_roots(get_search_paths(&home_dir), options);
+    // conda environments may have been created anywhere with `--prefix`
+    venv_paths.extend(get_registered_envs(&home_dir));
+    Ok(dedupe_venv_paths(venv_paths))
+}
 
-    let search_paths = vec![
+/// Drops environments found more than once, e.g. through search roots that
+/// overlap or a symlinked root, keeping the first canonical path of each
+pub fn dedupe_venv_paths(venv_paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    venv_paths
+        .into_iter()
+        .map(|path| canonicalize(&path).unwrap_or(path))
+        .filter(|path| seen.insert(path.clone()))
+        .collect()
+}
+
+/// Known environment locations for the user with the given home directory,
+/// plus the system-wide ones
+pub fn get_search_paths(home_dir: &Path) -> Vec<PathBuf> {
+    let mut search_paths = vec![
         // pipx
         home_dir.join(".local/pipx/venvs"),
+        // uv tools
+        home_dir.join(".local/share/uv/tools"),
         // virtualenvwrapper
         home_dir.join(".virtualenvs"),
         // virtualenv
@@ -62,8 +401,9 @@ pub fn get_venv_paths() -> Result<Vec<PathBuf>> {
         home_dir.join("miniforge3/envs"),
         home_dir.join("mambaforge/envs"),
         home_dir.join("mambaforge3/envs"),
-        // pyenv
-        home_dir.join(".pyenv/versions/envs"),
+        // micromamba's root prefix, before and after 2.0
+        home_dir.join("micromamba/envs"),
+        home_dir.join(".local/share/mamba/envs"),
         // asdf
         home_dir.join(".asdf/installs/python"),
         home_dir.join(".asdf/installs/python/versions"),
@@ -76,6 +416,66 @@ pub fn get_venv_paths() -> Result<Vec<PathBuf>> {
         "/opt/miniconda3/envs".into(),
     ];
 
+    // pyenv-virtualenv, below each Python version
+    search_paths.extend(get_pyenv_envs_dirs(home_dir));
+    // micromamba, moved with $MAMBA_ROOT_PREFIX
+    search_paths.extend(get_mamba_root_prefix().map(|prefix| prefix.join("envs")));
+    // conda, redirected with $CONDA_ENVS_PATH or `envs_dirs` in .condarc
+    search_paths.extend(get_conda_envs_dirs(home_dir));
+    // virtualenvwrapper, moved with $WORKON_HOME
+    search_paths.extend(get_workon_home());
+    // pdm, in its data directory or $PDM_HOME
+    search_paths.extend(get_pdm_venv_dirs(home_dir));
+    // hatch, below its data directory or $HATCH_DATA_DIR
+    search_paths.extend(get_hatch_project_dirs(home_dir));
+    // rye tools, in ~/.rye or $RYE_HOME
+    search_paths.push(get_rye_tools_dir(home_dir));
+
+    #[cfg(windows)]
+    search_paths.extend(get_windows_search_paths(home_dir));
+
+    // Sandboxed apps (e.g. IDE snaps/flatpaks) create envs in their own data dirs
+    // snap: ~/snap/<app>/common
+    search_paths.extend(get_app_data_dirs(&home_dir.join("snap"), "common"));
+    // flatpak: ~/.var/app/<app-id>/data
+    search_paths.extend(get_app_data_dirs(&home_dir.join(".var/app"), "data"));
+
+    search_paths
+}
+
+/// Locations only used on Windows, besides those under the home directory
+/// shared with other platforms (`.virtualenvs`, `anaconda3\envs`, ...)
+#[cfg(windows)]
+fn get_windows_search_paths(home_dir: &Path) -> Vec<PathBuf> {
+    let mut search_paths = vec![
+        // pipx keeps environments in %USERPROFILE%\pipx on Windows
+        home_dir.join("pipx/venvs"),
+    ];
+    if let Some(local_app_data) = dirs::data_local_dir() {
+        // poetry: %LOCALAPPDATA%\pypoetry\Cache\virtualenvs
+        search_paths.push(local_app_data.join("pypoetry/Cache/virtualenvs"));
+        // pipx before 1.3: %LOCALAPPDATA%\pipx\pipx\venvs
+        search_paths.push(local_app_data.join("pipx/pipx/venvs"));
+    }
+    search_paths
+}
+
+/// Expands `<apps_dir>/*/<data_dir>` to the data directories that exist
+pub fn get_app_data_dirs(apps_dir: &Path, data_dir: &str) -> Vec<PathBuf> {
+    match read_dir(apps_dir) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path().join(data_dir))
+            .filter(|path| path.is_dir())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// Walks the search paths and returns the root directory of every
+/// virtual environment found below them, skipping the excluded paths of
+/// `options` and walking each as deep as configured
+pub fn find_venv_roots(search_paths: Vec<PathBuf>, options: &ScanOptions) -> Vec<PathBuf> {
     // Step 1: Canonicalize each search path to resolve symlinks
     let canonical_paths: Vec<PathBuf> = search_paths
         .iter() // Use parallel iteration for efficiency
@@ -88,56 +488,277 @@ pub fn get_venv_paths() -> Result<Vec<PathBuf>> {
         .filter(|p| unique_canonical_paths.insert(p.clone())) // Insert returns false if already present
         .collect();
 
+    let blocked = BlockedDirs::default();
     let venv_roots: Vec<PathBuf> = unique_paths
         .into_par_iter()
         .map(|search_path| {
-            WalkDir::new(search_path)
-                .follow_links(false)
-                .max_depth(4)
-                .into_iter()
-                .filter_map(Result::ok)
-                .filter(|entry| {
-                    // Compare OsStr directly without type mismatch
-                    entry.file_name() == "python"
-                        && entry
-                            .path()
-                            .parent()
-                            .map_or(false, |p| p.file_name() == Some(OsStr::new("bin")))
-                })
-                .filter_map(|entry| {
-                    entry
-                        .path()
-                        .parent() // bin_dir
-                        .and_then(|bin_dir| bin_dir.parent()) // venv_root
-                        .map(|venv_root| venv_root.to_path_buf())
-                })
-                .collect::<Vec<_>>() // Collect the inner iterator into a Vec
+            timings::time(Phase::Walk, Some(&search_path), || {
+                // Relative patterns are anchored at each search path
+                let matcher = match ExcludeMatcher::new(
+                    &search_path,
+                    &options.excludes,
+                    options.skip_known_dirs,
+                ) {
+                    Ok(matcher) => Some(matcher),
+                    Err(err) => {
+                        eprintln!("{:#}", err);
+                        None
+                    }
+                };
+                let mut venv_roots = Vec::new();
+                let mut walker = WalkDir::new(&search_path)
+                    .follow_links(false)
+                    .max_depth(options.get_root_depth(&search_path).unwrap_or(SEARCH_DEPTH))
+                    .into_iter();
+                while let Some(entry) = walker.next() {
+                    if options.walk_expired() {
+                        break;
+                    }
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(err) => {
+                            blocked.note(&err);
+                            continue;
+                        }
+                    };
+                    let is_dir = entry.file_type().is_dir();
+                    let excluded = matcher
+                        .as_ref()
+                        .is_some_and(|matcher| matcher.is_excluded(entry.path(), is_dir));
+                    if excluded {
+                        // Excluded trees aren't even walked
+                        if is_dir {
+                            walker.skip_current_dir();
+                        }
+                        continue;
+                    }
+                    if is_dir && is_venv_root(entry.path()) {
+                        venv_roots.push(entry.path().to_path_buf());
+                        // Environments don't nest
+                        walker.skip_current_dir();
+                    }
+                }
+                venv_roots
+            })
         })
         .flatten() // Flatten the Vec<Vec<PathBuf>> into Vec<PathBuf>
         .collect(); // Collect the final results into Vec<PathBuf>
-                    // deduplicate the paths
+    blocked.report();
+
+    // A search path nested in another finds the same environments again
+    dedupe_venv_paths(venv_roots)
+}
+
+/// Whether `path` has a `pyvenv.cfg` or `conda-meta`, which only
+/// environments have, unlike a bare interpreter
+fn has_venv_marker(path: &Path) -> bool {
+    path.join("pyvenv.cfg").is_file() || path.join("conda-meta").is_dir()
+}
+
+/// Returns true if `path` looks like the root of a virtual environment:
+/// it has a `pyvenv.cfg` or `conda-meta`, or failing that an interpreter
+/// (including a dangling link to one), as in environments made by old
+/// virtualenv versions
+pub fn is_venv_root(path: &Path) -> bool {
+    let has_interpreter = |interpreters: &[PathBuf]| {
+        interpreters
+            .iter()
+            .any(|interpreter| interpreter.symlink_metadata().is_ok())
+    };
+    has_venv_marker(path)
+        || has_interpreter(&INTERPRETER_PATHS.map(|interpreter| path.join(interpreter)))
+        // Only `python3` counts next to an activation script, as `/usr` and
+        // other system prefixes have one too
+        || (path.join("bin/activate").is_file()
+            && has_interpreter(&get_interpreter_candidates(path)))
+        || pdm::is_pypackages_dir(path)
+}
+
+/// Whether the environment at `path` belongs to the project around it: a
+/// `.venv`, `venv` or `env` next to a `pyproject.toml` or `requirements.txt`,
+/// or one that tox, nox, direnv or PEP 582 keeps inside a project
+fn is_project_venv(path: &Path) -> bool {
+    match detect_manager(path) {
+        Manager::Tox | Manager::Nox | Manager::Direnv | Manager::Pep582 => true,
+        _ => {
+            let is_named = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| PROJECT_VENV_NAMES.contains(&name));
+            let in_project = path.parent().is_some_and(|project| {
+                PROJECT_FILES
+                    .iter()
+                    .any(|file| project.join(file).is_file())
+            });
+            is_named && in_project
+        }
+    }
+}
+
+/// Walks each project root looking for project-local virtual environments,
+/// skipping excluded paths without descending into them.
+pub fn get_project_venv_paths(options: &ScanOptions) -> Result<Vec<PathBuf>> {
+    let mut venv_roots = Vec::new();
+    let blocked = BlockedDirs::default();
+
+    for project_root in &options.project_roots {
+        let project_root = match canonicalize(project_root) {
+            Ok(path) => path,
+            Err(_) => {
+                eprintln!("Project root not found: {}", project_root.display());
+                continue;
+            }
+        };
+        let matcher =
+            ExcludeMatcher::new(&project_root, &options.excludes, options.skip_known_dirs)?;
+
+        let start = std::time::Instant::now();
+        let mut walker = WalkDir::new(&project_root)
+            .follow_links(false)
+            .max_depth(options.get_project_depth(&project_root))
+            .into_iter()
+            .filter_entry(|entry| {
+                let is_dir = entry.file_type().is_dir();
+                entry.depth() == 0 || !matcher.is_excluded(entry.path(), is_dir)
+            });
+
+        while let Some(entry) = walker.next() {
+            if options.walk_expired() {
+                break;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    blocked.note(&err);
+                    continue;
+                }
+            };
+            // The root itself is where the search starts, not an environment
+            if !entry.file_type().is_dir() || entry.depth() == 0 {
+                continue;
+            }
+            if is_venv_root(entry.path()) {
+                // `--only` takes every environment below its directory, but
+                // an interpreter alone is no proof outside a project: `/usr`
+                // and other prefixes have `bin/python` too
+                let only_venv = options.only.is_some() && has_venv_marker(entry.path());
+                if only_venv || is_project_venv(entry.path()) {
+                    venv_roots.push(entry.path().to_path_buf());
+                }
+                // Nothing of interest lives inside an environment
+                walker.skip_current_dir();
+            }
+        }
+        timings::record(Phase::Walk, Some(&project_root), start.elapsed());
+    }
+    blocked.report();
 
     Ok(venv_roots)
 }
 
+/// The environments of `indexed` that walking the project roots would find:
+/// within the walk depth, not excluded, not inside another environment and
+/// belonging to a project
+pub fn get_indexed_project_venv_paths(
+    options: &ScanOptions,
+    indexed: &[PathBuf],
+) -> Result<Vec<PathBuf>> {
+    let indexed_set: HashSet<&Path> = indexed.iter().map(PathBuf::as_path).collect();
+    let mut venv_roots = Vec::new();
+    for project_root in &options.project_roots {
+        let project_root = match canonicalize(project_root) {
+            Ok(path) => path,
+            Err(_) => {
+                eprintln!("Project root not found: {}", project_root.display());
+                continue;
+            }
+        };
+        let matcher =
+            ExcludeMatcher::new(&project_root, &options.excludes, options.skip_known_dirs)?;
+        let depth = options.get_project_depth(&project_root);
+        for path in indexed {
+            let relative = match path.strip_prefix(&project_root) {
+                Ok(relative) => relative,
+                Err(_) => continue,
+            };
+            if relative.components().count() > depth {
+                continue;
+            }
+            let hidden = path
+                .ancestors()
+                .take_while(|ancestor| *ancestor != project_root)
+                .enumerate()
+                .any(|(i, ancestor)| {
+                    matcher.is_excluded(ancestor, true) || (i > 0 && indexed_set.contains(ancestor))
+                });
+            if !hidden && is_project_venv(path) {
+                venv_roots.push(path.clone());
+            }
+        }
+    }
+    Ok(venv_roots)
+}
+
+/// Disk usage of a directory tree
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirUsage {
+    /// Bytes actually allocated, or the apparent size where that is smaller
+    pub size: u64,
+    /// Apparent bytes of sparse (or compressed) files not backed by disk blocks
+    pub sparse: u64,
+}
+
+impl std::ops::Add for DirUsage {
+    type Output = DirUsage;
+
+    fn add(self, other: DirUsage) -> DirUsage {
+        DirUsage {
+            size: self.size + other.size,
+            sparse: self.sparse + other.sparse,
+        }
+    }
+}
+
 pub fn get_dir_size(path: &Path) -> u64 {
+    get_dir_usage(path).size
+}
+
+/// Bytes allocated to a file; st_blocks is always in 512-byte units
+#[cfg(unix)]
+fn get_allocated_size(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+/// Windows doesn't report allocated blocks, so files count at their length
+#[cfg(not(unix))]
+fn get_allocated_size(metadata: &std::fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Sizes `path` by allocated blocks, so sparse files count for what they
+/// occupy rather than their length
+pub fn get_dir_usage(path: &Path) -> DirUsage {
     // Get the metadata of the current path without following symlinks
     let metadata = match symlink_metadata(path) {
         Ok(meta) => meta,
         Err(_) => {
             eprintln!("Failed to get metadata for {}", path.display());
-            return 0;
+            return DirUsage::default();
         }
     };
 
     // Check if the path is a symlink
     if metadata.file_type().is_symlink() {
         // Skip symlinks to avoid cycles and double-counting
-        return 0;
+        return DirUsage::default();
     }
 
-    // Start with the size of the current file
-    let mut size = metadata.len();
+    let allocated = get_allocated_size(&metadata);
+    let mut usage = DirUsage {
+        size: metadata.len().min(allocated),
+        sparse: metadata.len().saturating_sub(allocated),
+    };
 
     // If it's a directory, recursively get the size of its contents
     if metadata.is_dir() {
@@ -145,75 +766,97 @@ pub fn get_dir_size(path: &Path) -> u64 {
             Ok(entries) => entries,
             Err(_) => {
                 eprintln!("Failed to read directory {}", path.display());
-                return size;
+                return usage;
             }
         };
 
         // Process entries in parallel and accumulate sizes
-        let dir_size: u64 = entries
+        let dir_usage = entries
             .par_bridge()
             .map(|entry_result| {
                 match entry_result {
                     Ok(entry) => {
                         let entry_path = entry.path();
                         // Recursively calculate the size of each entry
-                        get_dir_size(&entry_path)
+                        get_dir_usage(&entry_path)
                     }
                     Err(_) => {
                         eprintln!("Failed to read an entry in {}", path.display());
-                        0
+                        DirUsage::default()
                     }
                 }
             })
-            .sum();
+            .reduce(DirUsage::default, |a, b| a + b);
 
         // Add the size of the directory contents to the current directory size
-        size += dir_size;
+        usage = usage + dir_usage;
     }
 
-    size
+    usage
 }
 
 pub fn build_virtualenv(path: PathBuf) -> Result<VirtualEnv> {
-    let bin_dir = path.join("bin");
-    let python_path = bin_dir.join("python");
-
-    // Ensure that the python executable exists
-    if !python_path.exists() {
-        if python_path.is_symlink() {
-            let symlinked_path = python_path.read_link().unwrap();
-            // Check if the symlink target exists
-            if !symlinked_path.exists() {
-                // we're missing the linked python executable
-                // notify of broken symlink or remove?
-
-                // std::fs::remove_file(&bin_dir.join("python")).with_context(|| {
-                //     format!("Failed to remove symlink: {}", python_path.display())
-                // })?;
-                return Err(anyhow!(
-                    "Broken symlink for virtualenv {} in {}",
-                    python_path.display(),
-                    symlinked_path.display()
-                ));
-            }
-        }
-        return Err(anyhow!(
-            "Python executable not found in {}",
-            python_path.display()
-        ));
-    }
+    let python_path = get_interpreter_path(&path);
+
+    // An environment whose interpreter is gone is broken, but still listed
+    // so it can be deleted
+    let missing_interpreter = match python_path.exists() {
+        true => None,
+        false => python_path.read_link().ok(),
+    };
 
     // Get the python version
-    let python_version = get_python_version(&path)?.unwrap_or_else(|| "Unknown".to_string());
+    let python_version = timings::time(Phase::Version, Some(&path), || get_python_version(&path))?
+        .unwrap_or_else(|| "Unknown".to_string());
 
     let name = path
         .file_name()
-        .and_then(|os_str| os_str.to_str())
         .ok_or_else(|| anyhow!("Failed to parse virtual environment name"))?
-        .to_string();
+        .to_string_lossy()
+        .into_owned();
 
-    let venv_size = get_dir_size(&path);
-    let venv_size_str = human_bytes(venv_size as f64);
+    let (usage, shared_size, ml_size) = timings::time(Phase::Sizing, Some(&path), || {
+        (
+            get_dir_usage(&path),
+            get_shared_size(&path),
+            get_ml_size(&path),
+        )
+    });
+    let venv_size = usage.size;
+    let venv_size_str = crate::size::format_human(venv_size);
+    let manager = detect_manager(&path);
+    let modified = get_modified(&path);
+    let created = get_created(&path);
+    let pinned = path.join(KEEP_MARKER).exists();
+    let project = match manager {
+        // `<project>/.tox/<env>`, `<project>/.nox/<session>`,
+        // `<project>/.direnv/python-<version>` and `<project>/__pypackages__/<version>`
+        Manager::Tox | Manager::Nox | Manager::Direnv | Manager::Pep582 => {
+            path.parent().and_then(Path::parent).map(Path::to_path_buf)
+        }
+        // Poetry's hashed names don't say where the project is either, but
+        // the project itself is usually installed in editable mode
+        Manager::Poetry | Manager::Pipenv => {
+            get_linked_project(&path).or_else(|| get_editable_project(&path))
+        }
+        _ => get_linked_project(&path),
+    };
+    let link = match manager {
+        Manager::Pyenv => get_pyenv_link(&path),
+        _ => None,
+    };
+    let orphaned = project.as_ref().is_some_and(|project| !project.exists());
+    let project_name = match manager {
+        Manager::Pdm => pdm::get_project_name(&name),
+        Manager::Hatch => hatch::get_project_name(&path),
+        _ => None,
+    };
+    // conda environments need not contain Python at all, e.g. an R one,
+    // and PEP 582 package directories never do
+    let broken = manager.is_python()
+        && manager != Manager::Pep582
+        && !python_path.exists()
+        && (missing_interpreter.is_some() || !path.join("conda-meta").is_dir());
 
     Ok(VirtualEnv {
         path,
@@ -222,10 +865,161 @@ pub fn build_virtualenv(path: PathBuf) -> Result<VirtualEnv> {
         python_version,
         venv_size,
         venv_size_str,
+        shared_size,
+        sparse_size: usage.sparse,
+        ml_size,
+        owner: None,
+        project,
+        project_name,
+        link,
+        dependents: Vec::new(),
+        missing_interpreter,
+        manager,
+        modified,
+        created,
+        clone_of: None,
+        superseded_by: None,
+        pinned,
+        note: None,
+        tags: vec![],
+        scheduled_by: None,
+        editor_configs: Vec::new(),
+        kernels: Vec::new(),
+        broken,
+        orphaned,
     })
 }
 
+/// Latest mtime of the environment root and the entries that change when
+/// packages are installed; walking the whole tree would be too slow
+pub fn get_modified(venv_root: &Path) -> Option<DateTime<Local>> {
+    ["", "bin", "pyvenv.cfg", "conda-meta", "lib"]
+        .iter()
+        .filter_map(|entry| venv_root.join(entry).metadata().ok())
+        .filter_map(|metadata| metadata.modified().ok())
+        .max()
+        .map(DateTime::from)
+}
+
+/// Birth time of the environment root or, where the filesystem doesn't
+/// record one, when `pyvenv.cfg` was written
+pub fn get_created(venv_root: &Path) -> Option<DateTime<Local>> {
+    venv_root
+        .metadata()
+        .and_then(|metadata| metadata.created())
+        .or_else(|_| {
+            venv_root
+                .join("pyvenv.cfg")
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+        })
+        .ok()
+        .map(DateTime::from)
+}
+
+/// The project directory recorded in the environment's `.project` file, as
+/// pipenv and virtualenvwrapper's `setvirtualenvproject` write it. Pipenv's
+/// hashed names like `myproj-a1B2c3D4` don't say where the project is.
+pub fn get_linked_project(venv_root: &Path) -> Option<PathBuf> {
+    let contents = std::fs::read_to_string(venv_root.join(".project")).ok()?;
+    match contents.trim() {
+        "" => None,
+        project => Some(PathBuf::from(project)),
+    }
+}
+
+/// The directory of the project installed in editable mode, as `poetry
+/// install` and `pipenv install -e .` do, from its `direct_url.json`
+pub fn get_editable_project(venv_root: &Path) -> Option<PathBuf> {
+    #[derive(Deserialize)]
+    struct DirectUrl {
+        url: String,
+        #[serde(default)]
+        dir_info: DirInfo,
+    }
+    #[derive(Default, Deserialize)]
+    struct DirInfo {
+        #[serde(default)]
+        editable: bool,
+    }
+
+    let pattern = venv_root.join("lib/python*/site-packages/*.dist-info/direct_url.json");
+    glob::glob(&pattern.to_string_lossy())
+        .ok()?
+        .filter_map(Result::ok)
+        .filter_map(|path| read_to_string(path).ok())
+        .filter_map(|json| serde_json::from_str::<DirectUrl>(&json).ok())
+        .filter(|direct_url| direct_url.dir_info.editable)
+        .find_map(|direct_url| {
+            let path = direct_url.url.strip_prefix("file://")?;
+            Some(PathBuf::from(percent_decode(path)))
+        })
+}
+
+/// Decodes the `%XX` escapes of a file URL's path
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes[i] {
+            b'%' => text
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Builds the environments at `paths`, failing if any of them is not one
+pub fn load_venvs(paths: &[PathBuf]) -> Result<Vec<VirtualEnv>> {
+    let mut venvs = paths
+        .iter()
+        .map(|path| {
+            if !is_venv_root(path) {
+                return Err(anyhow!("Not a virtual environment: {}", path.display()));
+            }
+            // Delete the environment a symlink like `~/.pyenv/versions/<name>`
+            // points to, not the link
+            build_virtualenv(canonicalize(path).unwrap_or_else(|_| path.clone()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let homes: Vec<PathBuf> = home_dir().into_iter().collect();
+    mark_scheduled(&mut venvs, &get_scheduled_jobs(&homes));
+    // Only the workspaces the environments live in, without walking
+    for home in &homes {
+        let mut settings = get_user_settings(home);
+        settings.extend(
+            venvs
+                .iter()
+                .filter_map(|venv| venv.path.parent())
+                .map(|folder| folder.join(".vscode/settings.json"))
+                .filter(|path| path.is_file()),
+        );
+        mark_editor_configs(&mut venvs, &get_editor_references(&settings, home));
+        mark_kernels(&mut venvs, &get_kernels(&get_kernel_dirs(home)));
+    }
+    annotate_metadata(&mut venvs);
+    Ok(venvs)
+}
+
 pub fn get_python_version(venv_root: &Path) -> Result<Option<String>> {
+    // A PEP 582 package directory is named after the version
+    if let Some(version) = pdm::get_pypackages_version(venv_root) {
+        return Ok(Some(version));
+    }
+
     // Method 1: Read 'pyvenv.cfg' if it exists
     let pyvenv_cfg_path = venv_root.join("pyvenv.cfg");
     if pyvenv_cfg_path.exists() {
@@ -233,7 +1027,7 @@ pub fn get_python_version(venv_root: &Path) -> Result<Option<String>> {
             .with_context(|| format!("Failed to open {}", pyvenv_cfg_path.display()))?;
         let version_line = BufReader::new(file)
             .lines()
-            .filter_map(Result::ok)
+            .map_while(Result::ok)
             .find(|line| line.starts_with("version = "));
         if let Some(line) = version_line {
             let version = line["version = ".len()..].trim().to_string();
@@ -265,14 +1059,16 @@ pub fn get_python_version(venv_root: &Path) -> Result<Option<String>> {
             .with_context(|| format!("Failed to open {}", conda_history_path.display()))?;
         let version_line = BufReader::new(file)
             .lines()
-            .filter_map(Result::ok)
+            .map_while(Result::ok)
             .find(|line| line.contains("python-"));
         if let Some(line) = version_line {
             if let Some(start) = line.find("python-") {
+                // `python-<version>-<build>`
                 let version_info = &line[start + "python-".len()..];
                 let version = version_info
                     .split_whitespace()
                     .next()
+                    .and_then(|package| package.split('-').next())
                     .unwrap_or("Unknown")
                     .to_string();
                 return Ok(Some(version));
@@ -281,7 +1077,7 @@ pub fn get_python_version(venv_root: &Path) -> Result<Option<String>> {
     }
 
     // Method 4: Run 'python --version' (Most computational load)
-    let python_exec = venv_root.join("bin/python");
+    let python_exec = get_interpreter_path(venv_root);
     if python_exec.exists() {
         let output = Command::new(&python_exec)
             .arg("--version")
@@ -294,9 +1090,8 @@ pub fn get_python_version(venv_root: &Path) -> Result<Option<String>> {
         } else {
             stderr.trim()
         };
-        if version_output.starts_with("Python ") {
-            let version = version_output["Python ".len()..].to_string();
-            return Ok(Some(version));
+        if let Some(version) = version_output.strip_prefix("Python ") {
+            return Ok(Some(version.to_string()));
         }
     }
 
@@ -305,37 +1100,364 @@ pub fn get_python_version(venv_root: &Path) -> Result<Option<String>> {
 }
 
 pub fn build_virtualenvs(venv_paths: Vec<PathBuf>) -> Result<Vec<VirtualEnv>> {
+    let sizing = SizingProgress::new(venv_paths.len());
     let venvs: Vec<VirtualEnv> = venv_paths
         .into_par_iter()
-        .filter_map(|path| match build_virtualenv(path) {
-            Ok(venv) => Some(venv),
-            Err(err) => {
-                eprintln!("Error building virtualenv: {}", err);
-                None
+        .filter_map(|path| {
+            let result = build_virtualenv(path);
+            sizing.finished(result.as_ref().ok());
+            match result {
+                Ok(venv) => Some(venv),
+                Err(err) => {
+                    eprintln!("Error building virtualenv: {}", err);
+                    None
+                }
             }
         })
         .collect();
     Ok(venvs)
 }
 
-pub fn get_venvs() -> Result<Vec<VirtualEnv>> {
-    let venv_paths = get_venv_paths().context("Failed to get virtual environment paths")?;
-    let venvs = build_virtualenvs(venv_paths).context("Failed to build virtual environments")?;
-    Ok(venvs)
+/// Counts environments as they are sized, for `--progress json`
+struct SizingProgress {
+    done: std::sync::atomic::AtomicUsize,
+    total: usize,
 }
+
+impl SizingProgress {
+    fn new(total: usize) -> SizingProgress {
+        SizingProgress {
+            done: Default::default(),
+            total,
+        }
+    }
+
+    /// Reports one environment as sized, and as found unless it failed
+    fn finished(&self, venv: Option<&VirtualEnv>) {
+        if let Some(venv) = venv {
+            progress::env_found(venv);
+        }
+        let done = self.done.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+        progress::emit(progress::Event::SizingProgress {
+            done,
+            total: self.total,
+        });
+    }
+}
+
+/// The home directories covered by the scan options
+fn get_homes(options: &ScanOptions) -> Result<Vec<PathBuf>> {
+    let home_dir = home_dir().ok_or_else(|| anyhow!("Could not find home directory"))?;
+    Ok(match options.all_users {
+        true => {
+            let mut homes = get_user_homes();
+            if !homes.contains(&home_dir) {
+                homes.push(home_dir);
+            }
+            homes
+        }
+        false => vec![home_dir],
+    })
+}
+
+/// Finds the root directory of every environment covered by the scan options
+pub fn discover_venv_paths(options: &ScanOptions) -> Result<Vec<PathBuf>> {
+    if options.only.is_some() {
+        return get_project_venv_paths(&options.restricted())
+            .context("Failed to scan the --only directory");
+    }
+    let homes = get_homes(options)?;
+    let mut venv_paths = match options.all_users {
+        true => {
+            let search_paths = homes
+                .iter()
+                .flat_map(|home| get_search_paths(home))
+                .collect();
+            let mut venv_paths = find_venv_roots(search_paths, options);
+            venv_paths.extend(homes.iter().flat_map(|home| get_registered_envs(home)));
+            venv_paths
+        }
+        false => get_venv_paths(options).context("Failed to get virtual environment paths")?,
+    };
+    let extra_paths = options
+        .search_paths
+        .iter()
+        .filter(|path| match path.is_dir() {
+            true => true,
+            false => {
+                eprintln!("Search path not found: {}", path.display());
+                false
+            }
+        })
+        .cloned()
+        .collect();
+    venv_paths.extend(find_venv_roots(extra_paths, options));
+    if options.query_conda {
+        venv_paths.extend(get_listed_envs());
+    }
+    let project_paths = match options.use_index.then(find_indexed_venv_roots).flatten() {
+        Some(indexed) => get_indexed_project_venv_paths(options, &indexed)?,
+        None => {
+            if options.use_index {
+                eprintln!("No filesystem index available, walking the project roots");
+            }
+            get_project_venv_paths(options).context("Failed to scan project roots")?
+        }
+    };
+    venv_paths.extend(project_paths);
+
+    // IDE-registered interpreters may live anywhere
+    venv_paths.extend(get_ide_projects().into_keys());
+    // So are the interpreters selected in VS Code settings
+    venv_paths.extend(
+        get_vscode_references(options)
+            .into_iter()
+            .map(|reference| reference.venv_root),
+    );
+    // And those of Jupyter kernels
+    venv_paths.extend(
+        get_jupyter_kernels(options)
+            .into_iter()
+            .map(|kernel| kernel.venv_root),
+    );
+
+    // Overlapping sources find the same environment under different paths,
+    // and each must only be sized once
+    let mut venv_paths = dedupe_venv_paths(venv_paths);
+
+    // Environments found without walking, e.g. registered by an IDE
+    let matcher = ExcludeMatcher::absolute_only(&options.excludes)?;
+    venv_paths.retain(|path| !matcher.is_excluded(path, true));
+
+    // A `.venvprunerignore` in a home directory covers everything below it
+    for home in &homes {
+        let home = canonicalize(home).unwrap_or_else(|_| home.clone());
+        match ExcludeMatcher::new(&home, &[], false) {
+            Ok(matcher) => venv_paths.retain(|path| !matcher.is_excluded(path, true)),
+            Err(err) => eprintln!("{:#}", err),
+        }
+    }
+
+    Ok(venv_paths)
+}
+
+/// Maps the canonical root of each IDE-registered environment to its project
+fn get_ide_projects() -> HashMap<PathBuf, Option<PathBuf>> {
+    get_pycharm_interpreters()
+        .into_iter()
+        .filter_map(|interpreter| {
+            let path = canonicalize(&interpreter.venv_root).ok()?;
+            Some((path, interpreter.project))
+        })
+        .collect()
+}
+
+/// The `.vscode/settings.json` files of the folders below the project roots
+fn get_workspace_settings(options: &ScanOptions) -> Vec<PathBuf> {
+    let mut settings = Vec::new();
+    for project_root in &options.restricted().project_roots {
+        let project_root = match canonicalize(project_root) {
+            Ok(path) => path,
+            Err(_) => continue,
+        };
+        let matcher =
+            match ExcludeMatcher::new(&project_root, &options.excludes, options.skip_known_dirs) {
+                Ok(matcher) => matcher,
+                Err(_) => continue,
+            };
+        let walker = WalkDir::new(&project_root)
+            .follow_links(false)
+            .max_depth(options.get_project_depth(&project_root))
+            .into_iter()
+            .filter_entry(|entry| {
+                let is_dir = entry.file_type().is_dir();
+                entry.depth() == 0
+                    || !(matcher.is_excluded(entry.path(), is_dir)
+                        || is_dir && is_venv_root(entry.path()))
+            });
+        for entry in walker.filter_map(Result::ok) {
+            let path = entry.path().join("settings.json");
+            if entry.file_name() == ".vscode" && path.is_file() {
+                settings.push(path);
+            }
+        }
+    }
+    settings
+}
+
+/// The environments selected in the users' VS Code settings and in the
+/// workspace settings below the project roots
+fn get_vscode_references(options: &ScanOptions) -> Vec<EditorReference> {
+    let homes = get_homes(options).unwrap_or_default();
+    let mut references: Vec<EditorReference> = homes
+        .iter()
+        .flat_map(|home| get_editor_references(&get_user_settings(home), home))
+        .collect();
+    if let Some(home) = home_dir() {
+        references.extend(get_editor_references(
+            &get_workspace_settings(options),
+            &home,
+        ));
+    }
+    references
+}
+
+/// The Jupyter kernels of the users whose homes are scanned
+fn get_jupyter_kernels(options: &ScanOptions) -> Vec<JupyterKernel> {
+    let kernel_dirs: Vec<PathBuf> = get_homes(options)
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|home| get_kernel_dirs(home))
+        .collect();
+    get_kernels(&kernel_dirs)
+}
+
+/// Fills in details that depend on how the scan was run
+pub fn annotate_venvs(venvs: &mut [VirtualEnv], options: &ScanOptions) {
+    let homes = get_homes(options).unwrap_or_default();
+    mark_scheduled(venvs, &get_scheduled_jobs(&homes));
+    let mut ide_projects = get_ide_projects();
+    for venv in venvs.iter_mut() {
+        if options.all_users {
+            venv.owner = get_owner(&venv.path);
+        }
+        if let Some(Some(project)) = ide_projects.remove(&venv.path) {
+            venv.orphaned = !project.exists();
+            venv.project = Some(project);
+        }
+    }
+    mark_editor_configs(venvs, &get_vscode_references(options));
+    mark_kernels(venvs, &get_jupyter_kernels(options));
+    mark_clones(venvs);
+    mark_copies(venvs);
+    mark_superseded(venvs);
+    annotate_metadata(venvs);
+}
+
+pub fn get_venvs(options: &ScanOptions) -> Result<Scan> {
+    let mut scan = match options.timeout {
+        Some(timeout) => get_venvs_within(options, timeout)?,
+        None => {
+            let venv_paths = discover_venv_paths(options)?;
+            Scan {
+                venvs: build_virtualenvs(venv_paths)
+                    .context("Failed to build virtual environments")?,
+                partial: false,
+            }
+        }
+    };
+    // Skipped once a timeout has cut the scan short
+    if !options.ecosystems.is_empty() && !scan.partial {
+        let dirs = get_ecosystem_dirs(&options.restricted(), &get_homes(options)?)
+            .context("Failed to scan for other ecosystems' caches")?;
+        scan.venvs.extend(dirs);
+    }
+    if options.include_pyenv_versions && !scan.partial {
+        let versions = get_pyenv_versions(&get_homes(options)?, &scan.venvs);
+        scan.venvs.extend(versions);
+    }
+    if !scan.partial {
+        let toolchains = get_rye_toolchains(&get_homes(options)?, &scan.venvs);
+        scan.venvs.extend(toolchains);
+    }
+    if let Some(only) = &options.only {
+        scan.venvs.retain(|venv| venv.path.starts_with(only));
+    }
+    timings::time(Phase::Annotate, None, || {
+        annotate_venvs(&mut scan.venvs, options)
+    });
+    Ok(scan)
+}
+
+/// Discovers and sizes environments in the background, returning those
+/// finished within `timeout`. Walks still going at half the timeout stop
+/// there, so the environments found in the other roots can be sized.
+/// Environments not started by the end are skipped; those in progress
+/// finish unobserved.
+fn get_venvs_within(options: &ScanOptions, timeout: std::time::Duration) -> Result<Scan> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+
+    let start = std::time::Instant::now();
+    // None when the timeout is too long to ever be reached
+    let deadline = start.checked_add(timeout);
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let walks_cut = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel::<Result<VirtualEnv>>();
+    {
+        let options = ScanOptions {
+            walk_deadline: start.checked_add(timeout / 2),
+            ..options.clone()
+        };
+        let cancelled = cancelled.clone();
+        let walks_cut = walks_cut.clone();
+        std::thread::spawn(move || {
+            let venv_paths = match discover_venv_paths(&options) {
+                Ok(venv_paths) => venv_paths,
+                Err(err) => {
+                    let _ = sender.send(Err(err));
+                    return;
+                }
+            };
+            walks_cut.store(options.walk_expired(), Ordering::Relaxed);
+            let sizing = SizingProgress::new(venv_paths.len());
+            venv_paths
+                .into_par_iter()
+                .for_each_with(sender, |sender, path| {
+                    if cancelled.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let result = build_virtualenv(path);
+                    sizing.finished(result.as_ref().ok());
+                    match result {
+                        Ok(venv) => {
+                            let _ = sender.send(Ok(venv));
+                        }
+                        Err(err) => eprintln!("Error building virtualenv: {}", err),
+                    }
+                });
+        });
+    }
+
+    let mut scan = Scan::default();
+    loop {
+        let received = match deadline {
+            Some(deadline) => {
+                receiver.recv_timeout(deadline.saturating_duration_since(std::time::Instant::now()))
+            }
+            None => receiver
+                .recv()
+                .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(venv) => scan.venvs.push(venv?),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                cancelled.store(true, Ordering::Relaxed);
+                scan.partial = true;
+                break;
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    scan.partial |= walks_cut.load(Ordering::Relaxed);
+    Ok(scan)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     pub fn test_get_venv_paths() {
-        let venv_paths = get_venv_paths().expect("Failed to get virtual environment paths");
+        let venv_paths = get_venv_paths(&ScanOptions::default())
+            .expect("Failed to get virtual environment paths");
         assert!(!venv_paths.is_empty(), "No virtual environments found");
     }
 
     #[test]
     pub fn test_build_virtualenv() {
-        let venv_paths = get_venv_paths().expect("Failed to get virtual environment paths");
+        let venv_paths = get_venv_paths(&ScanOptions::default())
+            .expect("Failed to get virtual environment paths");
         let venv =
             build_virtualenv(venv_paths[0].clone()).expect("Failed to build virtual environment");
         assert_eq!(venv.path, venv_paths[0]);
@@ -343,7 +1465,8 @@ mod tests {
 
     #[test]
     pub fn test_get_python_version() {
-        let venv_paths = get_venv_paths().expect("Failed to get virtual environment paths");
+        let venv_paths = get_venv_paths(&ScanOptions::default())
+            .expect("Failed to get virtual environment paths");
         let python_version = get_python_version(&venv_paths[0])
             .expect("Failed to get Python version")
             .expect("Python version not found");
@@ -352,7 +1475,8 @@ mod tests {
 
     #[test]
     pub fn test_serialize_venv() {
-        let venv_paths = get_venv_paths().expect("Failed to get virtual environment paths");
+        let venv_paths = get_venv_paths(&ScanOptions::default())
+            .expect("Failed to get virtual environment paths");
         let venv =
             build_virtualenv(venv_paths[0].clone()).expect("Failed to build virtual environment");
         let serialized = serde_json::to_string(&venv).expect("Failed to serialize virtual env");
@@ -361,7 +1485,8 @@ mod tests {
 
     #[test]
     pub fn test_serialize_all_venvs() {
-        let venv_paths = get_venv_paths().expect("Failed to get virtual environment paths");
+        let venv_paths = get_venv_paths(&ScanOptions::default())
+            .expect("Failed to get virtual environment paths");
         let venvs: Vec<VirtualEnv> = venv_paths
             .into_iter()
             .filter_map(|path| build_virtualenv(path).ok())
@@ -376,24 +1501,448 @@ mod tests {
 
     #[test]
     pub fn test_get_size() {
-        let venv_paths = get_venv_paths().expect("Failed to get virtual environment paths");
+        let venv_paths = get_venv_paths(&ScanOptions::default())
+            .expect("Failed to get virtual environment paths");
         let size = get_dir_size(&venv_paths[0]);
 
         assert!(size > 0, "Virtual environment size is zero");
     }
 
+    #[test]
+    pub fn test_sparse_file_usage() {
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+        let file = File::create(dir.path().join("sparse.bin")).expect("Failed to create file");
+        file.set_len(100 * 1024 * 1024)
+            .expect("Failed to extend file");
+
+        let usage = get_dir_usage(dir.path());
+        assert!(usage.size < 1024 * 1024, "Sparse file counted by length");
+        assert!(usage.sparse > 99 * 1024 * 1024);
+    }
+
     #[test]
     pub fn test_build_all_virtualenvs() {
-        let venv_paths = get_venv_paths().expect("Failed to get virtual environment paths");
+        let venv_paths = get_venv_paths(&ScanOptions::default())
+            .expect("Failed to get virtual environment paths");
         let venvs = build_virtualenvs(venv_paths).expect("Failed to build virtual environments");
         assert!(!venvs.is_empty(), "No virtual environments built");
     }
 
+    #[test]
+    pub fn test_get_project_venv_paths() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let root = dir.path();
+        for venv in [
+            "app/.venv",
+            "lib/node_modules/pkg/.venv",
+            "legacy/env",
+            "legacy/env/nested/.venv",
+            "scratch/.venv",
+            "tool/build-env",
+        ] {
+            std::fs::create_dir_all(root.join(venv)).unwrap();
+            File::create(root.join(venv).join("pyvenv.cfg")).unwrap();
+        }
+        // Only environments of a project count
+        for project in ["app", "lib/node_modules/pkg", "legacy", "tool"] {
+            File::create(root.join(project).join("pyproject.toml")).unwrap();
+        }
+
+        let options = ScanOptions {
+            project_roots: vec![root.to_path_buf()],
+            excludes: vec!["legacy/".to_string()],
+            skip_known_dirs: true,
+            ..Default::default()
+        };
+        let venv_paths = get_project_venv_paths(&options).expect("Failed to scan project roots");
+        let root = canonicalize(root).unwrap();
+        assert_eq!(venv_paths, vec![root.join("app/.venv")]);
+    }
+
+    #[test]
+    pub fn test_project_max_depth() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let root = canonicalize(dir.path()).unwrap();
+        let deep = root.join("nas/2019/clients/acme/projects/site/backend/.venv");
+        std::fs::create_dir_all(&deep).unwrap();
+        File::create(deep.join("pyvenv.cfg")).unwrap();
+        File::create(deep.with_file_name("requirements.txt")).unwrap();
+
+        let mut options = ScanOptions {
+            project_roots: vec![root.clone()],
+            ..Default::default()
+        };
+        assert!(get_project_venv_paths(&options).unwrap().is_empty());
+        options.max_depth = Some(usize::MAX);
+        assert_eq!(get_project_venv_paths(&options).unwrap(), vec![deep]);
+    }
+
+    #[test]
+    pub fn test_only_skips_prefixes() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let root = canonicalize(dir.path()).unwrap();
+        // The root and `usr` look like prefixes with an interpreter, as `/` does
+        for prefix in [root.clone(), root.join("usr")] {
+            std::fs::create_dir_all(prefix.join("bin")).unwrap();
+            File::create(prefix.join("bin/python")).unwrap();
+        }
+        for venv in ["envs/old", "opt/conda"] {
+            std::fs::create_dir_all(root.join(venv).join("bin")).unwrap();
+            File::create(root.join(venv).join("bin/python")).unwrap();
+        }
+        File::create(root.join("envs/old/pyvenv.cfg")).unwrap();
+        std::fs::create_dir_all(root.join("opt/conda/conda-meta")).unwrap();
+
+        let options = ScanOptions {
+            only: Some(root.clone()),
+            max_depth: Some(usize::MAX),
+            ..Default::default()
+        };
+        let mut venv_paths = discover_venv_paths(&options).expect("Failed to scan");
+        venv_paths.sort();
+        assert_eq!(
+            venv_paths,
+            vec![root.join("envs/old"), root.join("opt/conda")]
+        );
+    }
+
+    #[test]
+    pub fn test_walk_deadline() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let root = canonicalize(dir.path()).unwrap();
+        std::fs::create_dir_all(root.join("app/.venv")).unwrap();
+        File::create(root.join("app/.venv/pyvenv.cfg")).unwrap();
+        File::create(root.join("app/requirements.txt")).unwrap();
+
+        let mut options = ScanOptions {
+            project_roots: vec![root.clone()],
+            walk_deadline: Some(std::time::Instant::now()),
+            ..Default::default()
+        };
+        assert!(get_project_venv_paths(&options).unwrap().is_empty());
+        options.walk_deadline = None;
+        assert_eq!(
+            get_project_venv_paths(&options).unwrap(),
+            vec![root.join("app/.venv")]
+        );
+    }
+
+    #[test]
+    pub fn test_project_tool_envs() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let root = canonicalize(dir.path()).unwrap();
+        for venv in [
+            "app/.tox/py38",
+            "app/.tox/py311",
+            "app/.nox/tests-3-11",
+            "app/.direnv/python-3.11.9",
+        ] {
+            std::fs::create_dir_all(root.join(venv).join("bin")).unwrap();
+            std::fs::write(root.join(venv).join("pyvenv.cfg"), "version = 3.11.9\n").unwrap();
+            File::create(root.join(venv).join("bin/python")).unwrap();
+        }
+        std::fs::create_dir_all(root.join("app/.tox/log")).unwrap();
+        std::fs::create_dir_all(root.join("app/__pypackages__/3.10/lib/requests")).unwrap();
+
+        let options = ScanOptions {
+            project_roots: vec![root.clone()],
+            ..Default::default()
+        };
+        let mut venv_paths =
+            get_project_venv_paths(&options).expect("Failed to scan project roots");
+        venv_paths.sort();
+        assert_eq!(
+            venv_paths,
+            vec![
+                root.join("app/.direnv/python-3.11.9"),
+                root.join("app/.nox/tests-3-11"),
+                root.join("app/.tox/py311"),
+                root.join("app/.tox/py38"),
+                root.join("app/__pypackages__/3.10")
+            ]
+        );
+
+        let venv = build_virtualenv(venv_paths[4].clone()).expect("Failed to build env");
+        assert_eq!(venv.manager, Manager::Pep582);
+        assert_eq!(venv.python_version, "3.10");
+        assert_eq!(venv.project, Some(root.join("app")));
+        assert!(!venv.is_broken());
+
+        let venv_paths = &venv_paths[1..];
+
+        let venv = build_virtualenv(venv_paths[1].clone()).expect("Failed to build env");
+        assert_eq!(venv.manager, Manager::Tox);
+        assert_eq!(venv.name, "py311");
+        assert_eq!(venv.project, Some(root.join("app")));
+
+        let venv = build_virtualenv(venv_paths[0].clone()).expect("Failed to build env");
+        assert_eq!(venv.manager, Manager::Nox);
+        assert!(venv
+            .to_string()
+            .starts_with(&format!("tests-3-11 - {}", venv_paths[0].display())));
+        assert!(venv.to_string().contains("[3.11.9, nox]"));
+
+        let venv =
+            build_virtualenv(root.join("app/.direnv/python-3.11.9")).expect("Failed to build env");
+        assert_eq!(venv.manager, Manager::Direnv);
+        assert_eq!(venv.project, Some(root.join("app")));
+    }
+
+    #[test]
+    pub fn test_windows_layout() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let venv = dir.path().join("proj/.venv");
+        std::fs::create_dir_all(venv.join("Scripts")).unwrap();
+        File::create(venv.join("Scripts/python.exe")).unwrap();
+        assert!(is_venv_root(&venv));
+        assert_eq!(get_interpreter_path(&venv), venv.join("Scripts/python.exe"));
+        let found = find_venv_roots(vec![dir.path().to_path_buf()], &ScanOptions::default());
+        assert_eq!(found, vec![canonicalize(&venv).unwrap()]);
+    }
+
+    #[test]
+    pub fn test_python3_only_venv() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let root = canonicalize(dir.path()).unwrap();
+        for (venv, interpreter) in [("debian", "python3"), ("minor", "python3.12")] {
+            std::fs::create_dir_all(root.join(venv).join("bin")).unwrap();
+            std::fs::create_dir_all(root.join(venv).join("lib/python3.12")).unwrap();
+            File::create(root.join(venv).join("bin").join(interpreter)).unwrap();
+            File::create(root.join(venv).join("bin/activate")).unwrap();
+        }
+        File::create(root.join("minor/bin/python3.12-config")).unwrap();
+        // A prefix like `/usr`, with no activation script
+        std::fs::create_dir_all(root.join("prefix/bin")).unwrap();
+        File::create(root.join("prefix/bin/python3")).unwrap();
+
+        let mut found = find_venv_roots(vec![root.clone()], &ScanOptions::default());
+        found.sort();
+        assert_eq!(found, vec![root.join("debian"), root.join("minor")]);
+        assert_eq!(
+            get_interpreter_path(&root.join("debian")),
+            root.join("debian/bin/python3")
+        );
+        assert_eq!(
+            get_interpreter_path(&root.join("minor")),
+            root.join("minor/bin/python3.12")
+        );
+        assert!(!build_virtualenv(root.join("debian")).unwrap().is_broken());
+    }
+
+    #[test]
+    pub fn test_find_venv_roots_by_marker() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let root = canonicalize(dir.path()).unwrap();
+        // Half-built, without an interpreter
+        std::fs::create_dir_all(root.join("half")).unwrap();
+        File::create(root.join("half/pyvenv.cfg")).unwrap();
+        // conda environment without Python
+        std::fs::create_dir_all(root.join("r-env/conda-meta")).unwrap();
+        // conda environment whose Python was partially removed
+        std::fs::create_dir_all(root.join("stale/conda-meta")).unwrap();
+        std::fs::write(
+            root.join("stale/conda-meta/history"),
+            "+defaults/linux-64::python-3.10.4-h12debd9_0\n",
+        )
+        .unwrap();
+        // Not an environment, and nothing inside environments is searched
+        std::fs::create_dir_all(root.join("notes/bin")).unwrap();
+        std::fs::create_dir_all(root.join("half/lib/nested")).unwrap();
+        File::create(root.join("half/lib/nested/pyvenv.cfg")).unwrap();
+
+        let mut found = find_venv_roots(vec![root.clone()], &ScanOptions::default());
+        found.sort();
+        assert_eq!(
+            found,
+            vec![root.join("half"), root.join("r-env"), root.join("stale")]
+        );
+
+        let venv = build_virtualenv(root.join("r-env")).expect("Failed to build env");
+        assert!(!venv.is_broken());
+        assert_eq!(venv.manager, Manager::Conda);
+
+        let venv = build_virtualenv(root.join("stale")).expect("Failed to build env");
+        assert!(!venv.is_broken());
+        assert_eq!(venv.python_version, "3.10.4");
+    }
+
+    #[test]
+    pub fn test_indexed_project_venv_paths() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let root = canonicalize(dir.path()).unwrap();
+        let indexed = vec![
+            root.join("code/app/.venv"),
+            root.join("code/app/.venv/lib/vendored"),
+            root.join("code/web/node_modules/pkg/.venv"),
+            root.join("code/legacy/.venv"),
+            root.join("code/a/b/c/d/e/f/.venv"),
+            root.join("elsewhere/.venv"),
+        ];
+        for path in &indexed {
+            std::fs::create_dir_all(path).unwrap();
+        }
+        for project in [
+            "code/app",
+            "code/web/node_modules/pkg",
+            "code/legacy",
+            "code/a/b/c/d/e/f",
+            "elsewhere",
+        ] {
+            File::create(root.join(project).join("requirements.txt")).unwrap();
+        }
+        let options = ScanOptions {
+            project_roots: vec![root.join("code")],
+            excludes: vec!["legacy/".to_string()],
+            skip_known_dirs: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            get_indexed_project_venv_paths(&options, &indexed).unwrap(),
+            vec![root.join("code/app/.venv")]
+        );
+    }
+
+    #[test]
+    pub fn test_root_depths() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let root = canonicalize(dir.path()).unwrap();
+        let deep = root.join("deep/a/b/c/venv");
+        std::fs::create_dir_all(&deep).unwrap();
+        File::create(deep.join("pyvenv.cfg")).unwrap();
+        let flat = root.join("flat/venv");
+        std::fs::create_dir_all(&flat).unwrap();
+        File::create(flat.join("pyvenv.cfg")).unwrap();
+
+        let search_paths = vec![root.join("deep"), root.join("flat")];
+        let mut options = ScanOptions::default();
+        assert_eq!(
+            find_venv_roots(search_paths.clone(), &options),
+            vec![flat.clone()]
+        );
+
+        options.root_depths = HashMap::from([(root.join("deep"), 5), (root.join("flat"), 1)]);
+        let mut found = find_venv_roots(search_paths, &options);
+        found.sort();
+        assert_eq!(found, vec![deep.clone(), flat]);
+
+        options.project_roots = vec![root.join("deep")];
+        options.root_depths = HashMap::from([(root.join("deep"), 2)]);
+        assert!(get_project_venv_paths(&options).unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_dedupe_venv_paths() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let root = canonicalize(dir.path()).unwrap();
+        let venv = root.join("opt/miniconda3/envs/sci");
+        std::fs::create_dir_all(venv.join("conda-meta")).unwrap();
+        std::os::unix::fs::symlink(root.join("opt/miniconda3"), root.join("miniconda3")).unwrap();
+
+        // Nested search paths, and one reached through a symlink
+        let found = find_venv_roots(
+            vec![
+                root.join("opt/miniconda3"),
+                root.join("opt/miniconda3/envs"),
+                root.join("miniconda3/envs"),
+            ],
+            &ScanOptions::default(),
+        );
+        assert_eq!(found, vec![venv.clone()]);
+
+        let paths = vec![root.join("miniconda3/envs/sci"), venv.clone()];
+        assert_eq!(dedupe_venv_paths(paths), vec![venv]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_broken_venv() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let venv = dir.path().join("gone");
+        std::fs::create_dir_all(venv.join("bin")).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "version = 3.9.18\n").unwrap();
+        let missing = dir.path().join("python@3.9/bin/python3.9");
+        std::os::unix::fs::symlink(&missing, venv.join("bin/python")).unwrap();
+
+        let venv = build_virtualenv(venv).expect("Failed to build broken env");
+        assert!(venv.is_broken());
+        assert_eq!(venv.missing_interpreter, Some(missing.clone()));
+        assert!(venv
+            .to_string()
+            .contains(&format!("(broken: {} is gone)", missing.display())));
+    }
+
+    #[test]
+    pub fn test_get_linked_project() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        assert_eq!(get_linked_project(dir.path()), None);
+
+        std::fs::write(dir.path().join(".project"), "/home/me/code/myproj\n").unwrap();
+        assert_eq!(
+            get_linked_project(dir.path()),
+            Some(PathBuf::from("/home/me/code/myproj"))
+        );
+    }
+
+    #[test]
+    pub fn test_get_editable_project() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let venv = dir.path().join("pypoetry/virtualenvs/proj-AbC123xY-py3.11");
+        assert_eq!(get_editable_project(&venv), None);
+
+        let site_packages = venv.join("lib/python3.11/site-packages");
+        for (dist_info, direct_url) in [
+            (
+                "requests-2.31.0.dist-info",
+                r#"{"url": "https://files.pythonhosted.org/requests.whl", "archive_info": {}}"#,
+            ),
+            (
+                "my_proj-0.1.0.dist-info",
+                r#"{"dir_info": {"editable": true}, "url": "file:///home/me/old%20code/my-proj"}"#,
+            ),
+        ] {
+            std::fs::create_dir_all(site_packages.join(dist_info)).unwrap();
+            std::fs::write(
+                site_packages.join(dist_info).join("direct_url.json"),
+                direct_url,
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            get_editable_project(&venv),
+            Some(PathBuf::from("/home/me/old code/my-proj"))
+        );
+
+        // The editable project is gone, so the poetry environment is orphaned
+        std::fs::write(venv.join("pyvenv.cfg"), "version = 3.11.9\n").unwrap();
+        let orphan = build_virtualenv(venv).expect("Failed to build orphaned env");
+        assert_eq!(orphan.manager, Manager::Poetry);
+        assert!(orphan.is_orphaned());
+        assert!(orphan
+            .to_string()
+            .contains("(orphaned: /home/me/old code/my-proj is gone)"));
+    }
+
+    #[test]
+    pub fn test_get_app_data_dirs() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let apps = dir.path().join(".var/app");
+        std::fs::create_dir_all(apps.join("com.jetbrains.PyCharm-Community/data")).unwrap();
+        std::fs::create_dir_all(apps.join("org.gnome.Calculator")).unwrap();
+
+        let data_dirs = get_app_data_dirs(&apps, "data");
+        assert_eq!(
+            data_dirs,
+            vec![apps.join("com.jetbrains.PyCharm-Community/data")]
+        );
+    }
+
     #[test]
     pub fn test_get_size_human() {
-        let venv_paths = get_venv_paths().expect("Failed to get virtual environment paths");
+        let venv_paths = get_venv_paths(&ScanOptions::default())
+            .expect("Failed to get virtual environment paths");
         let size = get_dir_size(&venv_paths[0]);
-        let size_str = human_bytes(size as f64);
+        let size_str = format_size(size);
         dbg!(&size_str);
         assert!(!size_str.is_empty(), "Human-readable size is empty");
     }
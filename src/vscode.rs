@@ -0,0 +1,199 @@
+use crate::config::expand_home;
+use crate::venvs::{is_venv_root, VirtualEnv};
+use std::fs::{canonicalize, read_to_string};
+use std::path::{Path, PathBuf};
+
+/// The settings keys naming a workspace's interpreter, current and legacy
+const INTERPRETER_KEYS: [&str; 2] = ["python.defaultInterpreterPath", "python.pythonPath"];
+
+/// An environment whose interpreter a VS Code settings file selects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorReference {
+    /// Canonical root directory of the environment
+    pub venv_root: PathBuf,
+    /// The `settings.json` naming it
+    pub settings: PathBuf,
+}
+
+/// The user-level `settings.json` of VS Code, its Insiders build and
+/// VSCodium, on Linux and macOS
+pub fn get_user_settings(home_dir: &Path) -> Vec<PathBuf> {
+    let mut settings = Vec::new();
+    for config_dir in [".config", "Library/Application Support"] {
+        for app in ["Code", "Code - Insiders", "VSCodium"] {
+            let path = home_dir
+                .join(config_dir)
+                .join(app)
+                .join("User/settings.json");
+            if path.is_file() {
+                settings.push(path);
+            }
+        }
+    }
+    settings
+}
+
+/// The environments selected by each of the `settings` files. Workspace
+/// settings (`<folder>/.vscode/settings.json`) resolve relative paths and
+/// `${workspaceFolder}` against their folder.
+pub fn get_editor_references(settings: &[PathBuf], home_dir: &Path) -> Vec<EditorReference> {
+    settings
+        .iter()
+        .filter_map(|path| {
+            let text = read_to_string(path).ok()?;
+            let workspace = path
+                .parent()
+                .filter(|dir| dir.file_name().is_some_and(|name| name == ".vscode"))
+                .and_then(Path::parent);
+            let venv_roots: Vec<PathBuf> = INTERPRETER_KEYS
+                .iter()
+                .filter_map(|key| find_string_setting(&text, key))
+                .filter_map(|value| resolve_venv_root(&value, workspace, home_dir))
+                .collect();
+            Some(venv_roots.into_iter().map(|venv_root| EditorReference {
+                venv_root,
+                settings: path.clone(),
+            }))
+        })
+        .flatten()
+        .collect()
+}
+
+/// The string value of `key` in a settings file. These allow comments and
+/// trailing commas, so they aren't parsed as JSON as a whole.
+fn find_string_setting(text: &str, key: &str) -> Option<String> {
+    let quoted_key = format!("\"{}\"", key);
+    let start = text.find(&quoted_key)? + quoted_key.len();
+    let rest = text[start..].trim_start().strip_prefix(':')?.trim_start();
+    let rest = rest.strip_prefix('"')?;
+    let mut escaped = false;
+    let end = rest.char_indices().find_map(|(i, c)| {
+        match (escaped, c) {
+            (false, '"') => return Some(i),
+            (false, '\\') => escaped = true,
+            _ => escaped = false,
+        }
+        None
+    })?;
+    serde_json::from_str(&format!("\"{}\"", &rest[..end])).ok()
+}
+
+/// The environment of an interpreter path, which may also name the
+/// environment directory itself
+fn resolve_venv_root(value: &str, workspace: Option<&Path>, home_dir: &Path) -> Option<PathBuf> {
+    let value = value.replace("${userHome}", &home_dir.to_string_lossy());
+    let value = match (value.contains("${workspaceFolder}"), workspace) {
+        (true, Some(workspace)) => {
+            value.replace("${workspaceFolder}", &workspace.to_string_lossy())
+        }
+        (true, None) => return None,
+        (false, _) => value,
+    };
+    let path = match value.strip_prefix("~/") {
+        Some(rest) => home_dir.join(rest),
+        None => expand_home(Path::new(&value)),
+    };
+    let path = match (path.is_relative(), workspace) {
+        (true, Some(workspace)) => workspace.join(path),
+        (true, None) => return None,
+        (false, _) => path,
+    };
+    // `<venv>/bin/python` or `<venv>\Scripts\python.exe`
+    let venv_root = match is_venv_root(&path) {
+        true => path,
+        false => path.parent()?.parent()?.to_path_buf(),
+    };
+    match is_venv_root(&venv_root) {
+        true => canonicalize(venv_root).ok(),
+        false => None,
+    }
+}
+
+/// Records in each environment the settings files selecting it
+pub fn mark_editor_configs(venvs: &mut [VirtualEnv], references: &[EditorReference]) {
+    for reference in references {
+        for venv in venvs.iter_mut() {
+            if venv.path == reference.venv_root
+                && !venv.editor_configs.contains(&reference.settings)
+            {
+                venv.editor_configs.push(reference.settings.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_find_string_setting() {
+        let text = r#"{
+    // Comments and trailing commas are allowed
+    "editor.formatOnSave": true,
+    "python.defaultInterpreterPath" : "${workspaceFolder}/.venv/bin/python",
+    "python.pythonPath": "C:\\envs\\legacy\\Scripts\\python.exe",
+}"#;
+        assert_eq!(
+            find_string_setting(text, "python.defaultInterpreterPath").as_deref(),
+            Some("${workspaceFolder}/.venv/bin/python")
+        );
+        assert_eq!(
+            find_string_setting(text, "python.pythonPath").as_deref(),
+            Some(r"C:\envs\legacy\Scripts\python.exe")
+        );
+        assert_eq!(find_string_setting(text, "editor.formatOnSave"), None);
+        assert_eq!(find_string_setting(text, "python.venvPath"), None);
+    }
+
+    #[test]
+    pub fn test_get_editor_references() {
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+        let home = canonicalize(dir.path()).unwrap();
+        let project = home.join("code/app");
+        let venv = project.join(".venv");
+        let shared = home.join("envs/shared");
+        for env in [&venv, &shared] {
+            std::fs::create_dir_all(env.join("bin")).unwrap();
+            std::fs::write(env.join("pyvenv.cfg"), "version = 3.11.9\n").unwrap();
+        }
+        std::fs::create_dir_all(project.join(".vscode")).unwrap();
+        let workspace_settings = project.join(".vscode/settings.json");
+        std::fs::write(
+            &workspace_settings,
+            r#"{ "python.defaultInterpreterPath": "${workspaceFolder}/.venv/bin/python" }"#,
+        )
+        .unwrap();
+        let user_settings = home.join(".config/Code/User/settings.json");
+        std::fs::create_dir_all(user_settings.parent().unwrap()).unwrap();
+        std::fs::write(
+            &user_settings,
+            r#"{ "python.defaultInterpreterPath": "~/envs/shared" }"#,
+        )
+        .unwrap();
+        assert_eq!(get_user_settings(&home), vec![user_settings.clone()]);
+
+        let references =
+            get_editor_references(&[workspace_settings.clone(), user_settings.clone()], &home);
+        assert_eq!(
+            references,
+            vec![
+                EditorReference {
+                    venv_root: venv.clone(),
+                    settings: workspace_settings.clone(),
+                },
+                EditorReference {
+                    venv_root: shared.clone(),
+                    settings: user_settings,
+                },
+            ]
+        );
+
+        let mut venvs = vec![VirtualEnv {
+            path: venv,
+            ..Default::default()
+        }];
+        mark_editor_configs(&mut venvs, &references);
+        assert_eq!(venvs[0].editor_configs, vec![workspace_settings]);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::config::Config;
+use crate::notify::Event;
+use crate::policy::get_budget_usage;
+use crate::size::format_size;
+use crate::venvs::VirtualEnv;
+use std::collections::HashMap;
+
+/// Which budgets were exceeded at the last check, keyed by budget name
+#[derive(Debug, Default)]
+pub struct BudgetState {
+    over: HashMap<String, bool>,
+}
+
+impl BudgetState {
+    /// Compares `venvs` against every budget in `config` and returns an event
+    /// for each budget that was crossed, in either direction, since the
+    /// previous call
+    pub fn update(&mut self, config: &Config, venvs: &[VirtualEnv]) -> Vec<Event> {
+        let mut checks = Vec::new();
+        if let Some(budget) = config.budget {
+            let used: u64 = venvs.iter().map(|venv| venv.venv_size).sum();
+            checks.push(("all environments".to_string(), used, budget.0));
+        }
+        for usage in get_budget_usage(&config.budgets, venvs) {
+            checks.push((
+                usage.budget.path.display().to_string(),
+                usage.used,
+                usage.budget.max.0,
+            ));
+        }
+
+        let mut events = Vec::new();
+        for (name, used, max) in checks {
+            let over = used > max;
+            let was_over = self.over.insert(name.clone(), over).unwrap_or(false);
+            let usage = format!("{} of {}", format_size(used), format_size(max));
+            match (was_over, over) {
+                (false, true) => events.push(Event::new(
+                    "budget_exceeded",
+                    format!("Budget exceeded for {}: {}", name, usage),
+                )),
+                (true, false) => events.push(Event::new(
+                    "budget_recovered",
+                    format!("Back within budget for {}: {}", name, usage),
+                )),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::size::ByteSize;
+
+    #[test]
+    pub fn test_budget_crossings() {
+        let config = Config {
+            budget: Some(ByteSize(10)),
+            ..Default::default()
+        };
+        let venvs = |venv_size| {
+            vec![VirtualEnv {
+                venv_size,
+                ..Default::default()
+            }]
+        };
+        let kinds = |events: Vec<Event>| events.iter().map(|e| e.event).collect::<Vec<_>>();
+
+        let mut state = BudgetState::default();
+        assert!(state.update(&config, &venvs(5)).is_empty());
+        assert_eq!(
+            kinds(state.update(&config, &venvs(20))),
+            ["budget_exceeded"]
+        );
+        assert!(state.update(&config, &venvs(30)).is_empty());
+        assert_eq!(
+            kinds(state.update(&config, &venvs(5))),
+            ["budget_recovered"]
+        );
+    }
+}